@@ -20,6 +20,11 @@
 //! checksum = (B << 16) | A
 //! ```
 //!
+//! Rather than reducing after every byte, [`generate`] sums blocks of
+//! 5552 bytes and reduces once per block: that is the longest run
+//! for which **B** cannot overflow a `u32`, even when every byte is
+//! `0xFF`. The result is identical to the per-byte definition above.
+//!
 //! # Example
 //!
 //! ```
@@ -30,6 +35,13 @@
 #![deny(missing_docs)]
 #![cfg_attr(not(test), no_std)]
 
+/// Modulus of both accumulators: the largest prime smaller than 2¹⁶.
+const MOD_ADLER: u32 = 65521;
+
+/// Most bytes that can be summed before the accumulators must be reduced
+/// to stay within a `u32`.
+const NMAX: usize = 5552;
+
 /// Computes the Adler-32 checksum of `data`.
 ///
 /// Returns a 32-bit unsigned integer whose upper 16 bits are **B** and
@@ -45,9 +57,38 @@
 pub fn generate(data: &[u8]) -> u32 {
     let mut a = 1u32;
     let mut b = 0u32;
-    for &byte in data {
-        a = (a + byte as u32) % 65521;
-        b = (b + a) % 65521;
+    for block in data.chunks(NMAX) {
+        for &byte in block {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+/// Computes the same checksum as [`generate`] in a `const` context, so
+/// checksums of fixed byte strings such as protocol magic values can be
+/// constants and `match` patterns.
+///
+/// Reduces after every byte instead of once per block, which makes it
+/// slower than [`generate`]; use it for compile-time inputs only.
+///
+/// # Example
+///
+/// ```
+/// const HELLO: u32 = suon_adler32::generate_const(b"hello");
+/// assert_eq!(HELLO, suon_adler32::generate(b"hello"));
+/// ```
+pub const fn generate_const(data: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+    let mut i = 0;
+    while i < data.len() {
+        a = (a + data[i] as u32) % MOD_ADLER;
+        b = (b + a) % MOD_ADLER;
+        i += 1;
     }
     (b << 16) | a
 }
@@ -56,6 +97,76 @@ pub fn generate(data: &[u8]) -> u32 {
 mod tests {
     use super::*;
 
+    /// The per-byte definition, reducing after every byte.
+    fn reference(data: &[u8]) -> u32 {
+        let mut a = 1u32;
+        let mut b = 0u32;
+        for &byte in data {
+            a = (a + byte as u32) % MOD_ADLER;
+            b = (b + a) % MOD_ADLER;
+        }
+        (b << 16) | a
+    }
+
+    /// Deterministic xorshift bytes, so failures reproduce.
+    fn pseudo_random_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunked_sum_matches_the_per_byte_definition() {
+        let lengths = [
+            0,
+            1,
+            15,
+            4096,
+            NMAX - 1,
+            NMAX,
+            NMAX + 1,
+            3 * NMAX + 7,
+            100_000,
+        ];
+        for (seed, &len) in (1u64..).zip(lengths.iter()) {
+            let data = pseudo_random_bytes(len, seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            assert_eq!(
+                generate(&data),
+                reference(&data),
+                "random buffer of {len} bytes"
+            );
+
+            // All 0xFF bytes push the accumulators closest to overflow.
+            let data = vec![0xFFu8; len];
+            assert_eq!(
+                generate(&data),
+                reference(&data),
+                "0xFF buffer of {len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn const_checksum_matches_generate() {
+        const WIKIPEDIA: u32 = generate_const(b"Wikipedia");
+        const EMPTY: u32 = generate_const(b"");
+
+        assert_eq!(WIKIPEDIA, 0x11E60398);
+        assert_eq!(EMPTY, 1);
+        assert!(matches!(generate(b"Wikipedia"), WIKIPEDIA));
+
+        for len in [1, 15, NMAX + 1, 3 * NMAX + 7] {
+            let data = pseudo_random_bytes(len, len as u64);
+            assert_eq!(generate_const(&data), generate(&data), "{len} bytes");
+        }
+    }
+
     #[test]
     fn known_wikipedia_vector() {
         assert_eq!(generate(b"Wikipedia"), 0x11E60398);
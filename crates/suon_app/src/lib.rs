@@ -23,10 +23,12 @@
 //! [`Plugin`]: plugin::Plugin
 //! [`TaskHandler`]: suon_channel::TaskHandler
 
-use suon_channel::Channel;
+use std::panic::{self, AssertUnwindSafe};
+
+use suon_channel::{Channel, TaskHandler};
 use suon_resource::{Resource, Resources};
 use system::{IntoSystem, System};
-use tracing::{debug, info};
+use tracing::{debug, error, info};
 
 use self::{plugin::Plugin, shutdown::Exit};
 
@@ -43,6 +45,7 @@ pub struct App {
     channel: Channel,
     startup_systems: Vec<Box<dyn System>>,
     shutdown_systems: Vec<Box<dyn System>>,
+    max_tasks_per_drain: usize,
 }
 
 impl App {
@@ -68,6 +71,7 @@ impl App {
             channel: Channel::default(),
             startup_systems: Vec::new(),
             shutdown_systems: Vec::new(),
+            max_tasks_per_drain: usize::MAX,
         }
     }
 
@@ -125,6 +129,14 @@ impl App {
         self
     }
 
+    /// Caps how many tasks one iteration of the task loop dispatches; the
+    /// rest stay queued for the next iteration, so the `Exit` check runs
+    /// between bursts. Unlimited by default.
+    pub fn set_max_tasks_per_drain(&mut self, max: usize) -> &mut Self {
+        self.max_tasks_per_drain = max;
+        self
+    }
+
     /// Registers a plugin, which may add resources and systems to the app.
     pub fn add_plugin(&mut self, plugin: impl Plugin) -> &mut Self {
         let name = std::any::type_name_of_val(&plugin);
@@ -168,10 +180,11 @@ impl App {
         debug!(target: "App", "Entering task dispatch loop");
         let mut buffer = Vec::new();
         loop {
-            self.channel.wait_and_drain(&mut buffer);
+            self.channel
+                .wait_and_drain_limited(&mut buffer, self.max_tasks_per_drain);
 
             for mut task in buffer.drain(..) {
-                task.run(&mut self.resources);
+                run_isolated(task.as_mut(), &mut self.resources);
             }
 
             if **self.resources.get::<Exit>() {
@@ -189,6 +202,31 @@ impl App {
     }
 }
 
+/// Runs `task`, logging a panic instead of letting it unwind out of the
+/// task loop, so one faulty handler cannot take the server down. Returns
+/// whether the task ran to completion.
+///
+/// Whatever the task changed in `resources` before panicking stays
+/// changed; the loop carries on with the next task regardless.
+///
+/// This relies on panics unwinding, which is why the workspace's
+/// release profile keeps `panic = "unwind"`. A binary built with
+/// `panic = "abort"` still terminates on the first panicking task.
+fn run_isolated(task: &mut dyn TaskHandler, resources: &mut Resources) -> bool {
+    match panic::catch_unwind(AssertUnwindSafe(|| task.run(resources))) {
+        Ok(()) => true,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("non-string panic payload");
+            error!(target: "App", "Task panicked: {message}");
+            false
+        }
+    }
+}
+
 impl Default for App {
     fn default() -> Self {
         Self::new()
@@ -489,4 +527,19 @@ mod tests {
         }
         assert_eq!(app.get_resource::<Score>().0, 99);
     }
+
+    #[test]
+    fn panicking_task_does_not_stop_the_loop() {
+        use suon_channel::IntoTask;
+
+        let mut resources = Resources::default();
+        resources.insert(Num(0));
+
+        let mut faulty = (|_: &mut Resources| panic!("faulty handler")).into_task();
+        let mut next = (|resources: &mut Resources| **resources.get_mut::<Num>() += 1).into_task();
+
+        assert!(!run_isolated(&mut faulty, &mut resources));
+        assert!(run_isolated(&mut next, &mut resources));
+        assert_eq!(**resources.get::<Num>(), 1);
+    }
 }
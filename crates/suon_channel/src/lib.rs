@@ -218,7 +218,18 @@ impl Channel {
     /// `buffer` is pre-sized according to [`pending_count`] to minimise
     /// reallocations.
     pub fn wait_and_drain(&self, buffer: &mut Vec<Box<dyn TaskHandler>>) {
-        let estimated = self.pending.load(Ordering::Relaxed);
+        self.wait_and_drain_limited(buffer, usize::MAX);
+    }
+
+    /// Like [`wait_and_drain`], but moves at most `max` tasks into
+    /// `buffer` and leaves the rest queued for the next call, so one
+    /// burst cannot hold up whatever runs between drains. A `max` of `0`
+    /// is treated as `1`.
+    ///
+    /// [`wait_and_drain`]: Channel::wait_and_drain
+    pub fn wait_and_drain_limited(&self, buffer: &mut Vec<Box<dyn TaskHandler>>, max: usize) {
+        let max = max.max(1);
+        let estimated = self.pending.load(Ordering::Relaxed).min(max);
         if estimated > buffer.capacity() {
             buffer.reserve(estimated - buffer.len());
         }
@@ -227,15 +238,16 @@ impl Channel {
         if let Ok(msg) = self.receiver.try_recv() {
             self.pending.fetch_sub(1, Ordering::Release);
             buffer.push(msg);
-            Self::drain_main(&self.receiver, &self.pending, buffer);
+            Self::drain_main(&self.receiver, &self.pending, buffer, max - 1);
             return;
         }
 
         // Slow path: block until a message or a scheduled task is ready
         loop {
             if self.has_scheduled.load(Ordering::Acquire) {
-                self.pop_ready(buffer);
-                if !buffer.is_empty() {
+                let before = buffer.len();
+                self.pop_ready(buffer, max);
+                if buffer.len() > before {
                     return;
                 }
             }
@@ -258,7 +270,7 @@ impl Channel {
                 Ok(msg) => {
                     self.pending.fetch_sub(1, Ordering::Release);
                     buffer.push(msg);
-                    Self::drain_main(&self.receiver, &self.pending, buffer);
+                    Self::drain_main(&self.receiver, &self.pending, buffer, max - 1);
                     return;
                 }
                 Err(RecvTimeoutError::Timeout) => continue,
@@ -270,23 +282,30 @@ impl Channel {
         }
     }
 
-    /// Non-blocking drain of all messages from the main channel.
+    /// Non-blocking drain of up to `max` messages from the main channel.
     fn drain_main(
         receiver: &Receiver<Box<dyn TaskHandler>>,
         pending: &AtomicUsize,
         buffer: &mut Vec<Box<dyn TaskHandler>>,
+        max: usize,
     ) {
-        while let Ok(msg) = receiver.try_recv() {
+        for _ in 0..max {
+            let Ok(msg) = receiver.try_recv() else {
+                break;
+            };
             pending.fetch_sub(1, Ordering::Release);
             buffer.push(msg);
         }
     }
 
-    /// Move all ready scheduled tasks into `buffer`.
-    fn pop_ready(&self, buffer: &mut Vec<Box<dyn TaskHandler>>) {
+    /// Move up to `max` ready scheduled tasks into `buffer`.
+    fn pop_ready(&self, buffer: &mut Vec<Box<dyn TaskHandler>>, max: usize) {
         let mut scheduled = self.scheduled.lock();
         let now = Instant::now();
-        while let Some(task) = scheduled.peek() {
+        for _ in 0..max {
+            let Some(task) = scheduled.peek() else {
+                break;
+            };
             if task.at > now {
                 break;
             }
@@ -388,6 +407,44 @@ mod tests {
         assert_eq!(buffer.len(), 1000);
     }
 
+    #[test]
+    fn limited_drain_leaves_the_rest_queued() {
+        let channel = Channel::default();
+        for _ in 0..1000 {
+            channel.send(AddOne);
+        }
+
+        let mut buffer = Vec::new();
+        for drained in 1..=4 {
+            channel.wait_and_drain_limited(&mut buffer, 250);
+            assert_eq!(buffer.len(), 250);
+            assert_eq!(channel.pending_count(), 1000 - drained * 250);
+            buffer.clear();
+        }
+    }
+
+    #[test]
+    fn limited_drain_caps_ready_scheduled_tasks() {
+        let channel = Channel::default();
+        for _ in 0..3 {
+            channel.schedule(Duration::ZERO, AddOne);
+        }
+        std::thread::sleep(Duration::from_millis(5));
+
+        // The first drain only picks up the wake signal sent for the
+        // schedule.
+        let mut buffer = Vec::new();
+        channel.wait_and_drain_limited(&mut buffer, 1);
+        buffer.clear();
+
+        for _ in 0..3 {
+            channel.wait_and_drain_limited(&mut buffer, 1);
+            assert_eq!(buffer.len(), 1);
+            buffer.clear();
+        }
+        assert!(channel.scheduled.lock().is_empty());
+    }
+
     #[test]
     fn concurrent_senders() {
         let channel = Channel::default();
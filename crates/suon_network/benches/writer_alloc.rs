@@ -0,0 +1,135 @@
+//! Allocations per `PacketWriter::send`, compared against the old
+//! "frame into a temporary `Vec`, then copy into the buffer" path.
+//!
+//! Allocation counts are printed once per case; timings go through
+//! criterion as usual.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use suon_network::{protocol::PacketWriter, server::tcp::ProtocolSettings};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const SENDS: usize = 64;
+const PAYLOAD: &[u8] = &[0xAB; 64];
+
+const CHECKSUM: ProtocolSettings = ProtocolSettings {
+    header_size: 2,
+    has_checksum: true,
+    uses_xtea: false,
+    uses_rsa: false,
+};
+
+const XTEA: ProtocolSettings = ProtocolSettings {
+    header_size: 6,
+    has_checksum: true,
+    uses_xtea: true,
+    uses_rsa: true,
+};
+
+fn writer(protocol: ProtocolSettings) -> PacketWriter {
+    PacketWriter::new(protocol, SENDS * 128).with_xtea_key([
+        0x0123_4567,
+        0x89AB_CDEF,
+        0xFEDC_BA98,
+        0x7654_3210,
+    ])
+}
+
+/// The previous framing: build the checksum frame in its own `Vec`, then
+/// copy it into the writer.
+fn send_via_temporary(writer: &mut PacketWriter, plaintext: &[u8]) {
+    let checksum = suon_adler32::generate(plaintext);
+    let size = (4 + plaintext.len()) as u16;
+    let mut framed = Vec::with_capacity(2 + 4 + plaintext.len());
+    framed.extend_from_slice(&size.to_le_bytes());
+    framed.extend_from_slice(&checksum.to_le_bytes());
+    framed.extend_from_slice(plaintext);
+    writer.send_raw(&framed);
+}
+
+fn allocations_per_send(mut send: impl FnMut()) -> f64 {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..SENDS {
+        send();
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / SENDS as f64
+}
+
+fn send_allocations(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("send_allocations");
+
+    let mut temporary = writer(CHECKSUM);
+    let mut checksum = writer(CHECKSUM);
+    let mut xtea = writer(XTEA);
+    println!(
+        "allocations per send: temporary={} checksum={} xtea={}",
+        allocations_per_send(|| send_via_temporary(&mut temporary, PAYLOAD)),
+        allocations_per_send(|| checksum.send(PAYLOAD)),
+        allocations_per_send(|| xtea.send(PAYLOAD)),
+    );
+
+    group.bench_function("temporary", |bencher| {
+        let mut writer = writer(CHECKSUM);
+        bencher.iter(|| {
+            for _ in 0..SENDS {
+                send_via_temporary(&mut writer, black_box(PAYLOAD));
+            }
+            drop(writer.take_buffer());
+        });
+    });
+
+    group.bench_function("checksum", |bencher| {
+        let mut writer = writer(CHECKSUM);
+        bencher.iter(|| {
+            for _ in 0..SENDS {
+                writer.send(black_box(PAYLOAD));
+            }
+            drop(writer.take_buffer());
+        });
+    });
+
+    group.bench_function("xtea", |bencher| {
+        let mut writer = writer(XTEA);
+        bencher.iter(|| {
+            for _ in 0..SENDS {
+                writer.send(black_box(PAYLOAD));
+            }
+            drop(writer.take_buffer());
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    name = writer_alloc;
+    config = Criterion::default();
+    targets = send_allocations
+);
+criterion_main!(writer_alloc);
@@ -0,0 +1,26 @@
+use std::fmt;
+
+/// Whether game logic has accepted a connection's credentials.
+///
+/// Every connection starts out [`Unauthenticated`](AuthState::Unauthenticated)
+/// and stays so until [`ConnectionHandle::authenticate`] is called after
+/// the login has been validated. On ports with `require_authentication`
+/// set, packets arriving between the end of the handshake and that call
+/// are dropped by the reader session.
+///
+/// [`ConnectionHandle::authenticate`]: crate::connection::ConnectionHandle::authenticate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AuthState {
+    #[default]
+    Unauthenticated,
+    Authenticated,
+}
+
+impl fmt::Display for AuthState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuthState::Unauthenticated => "unauthenticated",
+            AuthState::Authenticated => "authenticated",
+        })
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use parking_lot::Mutex;
+
+/// Cap on the bytes buffered for sending across all TCP connections.
+///
+/// Per-connection limits alone do not bound the process: thousands of
+/// clients that stop reading can each hold a buffer near its maximum.
+/// Every packet is charged to this shared counter when it is queued on
+/// a connection handle and given back once its writer session has
+/// written it. Once half the budget is in use, sessions write out after
+/// every packet instead of waiting for their flush threshold, and a
+/// packet that would take the total past the cap is refused when it is
+/// queued.
+///
+/// Sized by `max_total_buffer_bytes` in the network settings; `0` (the
+/// default) leaves the total unbounded.
+#[derive(Clone, Default)]
+pub struct BufferBudget {
+    inner: Option<Arc<Budget>>,
+}
+
+struct Budget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl BufferBudget {
+    /// A budget of `limit` bytes, or an unbounded one for `0`.
+    pub fn new(limit: usize) -> Self {
+        BufferBudget {
+            inner: (limit > 0).then(|| {
+                Arc::new(Budget {
+                    limit,
+                    used: AtomicUsize::new(0),
+                })
+            }),
+        }
+    }
+
+    /// The cap in bytes, or `None` when unbounded.
+    pub fn limit(&self) -> Option<usize> {
+        self.inner.as_ref().map(|budget| budget.limit)
+    }
+
+    /// Bytes currently buffered across all connections. Always `0` for
+    /// an unbounded budget, which does not keep count.
+    pub fn used(&self) -> usize {
+        self.inner
+            .as_ref()
+            .map_or(0, |budget| budget.used.load(Ordering::Acquire))
+    }
+
+    /// Whether `bytes` more still fit under the cap.
+    ///
+    /// Connections check and charge separately, so concurrent senders
+    /// can overshoot the cap by at most one packet each.
+    pub fn has_room_for(&self, bytes: usize) -> bool {
+        self.inner.as_ref().is_none_or(|budget| {
+            budget.used.load(Ordering::Acquire).saturating_add(bytes) <= budget.limit
+        })
+    }
+
+    /// Whether at least half the budget is in use, at which point
+    /// sessions stop holding packets back.
+    pub fn is_under_pressure(&self) -> bool {
+        self.inner
+            .as_ref()
+            .is_some_and(|budget| budget.used.load(Ordering::Acquire) >= budget.limit / 2)
+    }
+
+    pub(crate) fn charge(&self, bytes: usize) {
+        if let Some(budget) = &self.inner {
+            budget.used.fetch_add(bytes, Ordering::AcqRel);
+        }
+    }
+
+    pub(crate) fn release(&self, bytes: usize) {
+        if let Some(budget) = &self.inner {
+            budget.used.fetch_sub(bytes, Ordering::AcqRel);
+        }
+    }
+}
+
+impl fmt::Debug for BufferBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferBudget")
+            .field("limit", &self.limit())
+            .field("used", &self.used())
+            .finish()
+    }
+}
+
+/// One connection's share of a [`BufferBudget`]: the payload bytes its
+/// handles have queued that are not written to the socket yet.
+///
+/// Shared by every clone of the connection's handle, which charge
+/// packets as they are queued, and by its writer session, which gives
+/// them back once written. When the session ends the whole share is
+/// given back, including packets still queued, and nothing more is
+/// charged.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BudgetCharge {
+    budget: BufferBudget,
+    share: Arc<Mutex<Share>>,
+}
+
+#[derive(Debug, Default)]
+struct Share {
+    charged: usize,
+    closed: bool,
+}
+
+impl BudgetCharge {
+    pub fn new(budget: BufferBudget) -> Self {
+        BudgetCharge {
+            budget,
+            share: Arc::default(),
+        }
+    }
+
+    pub fn budget(&self) -> &BufferBudget {
+        &self.budget
+    }
+
+    /// Charges `bytes` if they still fit under the cap. Once the
+    /// connection is closed nothing is charged, and queueing fails on
+    /// its own.
+    pub fn try_charge(&self, bytes: usize) -> bool {
+        if self.budget.limit().is_none() {
+            return true;
+        }
+        let mut share = self.share.lock();
+        if share.closed {
+            return true;
+        }
+        if !self.budget.has_room_for(bytes) {
+            return false;
+        }
+        self.budget.charge(bytes);
+        share.charged += bytes;
+        true
+    }
+
+    /// Gives back `bytes` charged with [`try_charge`](Self::try_charge).
+    pub fn release(&self, bytes: usize) {
+        if self.budget.limit().is_none() {
+            return;
+        }
+        let mut share = self.share.lock();
+        let bytes = bytes.min(share.charged);
+        self.budget.release(bytes);
+        share.charged -= bytes;
+    }
+
+    /// Gives the whole share back and stops charging.
+    fn close(&self) {
+        let mut share = self.share.lock();
+        share.closed = true;
+        self.budget.release(share.charged);
+        share.charged = 0;
+    }
+}
+
+/// The writer session's side of a [`BudgetCharge`]: the charged bytes it
+/// has framed into its buffer but not written yet. Closes the charge
+/// when dropped, so a session gives everything back however it ends.
+#[derive(Debug, Default)]
+pub(crate) struct BufferedCharge {
+    charge: BudgetCharge,
+    framed: usize,
+}
+
+impl BufferedCharge {
+    pub fn new(charge: BudgetCharge) -> Self {
+        BufferedCharge { charge, framed: 0 }
+    }
+
+    pub fn budget(&self) -> &BufferBudget {
+        self.charge.budget()
+    }
+
+    /// `bytes` of charged payload now sit in the session's buffer.
+    pub fn framed(&mut self, bytes: usize) {
+        self.framed += bytes;
+    }
+
+    /// `bytes` of charged payload were dropped instead of buffered.
+    pub fn dropped(&self, bytes: usize) {
+        self.charge.release(bytes);
+    }
+
+    /// Everything framed so far has been written to the socket.
+    pub fn written(&mut self) {
+        self.charge.release(self.framed);
+        self.framed = 0;
+    }
+}
+
+impl Drop for BufferedCharge {
+    fn drop(&mut self) {
+        self.charge.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unbounded_budget_always_has_room() {
+        let budget = BufferBudget::new(0);
+        budget.charge(usize::MAX / 2);
+        assert!(BudgetCharge::new(budget.clone()).try_charge(usize::MAX));
+
+        assert_eq!(budget.limit(), None);
+        assert!(budget.has_room_for(usize::MAX / 2));
+        assert!(!budget.is_under_pressure());
+    }
+
+    #[test]
+    fn charges_are_given_back_as_written_and_when_the_session_ends() {
+        let budget = BufferBudget::new(100);
+        let a = BudgetCharge::new(budget.clone());
+        let b = BudgetCharge::new(budget.clone());
+        let mut session = BufferedCharge::new(a.clone());
+
+        assert!(a.try_charge(40));
+        assert!(b.try_charge(30));
+        assert_eq!(budget.used(), 70);
+        assert!(budget.is_under_pressure());
+        assert!(!a.try_charge(31));
+
+        session.framed(30);
+        session.written();
+        assert_eq!(budget.used(), 40);
+
+        // Queued but never written: given back when the session ends,
+        // and nothing is charged afterwards.
+        drop(session);
+        assert_eq!(budget.used(), 30);
+        assert!(a.try_charge(50));
+        a.release(50);
+        assert_eq!(budget.used(), 30);
+
+        b.release(30);
+        assert_eq!(budget.used(), 0);
+    }
+}
@@ -0,0 +1,125 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+/// Opcode opening a status (server info) query.
+pub const STATUS_OPCODE: u8 = 0xFF;
+
+/// Opcode opening a login (character list) request.
+pub const LOGIN_OPCODE: u8 = 0x01;
+
+/// Opcode opening a game session.
+pub const GAME_OPCODE: u8 = 0x0A;
+
+/// What a client is, resolved from the opcode of its first packet.
+///
+/// Every connection on a port speaks the same framing, but a status
+/// query, a character-list request and a game session are handled by
+/// different Lua code. The kind is resolved once and passed along with
+/// every later `RawPacketEvent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientKind {
+    Status,
+    Login,
+    Game,
+    /// The first packet did not start with a known opcode.
+    Unknown,
+}
+
+impl ClientKind {
+    /// Resolves the kind from the first decoded packet of a connection.
+    pub fn from_first_packet(payload: &[u8]) -> Self {
+        match payload.first() {
+            Some(&STATUS_OPCODE) => ClientKind::Status,
+            Some(&LOGIN_OPCODE) => ClientKind::Login,
+            Some(&GAME_OPCODE) => ClientKind::Game,
+            _ => ClientKind::Unknown,
+        }
+    }
+
+    /// Like [`from_first_packet`](Self::from_first_packet), but looks the
+    /// opcode up in `extra` first, so a port can accept other opcodes as
+    /// the opening packet of a stage.
+    pub fn from_first_packet_with(payload: &[u8], extra: &BTreeMap<u8, ClientKind>) -> Self {
+        payload
+            .first()
+            .and_then(|opcode| extra.get(opcode))
+            .copied()
+            .unwrap_or_else(|| Self::from_first_packet(payload))
+    }
+
+    /// Short machine-readable name, passed to Lua event handlers.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClientKind::Status => "status",
+            ClientKind::Login => "login",
+            ClientKind::Game => "game",
+            ClientKind::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for ClientKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_opcode_selects_the_kind() {
+        assert_eq!(
+            ClientKind::from_first_packet(&[STATUS_OPCODE, b'i']),
+            ClientKind::Status
+        );
+        assert_eq!(
+            ClientKind::from_first_packet(&[LOGIN_OPCODE]),
+            ClientKind::Login
+        );
+        assert_eq!(
+            ClientKind::from_first_packet(&[GAME_OPCODE, 0x02]),
+            ClientKind::Game
+        );
+        assert_eq!(ClientKind::from_first_packet(&[0x64]), ClientKind::Unknown);
+        assert_eq!(ClientKind::from_first_packet(&[]), ClientKind::Unknown);
+    }
+
+    #[test]
+    fn extra_opcodes_resolve_before_the_built_ins() {
+        let extra = BTreeMap::from([(0x14, ClientKind::Login), (GAME_OPCODE, ClientKind::Login)]);
+
+        assert_eq!(
+            ClientKind::from_first_packet_with(&[0x14], &extra),
+            ClientKind::Login
+        );
+        assert_eq!(
+            ClientKind::from_first_packet_with(&[GAME_OPCODE], &extra),
+            ClientKind::Login
+        );
+        assert_eq!(
+            ClientKind::from_first_packet_with(&[STATUS_OPCODE], &extra),
+            ClientKind::Status
+        );
+        assert_eq!(
+            ClientKind::from_first_packet_with(&[0x64], &extra),
+            ClientKind::Unknown
+        );
+    }
+
+    #[test]
+    fn as_str_matches_serialized_name() {
+        for kind in [
+            ClientKind::Status,
+            ClientKind::Login,
+            ClientKind::Game,
+            ClientKind::Unknown,
+        ] {
+            let json = serde_json::to_string(&kind).expect("failed to serialize client kind");
+            assert_eq!(json, format!("\"{kind}\""));
+        }
+    }
+}
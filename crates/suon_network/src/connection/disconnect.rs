@@ -0,0 +1,154 @@
+use std::{fmt, io};
+
+use crate::protocol::reader::ProcessError;
+
+/// Why a connection's reader session stopped.
+///
+/// Carried by the `ConnectionEnd` task so that logging and Lua
+/// `ConnectionEndEvent` handlers can tell a clean close apart from a
+/// misbehaving client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The peer closed the socket.
+    Closed,
+    /// The socket timed out while waiting for data.
+    Timeout,
+    /// The socket failed with an I/O error.
+    Io(io::ErrorKind),
+    /// The peer sent data that could not be processed.
+    Protocol(String),
+    /// The peer sent data that could not be decrypted, such as XTEA
+    /// packets under the wrong key or before the key exchange.
+    Decryption(String),
+    /// The server is shutting down.
+    Shutdown,
+    /// The reader session exited without ending the connection itself,
+    /// which was then reaped.
+    SessionLost,
+}
+
+impl DisconnectReason {
+    /// Short machine-readable name, passed to Lua event handlers.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisconnectReason::Closed => "closed",
+            DisconnectReason::Timeout => "timeout",
+            DisconnectReason::Io(_) => "io_error",
+            DisconnectReason::Protocol(_) => "protocol_error",
+            DisconnectReason::Decryption(_) => "decryption_error",
+            DisconnectReason::Shutdown => "shutdown",
+            DisconnectReason::SessionLost => "session_lost",
+        }
+    }
+}
+
+impl From<&io::Error> for DisconnectReason {
+    fn from(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => DisconnectReason::Closed,
+            io::ErrorKind::TimedOut => DisconnectReason::Timeout,
+            kind => DisconnectReason::Io(kind),
+        }
+    }
+}
+
+impl From<&ProcessError> for DisconnectReason {
+    fn from(error: &ProcessError) -> Self {
+        match error {
+            ProcessError::RsaError
+            | ProcessError::XteaError
+            | ProcessError::Xtea(_)
+            | ProcessError::InvalidPadding { .. } => {
+                DisconnectReason::Decryption(error.to_string())
+            }
+            _ => DisconnectReason::Protocol(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisconnectReason::Closed => write!(f, "closed by peer"),
+            DisconnectReason::Timeout => write!(f, "timed out"),
+            DisconnectReason::Io(kind) => write!(f, "I/O error: {kind}"),
+            DisconnectReason::Protocol(detail) => write!(f, "protocol error: {detail}"),
+            DisconnectReason::Decryption(detail) => write!(f, "decryption error: {detail}"),
+            DisconnectReason::Shutdown => write!(f, "server shutdown"),
+            DisconnectReason::SessionLost => write!(f, "reader session lost"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timed_out_io_error_maps_to_timeout() {
+        let error = io::Error::from(io::ErrorKind::TimedOut);
+        assert_eq!(DisconnectReason::from(&error), DisconnectReason::Timeout);
+    }
+
+    #[test]
+    fn unexpected_eof_maps_to_closed() {
+        let error = io::Error::from(io::ErrorKind::UnexpectedEof);
+        assert_eq!(DisconnectReason::from(&error), DisconnectReason::Closed);
+    }
+
+    #[test]
+    fn other_io_errors_keep_their_kind() {
+        let error = io::Error::from(io::ErrorKind::ConnectionReset);
+        assert_eq!(
+            DisconnectReason::from(&error),
+            DisconnectReason::Io(io::ErrorKind::ConnectionReset)
+        );
+    }
+
+    #[test]
+    fn decryption_failures_are_told_apart_from_protocol_errors() {
+        assert!(matches!(
+            DisconnectReason::from(&ProcessError::XteaError),
+            DisconnectReason::Decryption(_)
+        ));
+        assert!(matches!(
+            DisconnectReason::from(&ProcessError::InvalidPadding { padding: 9 }),
+            DisconnectReason::Decryption(_)
+        ));
+        assert_eq!(
+            DisconnectReason::from(&ProcessError::InvalidSize),
+            DisconnectReason::Protocol("invalid packet size".into())
+        );
+    }
+
+    #[test]
+    fn as_str_names() {
+        assert_eq!(DisconnectReason::Closed.as_str(), "closed");
+        assert_eq!(DisconnectReason::Timeout.as_str(), "timeout");
+        assert_eq!(
+            DisconnectReason::Io(io::ErrorKind::BrokenPipe).as_str(),
+            "io_error"
+        );
+        assert_eq!(
+            DisconnectReason::Protocol("bad".into()).as_str(),
+            "protocol_error"
+        );
+        assert_eq!(
+            DisconnectReason::Decryption("bad".into()).as_str(),
+            "decryption_error"
+        );
+        assert_eq!(DisconnectReason::Shutdown.as_str(), "shutdown");
+        assert_eq!(DisconnectReason::SessionLost.as_str(), "session_lost");
+    }
+
+    #[test]
+    fn display_protocol_includes_detail() {
+        let reason = DisconnectReason::Protocol("invalid packet size".into());
+        assert_eq!(reason.to_string(), "protocol error: invalid packet size");
+    }
+
+    #[test]
+    fn display_timeout() {
+        assert_eq!(DisconnectReason::Timeout.to_string(), "timed out");
+    }
+}
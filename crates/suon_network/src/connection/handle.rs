@@ -1,23 +1,99 @@
-use std::net::SocketAddr;
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use tokio::sync::{Notify, oneshot, watch};
 use tracing::trace;
 
 use crossbeam_channel::TrySendError;
+use parking_lot::Mutex;
 
 use crate::{
-    connection::id::ConnectionId,
+    connection::{
+        auth_state::AuthState, budget::BudgetCharge, client_kind::ClientKind, id::ConnectionId,
+    },
     protocol::command::{Command, CommandSender},
+    server::tcp::version_has_checksum,
 };
 
+/// Why a packet queued with [`ConnectionHandle::send_flushed`] was not
+/// written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum WriteError {
+    #[error("command queue is full")]
+    Full,
+    #[error("packet does not fit the outgoing buffer or the total buffer budget")]
+    Rejected,
+    #[error("connection closed before the packet was written")]
+    Closed,
+}
+
+/// Sends commands to one connection's writer session.
+///
+/// Every method only queues a command on the connection's channel, so a
+/// handle works from any thread: clone it out of
+/// [`Connections`](crate::connections::Connections) and move it into a
+/// worker, such as an admin endpoint, to send without going through the
+/// task loop. Queueing fails once the connection has closed.
 #[derive(Clone)]
 pub struct ConnectionHandle {
     id: ConnectionId,
     addr: SocketAddr,
     sender: CommandSender,
+    client_kind: Arc<OnceLock<ClientKind>>,
+    protocol_version: Arc<OnceLock<u16>>,
+    handshake_complete: Arc<AtomicBool>,
+    authenticated: Arc<AtomicBool>,
+    size_limits: Arc<watch::Sender<Option<SizeLimits>>>,
+    reader_alive: Arc<AtomicBool>,
+    xtea_key: Arc<Mutex<Option<[u32; 4]>>>,
+    budget: BudgetCharge,
+    writer_wake: Arc<Notify>,
 }
 
+/// Per-opcode payload size limits, shared with the reader session.
+pub(crate) type SizeLimits = Arc<BTreeMap<u8, usize>>;
+
 impl ConnectionHandle {
     pub fn new(id: ConnectionId, addr: SocketAddr, sender: CommandSender) -> Self {
-        Self { id, addr, sender }
+        Self {
+            id,
+            addr,
+            sender,
+            client_kind: Arc::default(),
+            protocol_version: Arc::default(),
+            handshake_complete: Arc::default(),
+            authenticated: Arc::default(),
+            size_limits: Arc::new(watch::Sender::new(None)),
+            reader_alive: Arc::new(AtomicBool::new(true)),
+            xtea_key: Arc::default(),
+            budget: BudgetCharge::default(),
+            writer_wake: Arc::default(),
+        }
+    }
+
+    /// Charges the packets queued on this handle and its clones to
+    /// `budget`.
+    pub(crate) fn with_budget_charge(mut self, budget: BudgetCharge) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// The connection's share of the total buffer budget, given back by
+    /// its writer session as packets are written.
+    pub(crate) fn budget_charge(&self) -> &BudgetCharge {
+        &self.budget
+    }
+
+    /// Signalled whenever a command is queued on this handle or its
+    /// clones, so the writer session picks it up right away instead of
+    /// on its next flush tick.
+    pub(crate) fn writer_wake(&self) -> &Arc<Notify> {
+        &self.writer_wake
     }
 
     pub fn id(&self) -> ConnectionId {
@@ -28,6 +104,64 @@ impl ConnectionHandle {
         self.addr
     }
 
+    /// The kind resolved from the client's first packet, or `None` until
+    /// that packet has been read. Shared by every clone of the handle.
+    pub fn client_kind(&self) -> Option<ClientKind> {
+        self.client_kind.get().copied()
+    }
+
+    /// Records the resolved kind; later calls keep the first value.
+    pub(crate) fn resolve_client_kind(&self, kind: ClientKind) {
+        self.client_kind.get_or_init(|| kind);
+    }
+
+    /// The protocol version the client announced, once set with
+    /// [`set_protocol_version`](Self::set_protocol_version).
+    pub fn protocol_version(&self) -> Option<u16> {
+        self.protocol_version.get().copied()
+    }
+
+    /// Records the client's protocol version and switches the checksum
+    /// mode to match it: clients older than 8.40 neither send nor expect
+    /// a checksum. Later calls keep the first version.
+    pub fn set_protocol_version(&self, version: u16) -> Result<(), TrySendError<Command>> {
+        let version = *self.protocol_version.get_or_init(|| version);
+        trace!(target: "Connection",
+            "Connection {} set_protocol_version({version}) to {}",
+            self.id, self.addr
+        );
+        self.set_checksum_enabled(version_has_checksum(version))
+    }
+
+    /// Replaces the per-opcode payload size limits of this connection,
+    /// overriding the port's `packet_size_limits` setting. The reader
+    /// session applies them from the next packet it reads, so limits can
+    /// be tightened on a live connection without reconnecting.
+    pub fn set_packet_size_limits(&self, limits: BTreeMap<u8, usize>) {
+        trace!(target: "Connection",
+            "Connection {} set_packet_size_limits({limits:?}) to {}",
+            self.id, self.addr
+        );
+        self.size_limits.send_replace(Some(Arc::new(limits)));
+    }
+
+    /// Watches the limits set with
+    /// [`set_packet_size_limits`](Self::set_packet_size_limits); `None`
+    /// until they are first set.
+    pub(crate) fn watch_packet_size_limits(&self) -> watch::Receiver<Option<SizeLimits>> {
+        self.size_limits.subscribe()
+    }
+
+    /// Queues `data` to be framed and sent.
+    ///
+    /// Sends reach the wire in the order they were queued, including
+    /// [`send_raw`](Self::send_raw) calls and across size- or
+    /// threshold-triggered flushes. When several threads share clones of
+    /// one handle, each thread's own packets keep their relative order,
+    /// interleaved in whatever order the calls were queued.
+    ///
+    /// Fails with [`TrySendError::Full`] when the command queue is full
+    /// or the packet would take the total buffer budget past its cap.
     pub fn send(&self, data: Vec<u8>) -> Result<(), TrySendError<Command>> {
         trace!(target: "Connection",
             "Connection {} send {} bytes to {}",
@@ -35,7 +169,28 @@ impl ConnectionHandle {
             data.len(),
             self.addr
         );
-        self.sender.try_send(Command::Send(data))
+        self.queue_charged(data.len(), Command::Send(data))
+    }
+
+    /// Queues `packets` as one group: they are framed back to back and
+    /// reach the wire in a single flush, with nothing queued by other
+    /// callers in between. The group is sent whole or not at all, so a
+    /// packet too large for a frame drops every packet in it. Returns the
+    /// total payload bytes queued.
+    pub fn send_batch(
+        &self,
+        packets: impl IntoIterator<Item = Vec<u8>>,
+    ) -> Result<usize, TrySendError<Command>> {
+        let packets: Vec<Vec<u8>> = packets.into_iter().collect();
+        let total = packets.iter().map(Vec::len).sum();
+        trace!(target: "Connection",
+            "Connection {} send_batch {} packets ({total} bytes) to {}",
+            self.id,
+            packets.len(),
+            self.addr
+        );
+        self.queue_charged(total, Command::SendBatch(packets))?;
+        Ok(total)
     }
 
     pub fn send_raw(&self, data: Vec<u8>) -> Result<(), TrySendError<Command>> {
@@ -45,12 +200,62 @@ impl ConnectionHandle {
             data.len(),
             self.addr
         );
-        self.sender.try_send(Command::SendRaw(data))
+        self.queue_charged(data.len(), Command::SendRaw(data))
     }
 
+    /// Sets the XTEA key of the connection. Key negotiation is the last
+    /// handshake step, so this also completes the handshake.
     pub fn set_xtea_key(&self, key: [u32; 4]) -> Result<(), TrySendError<Command>> {
         trace!(target: "Connection", "Connection {} set_xtea_key to {}", self.id, self.addr);
-        self.sender.try_send(Command::SetXteaKey(key))
+        *self.xtea_key.lock() = Some(key);
+        self.complete_handshake();
+        self.queue(Command::SetXteaKey(key))
+    }
+
+    /// The XTEA key last set with [`set_xtea_key`](Self::set_xtea_key).
+    pub(crate) fn xtea_key(&self) -> Option<[u32; 4]> {
+        *self.xtea_key.lock()
+    }
+
+    /// Marks the handshake as done, lifting the port's
+    /// `handshake_timeout` from this connection.
+    pub fn complete_handshake(&self) {
+        self.handshake_complete.store(true, Ordering::Release);
+    }
+
+    /// Whether [`complete_handshake`](Self::complete_handshake) has been
+    /// called.
+    pub fn is_handshake_complete(&self) -> bool {
+        self.handshake_complete.load(Ordering::Acquire)
+    }
+
+    /// Marks the connection as logged in, once game logic has validated
+    /// its credentials. On ports with `require_authentication`, packets
+    /// are only passed on past the handshake after this call.
+    pub fn authenticate(&self) {
+        trace!(target: "Connection", "Connection {} authenticated from {}", self.id, self.addr);
+        self.authenticated.store(true, Ordering::Release);
+    }
+
+    /// Whether [`authenticate`](Self::authenticate) has been called.
+    pub fn auth_state(&self) -> AuthState {
+        if self.authenticated.load(Ordering::Acquire) {
+            AuthState::Authenticated
+        } else {
+            AuthState::Unauthenticated
+        }
+    }
+
+    /// Whether the connection's reader session is still running. It
+    /// starts out `true` so a connection is not taken for dead between
+    /// being registered and its sessions being spawned.
+    pub fn is_reader_alive(&self) -> bool {
+        self.reader_alive.load(Ordering::Acquire)
+    }
+
+    /// Held by the reader session for as long as it runs.
+    pub(crate) fn reader_liveness(&self) -> LivenessGuard {
+        LivenessGuard(self.reader_alive.clone())
     }
 
     pub fn set_encryption_enabled(&self, enabled: bool) -> Result<(), TrySendError<Command>> {
@@ -58,7 +263,19 @@ impl ConnectionHandle {
             "Connection {} set_encryption_enabled({enabled}) to {}",
             self.id, self.addr
         );
-        self.sender.try_send(Command::SetEncryptionEnabled(enabled))
+        self.queue(Command::SetEncryptionEnabled(enabled))
+    }
+
+    /// Switches the connection's checksum mode. The writer applies it to
+    /// every packet queued after this call, and the reader to every packet
+    /// read once the writer has picked the command up, so both directions
+    /// always agree on the negotiated mode.
+    pub fn set_checksum_enabled(&self, enabled: bool) -> Result<(), TrySendError<Command>> {
+        trace!(target: "Connection",
+            "Connection {} set_checksum_enabled({enabled}) to {}",
+            self.id, self.addr
+        );
+        self.queue(Command::SetChecksumEnabled(enabled))
     }
 
     pub fn set_compression_threshold(&self, threshold: usize) -> Result<(), TrySendError<Command>> {
@@ -66,8 +283,7 @@ impl ConnectionHandle {
             "Connection {} set_compression_threshold({threshold}) to {}",
             self.id, self.addr
         );
-        self.sender
-            .try_send(Command::SetCompressionThreshold(threshold))
+        self.queue(Command::SetCompressionThreshold(threshold))
     }
 
     pub fn close_with_reason(&self, reason: String) -> Result<(), TrySendError<Command>> {
@@ -75,12 +291,111 @@ impl ConnectionHandle {
             "Connection {} close_with_reason({reason}) to {}",
             self.id, self.addr
         );
-        self.sender.try_send(Command::CloseWithReason(reason))
+        self.queue(Command::CloseWithReason(reason))
+    }
+
+    /// Queues `data` like [`send`](Self::send), but flushes it straight
+    /// away and returns a future that resolves once it has been written
+    /// to the socket, for flows that must know a packet went out before
+    /// carrying on.
+    pub fn send_flushed(
+        &self,
+        data: Vec<u8>,
+    ) -> impl Future<Output = Result<(), WriteError>> + use<> {
+        trace!(target: "Connection",
+            "Connection {} send_flushed {} bytes to {}",
+            self.id,
+            data.len(),
+            self.addr
+        );
+        let (ack, written) = oneshot::channel();
+        let len = data.len();
+        let queued = if self.budget.try_charge(len) {
+            self.queue(Command::SendFlushed(data, ack)).map_err(|e| {
+                self.budget.release(len);
+                match e {
+                    TrySendError::Full(_) => WriteError::Full,
+                    TrySendError::Disconnected(_) => WriteError::Closed,
+                }
+            })
+        } else {
+            Err(WriteError::Rejected)
+        };
+
+        async move {
+            queued?;
+            written.await.unwrap_or(Err(WriteError::Closed))
+        }
+    }
+
+    /// Pushes buffered packets to the socket now. Needed with
+    /// [`FlushPolicy::Manual`](crate::server::tcp::FlushPolicy::Manual),
+    /// harmless with the other policies.
+    pub fn flush(&self) -> Result<(), TrySendError<Command>> {
+        trace!(target: "Connection", "Connection {} flush to {}", self.id, self.addr);
+        self.queue(Command::Flush)
     }
 
     pub fn close(&self) -> Result<(), TrySendError<Command>> {
         trace!(target: "Connection", "Connection {} close to {}", self.id, self.addr);
-        self.sender.try_send(Command::Close)
+        self.queue(Command::Close)
+    }
+
+    /// Queues `data` as a final packet and closes the connection once it
+    /// has been written and flushed, for notices sent right before a
+    /// disconnect (failed login, kick). Queued as one command, so there
+    /// is no window in which the packet is queued but the close is not.
+    /// When the packet does not fit the total buffer budget, the
+    /// connection is closed without it.
+    pub fn send_and_close(&self, data: Vec<u8>) -> Result<(), TrySendError<Command>> {
+        trace!(target: "Connection",
+            "Connection {} send_and_close {} bytes to {}",
+            self.id,
+            data.len(),
+            self.addr
+        );
+        if !self.budget.try_charge(data.len()) {
+            trace!(target: "Connection",
+                "Connection {} final packet dropped: total buffer budget exhausted",
+                self.id
+            );
+            return self.close();
+        }
+        let len = data.len();
+        self.queue(Command::SendAndClose(data))
+            .inspect_err(|_| self.budget.release(len))
+    }
+
+    /// Queues `command` and wakes the writer session to handle it.
+    fn queue(&self, command: Command) -> Result<(), TrySendError<Command>> {
+        self.sender.try_send(command)?;
+        self.writer_wake.notify_one();
+        Ok(())
+    }
+
+    /// Charges `bytes` to the buffer budget and queues `command`, giving
+    /// the charge back if it is refused.
+    fn queue_charged(&self, bytes: usize, command: Command) -> Result<(), TrySendError<Command>> {
+        if !self.budget.try_charge(bytes) {
+            trace!(target: "Connection",
+                "Connection {} refused {bytes} bytes: total buffer budget exhausted",
+                self.id
+            );
+            return Err(TrySendError::Full(command));
+        }
+        self.queue(command)
+            .inspect_err(|_| self.budget.release(bytes))
+    }
+}
+
+/// Clears a session's liveness flag when dropped, so the flag goes down
+/// however the session ends, including when its task panics or is
+/// cancelled before the session's own cleanup runs.
+pub(crate) struct LivenessGuard(Arc<AtomicBool>);
+
+impl Drop for LivenessGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
     }
 }
 
@@ -114,6 +429,23 @@ mod tests {
         assert!(matches!(cmd, Command::Send(data) if data == vec![1, 2, 3]));
     }
 
+    #[test]
+    fn handle_send_batch_queues_one_command() {
+        let (sender, receiver) = crossbeam_channel::bounded(16);
+        let handle = ConnectionHandle::new(test_id(), test_addr(), sender);
+
+        let total = handle
+            .send_batch([vec![1, 2, 3], vec![4, 5]])
+            .expect("failed to send batch command in test");
+
+        assert_eq!(total, 5);
+        assert!(matches!(
+            receiver.try_recv().expect("failed to receive SendBatch command"),
+            Command::SendBatch(packets) if packets == [vec![1, 2, 3], vec![4, 5]]
+        ));
+        assert!(receiver.try_recv().is_err());
+    }
+
     #[test]
     fn handle_close_receives_command_close() {
         let (sender, receiver) = crossbeam_channel::bounded(16);
@@ -130,6 +462,22 @@ mod tests {
         assert!(matches!(cmd, Command::Close));
     }
 
+    #[test]
+    fn handle_send_and_close_queues_one_command() {
+        let (sender, receiver) = crossbeam_channel::bounded(16);
+        let handle = ConnectionHandle::new(test_id(), test_addr(), sender);
+
+        handle
+            .send_and_close(vec![0x14, 0x01])
+            .expect("failed to queue send_and_close in test");
+
+        assert!(matches!(
+            receiver.try_recv().expect("failed to receive SendAndClose command"),
+            Command::SendAndClose(data) if data == [0x14, 0x01]
+        ));
+        assert!(receiver.try_recv().is_err());
+    }
+
     #[test]
     fn handle_send_full_channel_returns_error() {
         let (sender, rx) = crossbeam_channel::bounded(1);
@@ -160,6 +508,22 @@ mod tests {
         assert!(matches!(cmd, Command::SetXteaKey(_)));
     }
 
+    #[test]
+    fn handle_set_checksum_enabled() {
+        let (sender, receiver) = crossbeam_channel::bounded(16);
+        let handle = ConnectionHandle::new(test_id(), test_addr(), sender);
+
+        handle
+            .set_checksum_enabled(false)
+            .expect("failed to set checksum mode in test");
+
+        let cmd = receiver
+            .try_recv()
+            .expect("failed to receive SetChecksumEnabled command in test");
+
+        assert!(matches!(cmd, Command::SetChecksumEnabled(false)));
+    }
+
     #[test]
     fn handle_close_with_reason() {
         let (sender, receiver) = crossbeam_channel::bounded(16);
@@ -175,4 +539,24 @@ mod tests {
 
         assert!(matches!(cmd, Command::CloseWithReason(r) if r == "timeout"));
     }
+
+    #[test]
+    fn handle_sends_from_another_thread() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ConnectionHandle>();
+
+        let (sender, receiver) = crossbeam_channel::bounded(16);
+        let handle = ConnectionHandle::new(test_id(), test_addr(), sender);
+
+        let remote = handle.clone();
+        std::thread::spawn(move || remote.send(b"notice".to_vec()))
+            .join()
+            .expect("sender thread should not panic")
+            .expect("failed to send from another thread");
+
+        let cmd = receiver
+            .try_recv()
+            .expect("failed to receive command sent from another thread");
+        assert!(matches!(cmd, Command::Send(data) if data == b"notice"));
+    }
 }
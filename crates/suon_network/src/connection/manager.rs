@@ -9,13 +9,20 @@ use std::{
 use tracing::{trace, warn};
 
 use dashmap::DashMap;
+use parking_lot::RwLock;
 
 use crate::{
     connection::{
-        handle::ConnectionHandle, id::ConnectionId, info::ConnectionInfo, stats::ConnectionStats,
+        budget::{BudgetCharge, BufferBudget},
+        handle::ConnectionHandle,
+        id::ConnectionId,
+        info::ConnectionInfo,
+        resumption::SessionTokens,
+        stats::ConnectionStats,
+        tap::PacketTap,
     },
     protocol::command::CommandSender,
-    server::tcp::ProtocolSettings,
+    server::{accept_filter::AcceptFilter, tcp::ProtocolSettings},
 };
 
 /// ID namespace for a listener port.
@@ -30,6 +37,10 @@ pub struct ConnectionManager {
     connections: DashMap<u64, (ConnectionHandle, ProtocolSettings, Instant)>,
     port_namespace: PortNamespace,
     stats: Arc<ConnectionStats>,
+    accept_filter: RwLock<AcceptFilter>,
+    packet_tap: PacketTap,
+    buffer_budget: BufferBudget,
+    session_tokens: SessionTokens,
 }
 
 impl ConnectionManager {
@@ -44,9 +55,57 @@ impl ConnectionManager {
             connections: DashMap::new(),
             port_namespace,
             stats: Arc::new(ConnectionStats::default()),
+            accept_filter: RwLock::new(AcceptFilter::default()),
+            packet_tap: PacketTap::default(),
+            buffer_budget: BufferBudget::default(),
+            session_tokens: SessionTokens::default(),
         }
     }
 
+    /// Records the frames of every connection on `tap`.
+    pub fn with_packet_tap(mut self, tap: PacketTap) -> Self {
+        self.packet_tap = tap;
+        self
+    }
+
+    /// The capture shared by the sessions of this manager's connections.
+    pub fn packet_tap(&self) -> &PacketTap {
+        &self.packet_tap
+    }
+
+    /// Charges the packets queued on every connection to `budget`.
+    pub fn with_buffer_budget(mut self, budget: BufferBudget) -> Self {
+        self.buffer_budget = budget;
+        self
+    }
+
+    /// The budget shared by this manager's connections.
+    pub fn buffer_budget(&self) -> &BufferBudget {
+        &self.buffer_budget
+    }
+
+    /// Keeps resumption tokens in `tokens`, shared with whoever issues
+    /// them.
+    pub fn with_session_tokens(mut self, tokens: SessionTokens) -> Self {
+        self.session_tokens = tokens;
+        self
+    }
+
+    /// The resumption tokens redeemed by this manager's connections.
+    pub fn session_tokens(&self) -> &SessionTokens {
+        &self.session_tokens
+    }
+
+    /// Replaces the filter the acceptors consult before taking a socket.
+    pub fn set_accept_filter(&self, filter: AcceptFilter) {
+        *self.accept_filter.write() = filter;
+    }
+
+    /// Whether the current [`AcceptFilter`] lets `peer` connect.
+    pub fn accepts(&self, peer: &SocketAddr) -> bool {
+        self.accept_filter.read().allows(peer)
+    }
+
     /// Registers a new connection and returns its assigned ID and handle.
     pub fn register(
         &self,
@@ -56,7 +115,8 @@ impl ConnectionManager {
     ) -> ConnectionId {
         let seq = self.next_id.fetch_add(1, Ordering::Relaxed) as u32;
         let id = ConnectionId::new(self.port_namespace, seq);
-        let handle = ConnectionHandle::new(id, peer, sender);
+        let handle = ConnectionHandle::new(id, peer, sender)
+            .with_budget_charge(BudgetCharge::new(self.buffer_budget.clone()));
         self.connections
             .insert(id.as_u64(), (handle, protocol, Instant::now()));
         self.stats.record_accepted();
@@ -83,6 +143,14 @@ impl ConnectionManager {
         self.connections.len()
     }
 
+    /// Returns a snapshot of the handles of all active connections.
+    pub fn handles(&self) -> Vec<ConnectionHandle> {
+        self.connections
+            .iter()
+            .map(|entry| entry.value().0.clone())
+            .collect()
+    }
+
     /// Returns a serializable list of all active connections.
     pub fn active_connections(&self) -> Vec<ConnectionInfo> {
         self.connections
@@ -99,6 +167,39 @@ impl ConnectionManager {
         &self.stats
     }
 
+    /// Returns a shared handle to the statistics, for sessions that
+    /// outlive a borrow of the manager.
+    pub fn stats_handle(&self) -> Arc<ConnectionStats> {
+        self.stats.clone()
+    }
+
+    /// Removes the connections whose reader session has exited without
+    /// unregistering them, and returns their IDs.
+    ///
+    /// The reader session unregisters its connection as its last step,
+    /// so an entry outliving it means that step never ran: the session's
+    /// future was dropped at one of its awaits instead of returning. Tokio
+    /// does that when a session panics, since panics unwind and are
+    /// caught by the task, and when the task is cancelled. Dropping the
+    /// entry also drops its command sender, which lets the writer session
+    /// wind down.
+    pub fn reap_exited(&self) -> Vec<ConnectionId> {
+        let mut reaped = Vec::new();
+        self.connections.retain(|_, (handle, ..)| {
+            let alive = handle.is_reader_alive();
+            if !alive {
+                reaped.push(handle.id());
+            }
+            alive
+        });
+
+        for id in &reaped {
+            self.stats.record_closed();
+            warn!(target: "Connection", "Reaped connection {id} whose reader session exited");
+        }
+        reaped
+    }
+
     /// Removes all connections and returns the count of cleaned-up entries.
     pub fn clear(&self) -> usize {
         let count = self.connections.len();
@@ -1,10 +1,25 @@
+pub mod auth_state;
+pub mod budget;
+pub mod client_kind;
+pub mod disconnect;
 pub mod handle;
 pub mod id;
 pub mod info;
 pub mod manager;
+pub mod resumption;
 pub mod stats;
+pub mod tap;
 
 pub use self::{
-    handle::ConnectionHandle, id::ConnectionId, info::ConnectionInfo, manager::ConnectionManager,
+    auth_state::AuthState,
+    budget::BufferBudget,
+    client_kind::ClientKind,
+    disconnect::DisconnectReason,
+    handle::{ConnectionHandle, WriteError},
+    id::ConnectionId,
+    info::ConnectionInfo,
+    manager::ConnectionManager,
+    resumption::{ResumableSession, ResumptionToken, SessionTokens},
     stats::ConnectionStats,
+    tap::{CapturedPacket, PacketDirection, PacketTap},
 };
@@ -0,0 +1,183 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use suon_macros::Resource;
+
+use crate::connection::{client_kind::ClientKind, handle::ConnectionHandle, id::ConnectionId};
+
+/// Opaque token a reconnecting client presents to pick up its previous
+/// session without logging in again.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResumptionToken(pub [u8; 16]);
+
+impl ResumptionToken {
+    /// A fresh token drawn from the operating system's secure random
+    /// source, so tokens cannot be guessed from earlier ones.
+    fn generate() -> Result<Self, getrandom::Error> {
+        let mut token = [0; 16];
+        getrandom::fill(&mut token)?;
+        Ok(ResumptionToken(token))
+    }
+}
+
+impl fmt::Debug for ResumptionToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ResumptionToken(..)")
+    }
+}
+
+/// What a resumed connection takes over from the one that issued its
+/// token.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ResumableSession {
+    /// The connection the token was issued on.
+    pub previous: ConnectionId,
+    pub client_kind: Option<ClientKind>,
+    pub protocol_version: Option<u16>,
+    pub xtea_key: Option<[u32; 4]>,
+}
+
+impl fmt::Debug for ResumableSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResumableSession")
+            .field("previous", &self.previous)
+            .field("client_kind", &self.client_kind)
+            .field("protocol_version", &self.protocol_version)
+            .field("xtea_key", &self.xtea_key.map(|_| ".."))
+            .finish()
+    }
+}
+
+/// Resumption tokens issued to logged-in connections.
+///
+/// Game logic issues a token once a login succeeds and hands it to the
+/// client. On ports with `allow_resumption`, a new connection whose first
+/// packet is a [`ResumeSessionPacket`](crate::server::tcp::ResumeSessionPacket)
+/// carrying a live token skips the handshake and login: it takes over
+/// the protocol version and XTEA key of the issuing connection and is
+/// marked authenticated. Tokens are single-use and expire after the
+/// store's time to live.
+#[derive(Clone, Resource)]
+pub struct SessionTokens {
+    sessions: Arc<Mutex<HashMap<ResumptionToken, (ResumableSession, Instant)>>>,
+    ttl: Duration,
+}
+
+impl SessionTokens {
+    /// How long an issued token stays valid by default.
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+    pub fn new(ttl: Duration) -> Self {
+        SessionTokens {
+            sessions: Arc::default(),
+            ttl,
+        }
+    }
+
+    /// Issues a token resuming the session of `handle`, with the XTEA key
+    /// last set on it. Fails only if the operating system cannot supply
+    /// random bytes.
+    pub fn issue(&self, handle: &ConnectionHandle) -> Result<ResumptionToken, getrandom::Error> {
+        let session = ResumableSession {
+            previous: handle.id(),
+            client_kind: handle.client_kind(),
+            protocol_version: handle.protocol_version(),
+            xtea_key: handle.xtea_key(),
+        };
+
+        let now = Instant::now();
+        let token = ResumptionToken::generate()?;
+        let mut sessions = self.sessions.lock();
+        sessions.retain(|_, (_, issued)| now.duration_since(*issued) < self.ttl);
+        sessions.insert(token, (session, now));
+        Ok(token)
+    }
+
+    /// Takes the session behind `token`, or `None` if the token was never
+    /// issued, has been redeemed already or has expired.
+    pub fn redeem(&self, token: &ResumptionToken) -> Option<ResumableSession> {
+        let (session, issued) = self.sessions.lock().remove(token)?;
+        (issued.elapsed() < self.ttl).then_some(session)
+    }
+
+    /// Tokens issued and not yet redeemed, including expired ones not
+    /// swept yet.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for SessionTokens {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TTL)
+    }
+}
+
+impl fmt::Debug for SessionTokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionTokens")
+            .field("issued", &self.len())
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle() -> ConnectionHandle {
+        let (sender, _) = crossbeam_channel::bounded(4);
+        let peer = "127.0.0.1:7171".parse().expect("valid test address");
+        ConnectionHandle::new(ConnectionId::new(0, 7), peer, sender)
+    }
+
+    #[test]
+    fn tokens_are_single_use() {
+        let tokens = SessionTokens::default();
+        let handle = handle();
+        let _ = handle.set_xtea_key([1, 2, 3, 4]);
+
+        let token = tokens.issue(&handle).expect("failed to issue a token");
+        let other = tokens.issue(&handle).expect("failed to issue a token");
+        assert_ne!(token, other);
+
+        let session = tokens.redeem(&token).expect("a fresh token should redeem");
+        assert_eq!(session.previous, ConnectionId::new(0, 7));
+        assert_eq!(session.xtea_key, Some([1, 2, 3, 4]));
+        assert_eq!(tokens.redeem(&token), None);
+        assert_eq!(tokens.redeem(&ResumptionToken([0; 16])), None);
+    }
+
+    #[test]
+    fn session_debug_hides_the_xtea_key() {
+        let session = ResumableSession {
+            previous: ConnectionId::new(0, 7),
+            client_kind: None,
+            protocol_version: None,
+            xtea_key: Some([0xDEAD_BEEF; 4]),
+        };
+
+        let debug = format!("{session:?}");
+        assert!(debug.contains("xtea_key: Some(\"..\")"));
+        assert!(!debug.contains(&0xDEAD_BEEF_u32.to_string()));
+    }
+
+    #[test]
+    fn expired_tokens_do_not_redeem() {
+        let tokens = SessionTokens::new(Duration::ZERO);
+        let token = tokens.issue(&handle()).expect("failed to issue a token");
+
+        assert_eq!(tokens.redeem(&token), None);
+        assert!(tokens.is_empty());
+    }
+}
@@ -8,6 +8,12 @@ pub struct ConnectionStats {
     pub total_closed: AtomicU64,
     pub bytes_received: AtomicU64,
     pub bytes_sent: AtomicU64,
+    /// Accepted sockets currently waiting for the `onConnect` decision.
+    pub pending_accepts: AtomicU64,
+    pub packets_received: AtomicU64,
+    pub packets_sent: AtomicU64,
+    /// Accepted sockets dropped by the rate or connection limiter.
+    pub total_throttled: AtomicU64,
 }
 
 impl ConnectionStats {
@@ -26,6 +32,27 @@ impl ConnectionStats {
     pub fn record_bytes_sent(&self, n: u64) {
         self.bytes_sent.fetch_add(n, Ordering::Relaxed);
     }
+
+    pub fn record_packet_received(&self, bytes: u64) {
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+        self.record_bytes_received(bytes);
+    }
+
+    pub fn record_packet_sent(&self) {
+        self.packets_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_throttled(&self) {
+        self.total_throttled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_accept_queued(&self) {
+        self.pending_accepts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_accept_dequeued(&self) {
+        self.pending_accepts.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 #[cfg(test)]
@@ -39,6 +66,7 @@ mod tests {
         assert_eq!(stats.total_closed.load(Ordering::Relaxed), 0);
         assert_eq!(stats.bytes_received.load(Ordering::Relaxed), 0);
         assert_eq!(stats.bytes_sent.load(Ordering::Relaxed), 0);
+        assert_eq!(stats.pending_accepts.load(Ordering::Relaxed), 0);
     }
 
     #[test]
@@ -64,6 +92,26 @@ mod tests {
         assert_eq!(stats.bytes_sent.load(Ordering::Relaxed), 2048);
     }
 
+    #[test]
+    fn stats_record_pending_accepts() {
+        let stats = ConnectionStats::default();
+        stats.record_accept_queued();
+        stats.record_accept_queued();
+        stats.record_accept_dequeued();
+        assert_eq!(stats.pending_accepts.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn stats_record_packets() {
+        let stats = ConnectionStats::default();
+        stats.record_packet_received(10);
+        stats.record_packet_received(6);
+        stats.record_packet_sent();
+        assert_eq!(stats.packets_received.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.bytes_received.load(Ordering::Relaxed), 16);
+        assert_eq!(stats.packets_sent.load(Ordering::Relaxed), 1);
+    }
+
     #[test]
     fn stats_record_multiple() {
         let stats = ConnectionStats::default();
@@ -0,0 +1,154 @@
+use std::{collections::VecDeque, fmt, sync::Arc};
+
+use parking_lot::Mutex;
+use suon_macros::Resource;
+
+use crate::connection::id::ConnectionId;
+
+/// Which way a [`CapturedPacket`] crossed the socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketDirection {
+    Incoming,
+    Outgoing,
+}
+
+impl fmt::Display for PacketDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PacketDirection::Incoming => "in",
+            PacketDirection::Outgoing => "out",
+        })
+    }
+}
+
+/// One frame recorded by a [`PacketTap`], exactly as it went over the
+/// wire: size header included, before decryption on the way in and
+/// after encryption on the way out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedPacket {
+    pub id: ConnectionId,
+    pub direction: PacketDirection,
+    pub data: Vec<u8>,
+}
+
+/// Optional capture of the raw frames every TCP connection reads and
+/// writes, for diagnosing framing bugs.
+///
+/// Keeps the last `capacity` frames across all connections, oldest
+/// dropped first, so a busy server cannot grow it without bound and
+/// frames of a connection that already hung up can still be dumped.
+/// Sized by `packet_tap_capacity` in the network settings; a capacity of
+/// `0` (the default) disables it, and the sessions then skip copying
+/// entirely.
+#[derive(Clone, Default, Resource)]
+pub struct PacketTap {
+    ring: Option<Arc<Ring>>,
+}
+
+struct Ring {
+    capacity: usize,
+    packets: Mutex<VecDeque<CapturedPacket>>,
+}
+
+impl PacketTap {
+    /// A tap holding up to `capacity` frames, or a disabled one for `0`.
+    pub fn new(capacity: usize) -> Self {
+        PacketTap {
+            ring: (capacity > 0).then(|| {
+                Arc::new(Ring {
+                    capacity,
+                    packets: Mutex::new(VecDeque::with_capacity(capacity)),
+                })
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.ring.is_some()
+    }
+
+    /// Adds `data` to the capture, evicting the oldest frame when full.
+    /// Does nothing when the tap is disabled or `data` is empty; check
+    /// [`is_enabled`](Self::is_enabled) before copying a frame out.
+    pub fn record(&self, id: ConnectionId, direction: PacketDirection, data: Vec<u8>) {
+        let Some(ring) = &self.ring else {
+            return;
+        };
+        if data.is_empty() {
+            return;
+        }
+
+        let mut packets = ring.packets.lock();
+        if packets.len() == ring.capacity {
+            packets.pop_front();
+        }
+        packets.push_back(CapturedPacket {
+            id,
+            direction,
+            data,
+        });
+    }
+
+    /// The captured frames of connection `id`, oldest first.
+    pub fn dump(&self, id: ConnectionId) -> Vec<CapturedPacket> {
+        self.ring.as_ref().map_or_else(Vec::new, |ring| {
+            ring.packets
+                .lock()
+                .iter()
+                .filter(|packet| packet.id == id)
+                .cloned()
+                .collect()
+        })
+    }
+
+    /// Every captured frame, oldest first.
+    pub fn dump_all(&self) -> Vec<CapturedPacket> {
+        self.ring.as_ref().map_or_else(Vec::new, |ring| {
+            ring.packets.lock().iter().cloned().collect()
+        })
+    }
+}
+
+impl fmt::Debug for PacketTap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PacketTap")
+            .field(
+                "capacity",
+                &self.ring.as_ref().map_or(0, |ring| ring.capacity),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_tap_records_nothing() {
+        let tap = PacketTap::new(0);
+        tap.record(
+            ConnectionId::new(0, 1),
+            PacketDirection::Incoming,
+            vec![1, 2],
+        );
+
+        assert!(!tap.is_enabled());
+        assert!(tap.dump_all().is_empty());
+    }
+
+    #[test]
+    fn tap_keeps_the_most_recent_frames_per_connection() {
+        let tap = PacketTap::new(3);
+        let (a, b) = (ConnectionId::new(0, 1), ConnectionId::new(0, 2));
+        tap.record(a, PacketDirection::Incoming, vec![1]);
+        tap.record(b, PacketDirection::Outgoing, vec![2]);
+        tap.record(a, PacketDirection::Outgoing, vec![3]);
+        tap.record(a, PacketDirection::Incoming, vec![4]);
+
+        let dumped: Vec<_> = tap.dump(a).into_iter().map(|packet| packet.data).collect();
+        assert_eq!(dumped, [vec![3], vec![4]]);
+        assert_eq!(tap.dump(b)[0].direction, PacketDirection::Outgoing);
+        assert_eq!(tap.dump_all().len(), 3);
+    }
+}
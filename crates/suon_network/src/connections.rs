@@ -2,7 +2,29 @@ use std::sync::Arc;
 
 use suon_macros::Resource;
 
-use crate::connection::{handle::ConnectionHandle, id::ConnectionId, manager::ConnectionManager};
+use crate::connection::{
+    auth_state::AuthState, handle::ConnectionHandle, id::ConnectionId, manager::ConnectionManager,
+    resumption::ResumptionToken,
+};
+
+/// Snapshot of how many connections are open, returned by
+/// [`Connections::active`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActiveConnections {
+    /// Every registered connection.
+    pub total: usize,
+    /// Connections whose handshake has not been completed yet.
+    pub handshaking: usize,
+    /// Connections game logic has marked as logged in.
+    pub authenticated: usize,
+}
+
+impl ActiveConnections {
+    /// Connections that have completed their handshake.
+    pub fn established(&self) -> usize {
+        self.total - self.handshaking
+    }
+}
 
 /// Global registry of all active connections.
 ///
@@ -26,6 +48,27 @@ impl Connections {
         self.manager.get(identifier)
     }
 
+    /// Returns a snapshot of the handles of all active connections.
+    pub fn handles(&self) -> Vec<ConnectionHandle> {
+        self.manager.handles()
+    }
+
+    /// Counts the active connections, without exposing their handles.
+    pub fn active(&self) -> ActiveConnections {
+        let handles = self.manager.handles();
+        ActiveConnections {
+            total: handles.len(),
+            handshaking: handles
+                .iter()
+                .filter(|handle| !handle.is_handshake_complete())
+                .count(),
+            authenticated: handles
+                .iter()
+                .filter(|handle| handle.auth_state() == AuthState::Authenticated)
+                .count(),
+        }
+    }
+
     /// Send raw bytes to the identified connection.
     pub fn send(&self, id: u64, data: Vec<u8>) -> Result<(), String> {
         let identifier = ConnectionId::from_u64(id);
@@ -39,6 +82,57 @@ impl Connections {
             .map_err(|error| format!("send failed: {error}"))
     }
 
+    /// Send a group of packets to the identified connection in one flush.
+    pub fn send_batch(&self, id: u64, packets: Vec<Vec<u8>>) -> Result<usize, String> {
+        let identifier = ConnectionId::from_u64(id);
+        let handle = self
+            .manager
+            .get(identifier)
+            .ok_or_else(|| format!("connection {id} not found"))?;
+
+        handle
+            .send_batch(packets)
+            .map_err(|error| format!("send_batch failed: {error}"))
+    }
+
+    /// Send the same packet to every active connection and return how
+    /// many accepted it. Connections whose queue is full or closed are
+    /// skipped rather than failing the whole broadcast.
+    pub fn broadcast(&self, data: &[u8]) -> usize {
+        self.broadcast_to(&self.handles(), data)
+    }
+
+    /// Like [`broadcast`](Self::broadcast), but only to `handles`.
+    ///
+    /// Narrow the audience by filtering [`handles`](Self::handles), for
+    /// example by client kind or against the game's own set of players
+    /// in a zone, and pass the filtered iterator straight in.
+    pub fn broadcast_to<'a>(
+        &self,
+        handles: impl IntoIterator<Item = &'a ConnectionHandle>,
+        data: &[u8],
+    ) -> usize {
+        handles
+            .into_iter()
+            .filter(|handle| handle.send(data.to_vec()).is_ok())
+            .count()
+    }
+
+    /// Ask every active connection to push its buffered packets to the
+    /// socket and return how many connections were asked to.
+    ///
+    /// Writer sessions otherwise flush on their own `flush_policy`, so
+    /// call this to flush at a chosen point, such as the end of a game
+    /// tick. Each writer session flushes as soon as it picks the request
+    /// up, which may be after this returns. Connections whose queue is
+    /// full or closed are skipped.
+    pub fn flush_all(&self) -> usize {
+        self.handles()
+            .iter()
+            .filter(|handle| handle.flush().is_ok())
+            .count()
+    }
+
     /// Send raw bytes, bypassing protocol framing/encryption.
     pub fn send_raw(&self, id: u64, data: Vec<u8>) -> Result<(), String> {
         let identifier = ConnectionId::from_u64(id);
@@ -52,6 +146,87 @@ impl Connections {
             .map_err(|error| format!("send_raw failed: {error}"))
     }
 
+    /// Push the identified connection's buffered packets to the socket.
+    pub fn flush(&self, id: u64) -> Result<(), String> {
+        let identifier = ConnectionId::from_u64(id);
+        let handle = self
+            .manager
+            .get(identifier)
+            .ok_or_else(|| format!("connection {id} not found"))?;
+
+        handle
+            .flush()
+            .map_err(|error| format!("flush failed: {error}"))
+    }
+
+    /// Record the client's protocol version, which picks whether its
+    /// packets carry a checksum.
+    pub fn set_protocol_version(&self, id: u64, version: u16) -> Result<(), String> {
+        let identifier = ConnectionId::from_u64(id);
+        let handle = self
+            .manager
+            .get(identifier)
+            .ok_or_else(|| format!("connection {id} not found"))?;
+
+        handle
+            .set_protocol_version(version)
+            .map_err(|error| format!("set_protocol_version failed: {error}"))
+    }
+
+    /// Mark the connection's handshake as done, lifting the handshake
+    /// timeout.
+    pub fn complete_handshake(&self, id: u64) -> Result<(), String> {
+        let identifier = ConnectionId::from_u64(id);
+        let handle = self
+            .manager
+            .get(identifier)
+            .ok_or_else(|| format!("connection {id} not found"))?;
+
+        handle.complete_handshake();
+        Ok(())
+    }
+
+    /// Mark the connection as logged in, letting its packets through on
+    /// ports that require authentication.
+    pub fn authenticate(&self, id: u64) -> Result<(), String> {
+        let identifier = ConnectionId::from_u64(id);
+        let handle = self
+            .manager
+            .get(identifier)
+            .ok_or_else(|| format!("connection {id} not found"))?;
+
+        handle.authenticate();
+        Ok(())
+    }
+
+    /// Issue a token the client can present to resume this session
+    /// after reconnecting, on ports with `allow_resumption`.
+    pub fn issue_resumption_token(&self, id: u64) -> Result<ResumptionToken, String> {
+        let identifier = ConnectionId::from_u64(id);
+        let handle = self
+            .manager
+            .get(identifier)
+            .ok_or_else(|| format!("connection {id} not found"))?;
+
+        self.manager
+            .session_tokens()
+            .issue(&handle)
+            .map_err(|error| format!("issue_resumption_token failed: {error}"))
+    }
+
+    /// Send a final packet, then close the connection once it is out.
+    pub fn send_and_close(&self, id: u64, data: Vec<u8>) -> Result<(), String> {
+        let identifier = ConnectionId::from_u64(id);
+        let handle = self
+            .manager
+            .get(identifier)
+            .ok_or_else(|| format!("connection {id} not found"))?;
+
+        handle
+            .send_and_close(data)
+            .map_err(|error| format!("send_and_close failed: {error}"))
+    }
+
     /// Gracefully close the connection.
     pub fn close(&self, id: u64) -> Result<(), String> {
         let id = ConnectionId::from_u64(id);
@@ -75,6 +250,7 @@ impl Default for Connections {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::command::Command;
 
     #[test]
     fn new_is_empty() {
@@ -101,6 +277,184 @@ mod tests {
         assert!(connections.get(identifier).is_some());
     }
 
+    fn register_mock(
+        connections: &Connections,
+        capacity: usize,
+    ) -> (u64, crossbeam_channel::Receiver<Command>) {
+        use crate::server::tcp::ProtocolSettings;
+        use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+        let (sender, receiver) = crossbeam_channel::bounded(capacity);
+        let settings = ProtocolSettings {
+            header_size: 2,
+            has_checksum: false,
+            uses_xtea: false,
+            uses_rsa: false,
+        };
+        let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 7000));
+        let identifier = connections.manager.register(peer, settings, sender);
+        (identifier.as_u64(), receiver)
+    }
+
+    #[test]
+    fn active_splits_handshaking_from_established_connections() {
+        use crate::connection::client_kind::ClientKind;
+
+        let connections = Connections::new();
+        assert_eq!(connections.active(), ActiveConnections::default());
+
+        let (player, _player_rx) = register_mock(&connections, 4);
+        let (login, _login_rx) = register_mock(&connections, 4);
+        let (pending, _pending_rx) = register_mock(&connections, 4);
+        let (closed, _closed_rx) = register_mock(&connections, 4);
+        let handle = |id| {
+            connections
+                .get(ConnectionId::from_u64(id))
+                .expect("connection should be registered")
+        };
+        handle(player).complete_handshake();
+        handle(player).authenticate();
+        handle(login).complete_handshake();
+        handle(pending).resolve_client_kind(ClientKind::Game);
+
+        let active = connections.active();
+        assert_eq!(active.total, 4);
+        assert_eq!(
+            active.handshaking, 2,
+            "a resolved client kind is not a handshake"
+        );
+        assert_eq!(active.authenticated, 1);
+        assert_eq!(active.established(), 2);
+
+        connections
+            .manager
+            .unregister(ConnectionId::from_u64(closed));
+        assert_eq!(
+            connections.active(),
+            ActiveConnections {
+                total: 3,
+                handshaking: 1,
+                authenticated: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn send_reaches_only_the_identified_connection() {
+        let connections = Connections::new();
+        let (first, first_rx) = register_mock(&connections, 4);
+        let (_, second_rx) = register_mock(&connections, 4);
+
+        connections
+            .send(first, vec![1, 2, 3])
+            .expect("send to a registered connection should succeed");
+
+        assert!(matches!(first_rx.try_recv(), Ok(Command::Send(data)) if data == [1, 2, 3]));
+        assert!(second_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn broadcast_reaches_every_connection_and_skips_full_ones() {
+        let connections = Connections::new();
+        let (_, first_rx) = register_mock(&connections, 4);
+        let (_, second_rx) = register_mock(&connections, 4);
+        let (full, full_rx) = register_mock(&connections, 1);
+        connections
+            .send(full, vec![0])
+            .expect("failed to fill the mock queue");
+
+        assert_eq!(connections.broadcast(&[7, 8]), 2);
+        assert_eq!(connections.handles().len(), 3);
+
+        for rx in [&first_rx, &second_rx] {
+            assert!(matches!(rx.try_recv(), Ok(Command::Send(data)) if data == [7, 8]));
+        }
+        assert!(matches!(full_rx.try_recv(), Ok(Command::Send(data)) if data == [0]));
+        assert!(full_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn broadcast_to_reaches_only_the_filtered_connections() {
+        let connections = Connections::new();
+        let (zone, zone_rx) = register_mock(&connections, 4);
+        let (_, other_rx) = register_mock(&connections, 4);
+        let in_zone = [zone];
+
+        let handles = connections.handles();
+        let filtered = handles
+            .iter()
+            .filter(|handle| in_zone.contains(&handle.id().as_u64()));
+        assert_eq!(connections.broadcast_to(filtered, &[9]), 1);
+
+        assert!(matches!(zone_rx.try_recv(), Ok(Command::Send(data)) if data == [9]));
+        assert!(other_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn flush_all_skips_connections_with_a_full_queue() {
+        let connections = Connections::new();
+        let (_, open_rx) = register_mock(&connections, 4);
+        let (full, full_rx) = register_mock(&connections, 1);
+        connections
+            .send(full, vec![0])
+            .expect("failed to fill the mock queue");
+
+        assert_eq!(connections.flush_all(), 1);
+
+        assert!(matches!(open_rx.try_recv(), Ok(Command::Flush)));
+        assert!(matches!(full_rx.try_recv(), Ok(Command::Send(data)) if data == [0]));
+        assert!(full_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn flush_all_writes_the_buffered_packets_of_every_connection() {
+        use crate::server::{
+            shutdown::Shutdown,
+            tcp::{FlushPolicy, TcpSettings, writer_session::WriterSession},
+        };
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+
+        // A manual policy and a one-minute tick: only the flush can get
+        // the packets onto the wire.
+        let config = TcpSettings {
+            flush_interval: Duration::from_secs(60),
+            flush_policy: FlushPolicy::Manual,
+            ..TcpSettings::default()
+        };
+        let connections = Connections::new();
+        let mut clients = Vec::new();
+        for byte in [1, 2] {
+            let (id, receiver) = register_mock(&connections, 4);
+            let handle = connections
+                .get(ConnectionId::from_u64(id))
+                .expect("connection should be registered");
+            let (client, server) = tokio::io::duplex(1024);
+            WriterSession::new(
+                receiver,
+                server,
+                config.clone(),
+                Shutdown::new(),
+                crate::test_buffer_pool(),
+            )
+            .with_wake(handle.writer_wake().clone())
+            .spawn();
+            handle.send(vec![byte]).expect("failed to queue packet");
+            clients.push((client, byte));
+        }
+
+        assert_eq!(connections.flush_all(), 2);
+
+        for (mut client, byte) in clients {
+            let mut frame = [0u8; 2 + 4 + 1];
+            tokio::time::timeout(Duration::from_secs(1), client.read_exact(&mut frame))
+                .await
+                .expect("flush_all should get the packet written")
+                .expect("failed to read flushed packet");
+            assert_eq!(frame[6], byte);
+        }
+    }
+
     #[test]
     fn send_missing_connection_returns_error() {
         let connections = Connections::new();
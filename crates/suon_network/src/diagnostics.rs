@@ -0,0 +1,236 @@
+use std::{
+    fmt,
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use suon_app::{App, plugin::Plugin};
+use tracing::info;
+
+use crate::{
+    connection::manager::ConnectionManager, connections::Connections, manager::NetworkManager,
+};
+
+/// Point-in-time copy of the cumulative network counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkSnapshot {
+    pub active_connections: usize,
+    pub pending_accepts: u64,
+    pub packets_received: u64,
+    pub packets_sent: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub throttled: u64,
+}
+
+impl NetworkSnapshot {
+    pub fn capture(manager: &ConnectionManager) -> Self {
+        let stats = manager.stats();
+        NetworkSnapshot {
+            active_connections: manager.count(),
+            pending_accepts: stats.pending_accepts.load(Ordering::Relaxed),
+            packets_received: stats.packets_received.load(Ordering::Relaxed),
+            packets_sent: stats.packets_sent.load(Ordering::Relaxed),
+            bytes_received: stats.bytes_received.load(Ordering::Relaxed),
+            bytes_sent: stats.bytes_sent.load(Ordering::Relaxed),
+            throttled: stats.total_throttled.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Current gauges plus per-second rates over one sampling interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetworkDiagnostics {
+    pub active_connections: usize,
+    pub pending_accepts: u64,
+    pub packets_in_per_sec: f64,
+    pub packets_out_per_sec: f64,
+    pub bytes_in_per_sec: f64,
+    pub bytes_out_per_sec: f64,
+    pub throttled_per_sec: f64,
+}
+
+impl NetworkDiagnostics {
+    /// Derives rates from the counter growth between two snapshots taken
+    /// `elapsed` apart.
+    pub fn between(
+        previous: &NetworkSnapshot,
+        current: &NetworkSnapshot,
+        elapsed: Duration,
+    ) -> Self {
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+        let rate = |before: u64, after: u64| after.saturating_sub(before) as f64 / seconds;
+
+        NetworkDiagnostics {
+            active_connections: current.active_connections,
+            pending_accepts: current.pending_accepts,
+            packets_in_per_sec: rate(previous.packets_received, current.packets_received),
+            packets_out_per_sec: rate(previous.packets_sent, current.packets_sent),
+            bytes_in_per_sec: rate(previous.bytes_received, current.bytes_received),
+            bytes_out_per_sec: rate(previous.bytes_sent, current.bytes_sent),
+            throttled_per_sec: rate(previous.throttled, current.throttled),
+        }
+    }
+}
+
+impl fmt::Display for NetworkDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "connections={} pending={} packets/s in={:.1} out={:.1} bytes/s in={:.1} out={:.1} \
+             throttled/s={:.1}",
+            self.active_connections,
+            self.pending_accepts,
+            self.packets_in_per_sec,
+            self.packets_out_per_sec,
+            self.bytes_in_per_sec,
+            self.bytes_out_per_sec,
+            self.throttled_per_sec,
+        )
+    }
+}
+
+/// Logs [`NetworkDiagnostics`] under the `Diagnostics` target every
+/// `interval`.
+///
+/// Must be added after [`NetworkPlugin`](crate::NetworkPlugin), whose
+/// connection registry and runtime it samples.
+pub struct NetworkDiagnosticsPlugin {
+    pub interval: Duration,
+}
+
+impl Default for NetworkDiagnosticsPlugin {
+    fn default() -> Self {
+        NetworkDiagnosticsPlugin {
+            interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Plugin for NetworkDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        let manager = app.get_resource::<Connections>().manager.clone();
+        let runtime = app.get_resource::<NetworkManager>().runtime().clone();
+
+        runtime.spawn(sample(manager, self.interval, |diagnostics| {
+            info!(target: "Diagnostics", "{diagnostics}");
+        }));
+    }
+}
+
+/// Calls `report` with fresh diagnostics every `interval`, forever.
+async fn sample(
+    manager: Arc<ConnectionManager>,
+    interval: Duration,
+    mut report: impl FnMut(NetworkDiagnostics),
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    ticker.tick().await;
+
+    let mut previous = NetworkSnapshot::capture(&manager);
+    let mut sampled_at = Instant::now();
+    loop {
+        ticker.tick().await;
+
+        let current = NetworkSnapshot::capture(&manager);
+        let now = Instant::now();
+        report(NetworkDiagnostics::between(
+            &previous,
+            &current,
+            now - sampled_at,
+        ));
+
+        previous = current;
+        sampled_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::tcp::ProtocolSettings;
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    #[test]
+    fn between_computes_per_second_rates() {
+        let previous = NetworkSnapshot {
+            packets_received: 10,
+            bytes_sent: 1_000,
+            ..NetworkSnapshot::default()
+        };
+        let current = NetworkSnapshot {
+            active_connections: 3,
+            packets_received: 30,
+            bytes_sent: 5_000,
+            throttled: 4,
+            ..NetworkSnapshot::default()
+        };
+
+        let diagnostics = NetworkDiagnostics::between(&previous, &current, Duration::from_secs(2));
+
+        assert_eq!(diagnostics.active_connections, 3);
+        assert_eq!(diagnostics.packets_in_per_sec, 10.0);
+        assert_eq!(diagnostics.bytes_out_per_sec, 2_000.0);
+        assert_eq!(diagnostics.throttled_per_sec, 2.0);
+        assert_eq!(diagnostics.packets_out_per_sec, 0.0);
+    }
+
+    #[test]
+    fn capture_reads_manager_counters() {
+        let manager = ConnectionManager::new(0);
+        let (sender, _) = crossbeam_channel::bounded(1);
+        manager.register(
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 7000)),
+            ProtocolSettings::default(),
+            sender,
+        );
+        manager.stats().record_packet_received(12);
+        manager.stats().record_packet_sent();
+        manager.stats().record_throttled();
+
+        let snapshot = NetworkSnapshot::capture(&manager);
+        assert_eq!(snapshot.active_connections, 1);
+        assert_eq!(snapshot.packets_received, 1);
+        assert_eq!(snapshot.bytes_received, 12);
+        assert_eq!(snapshot.packets_sent, 1);
+        assert_eq!(snapshot.throttled, 1);
+    }
+
+    #[tokio::test]
+    async fn sample_reports_activity_each_interval() {
+        let manager = Arc::new(ConnectionManager::new(0));
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        for _ in 0..5 {
+            manager.stats().record_packet_received(100);
+        }
+
+        // The baseline is taken on the first tick, so only activity after
+        // it shows up in the first report.
+        let sampler = tokio::spawn(sample(
+            manager.clone(),
+            Duration::from_millis(50),
+            move |diagnostics| {
+                sender.send(diagnostics).ok();
+            },
+        ));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        manager.stats().record_packet_received(100);
+        manager.stats().record_packet_sent();
+
+        let first = receiver.recv().await.expect("sampler should report");
+        assert!(first.packets_in_per_sec > 0.0);
+        assert!(first.packets_out_per_sec > 0.0);
+        assert!(first.bytes_in_per_sec > first.packets_in_per_sec);
+
+        let second = receiver
+            .recv()
+            .await
+            .expect("sampler should keep reporting");
+        assert_eq!(second.packets_in_per_sec, 0.0);
+        assert_eq!(second.packets_out_per_sec, 0.0);
+
+        sampler.abort();
+    }
+}
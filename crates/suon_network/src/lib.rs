@@ -1,5 +1,6 @@
 pub mod connection;
 pub mod connections;
+pub mod diagnostics;
 pub mod error;
 pub mod manager;
 mod plugin;
@@ -9,8 +10,14 @@ pub mod server;
 mod settings;
 mod settings_error;
 
+pub use diagnostics::NetworkDiagnosticsPlugin;
 pub use manager::NetworkManager;
 pub use plugin::NetworkPlugin;
+pub use server::accept_filter::AcceptFilter;
+pub use settings::{BufferPoolSettings, NetworkSettings, NetworkSettingsBuilder};
+pub use settings_error::SettingsError;
 
 #[cfg(test)]
 pub(crate) use pool::test_buffer_pool;
+#[cfg(test)]
+pub(crate) use settings::test_runtime;
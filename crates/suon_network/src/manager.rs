@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 
 use tracing::info;
 
@@ -10,7 +10,10 @@ use tracing::error;
 use crate::{
     connection::manager::ConnectionManager,
     error::NetworkError,
-    server::{binder::Binder, kind::ServerKind, settings::ServerSettings, shutdown::Shutdown},
+    server::{
+        binder::Binder, kind::ServerKind, listen_address::ListenAddress, settings::ServerSettings,
+        shutdown::Shutdown, tcp::reaper,
+    },
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -25,11 +28,14 @@ pub struct ServerInfo {
     pub port: u16,
     pub kind: ServerKind,
     pub status: ServerStatus,
+    /// Where the listener is bound, once it is.
+    pub local_addr: Option<SocketAddr>,
 }
 
 struct ManagedServer {
     shutdown: Shutdown,
     kind: ServerKind,
+    listen_address: ListenAddress,
 }
 
 #[derive(Resource)]
@@ -54,6 +60,10 @@ impl NetworkManager {
         &self.buffer_pool
     }
 
+    pub fn runtime(&self) -> &Arc<Runtime> {
+        &self.runtime
+    }
+
     pub fn spawn_server(
         &mut self,
         settings: ServerSettings,
@@ -65,12 +75,14 @@ impl NetworkManager {
         }
 
         let shutdown = Shutdown::new();
+        let listen_address = ListenAddress::new();
 
         self.servers.insert(
             port,
             ManagedServer {
                 shutdown: shutdown.clone(),
                 kind: settings.kind.clone(),
+                listen_address: listen_address.clone(),
             },
         );
 
@@ -89,11 +101,30 @@ impl NetworkManager {
             self.buffer_pool.clone(),
             connection_manager,
         )
+        .with_listen_address(listen_address)
         .launch();
 
         Ok(())
     }
 
+    /// Periodically removes the connections of `connection_manager`
+    /// whose reader session has exited without cleaning up after itself.
+    pub fn spawn_reaper(&self, connection_manager: Arc<ConnectionManager>) {
+        self.runtime.spawn(reaper::run(
+            connection_manager,
+            self.channel.clone(),
+            reaper::REAP_INTERVAL,
+        ));
+    }
+
+    /// The bound-address signal for the server spawned for `port`, to
+    /// await readiness or read the ephemeral port behind `port = 0`.
+    pub fn listen_address(&self, port: u16) -> Option<ListenAddress> {
+        self.servers
+            .get(&port)
+            .map(|managed_server| managed_server.listen_address.clone())
+    }
+
     pub fn stop(&mut self, port: u16) -> Result<(), NetworkError> {
         match self.servers.remove(&port) {
             Some(managed_server) => {
@@ -117,6 +148,7 @@ impl NetworkManager {
                     port: *port,
                     kind: managed_server.kind.clone(),
                     status,
+                    local_addr: managed_server.listen_address.get(),
                 }
             })
             .collect()
@@ -141,31 +173,27 @@ mod tests {
     use tokio::runtime::Runtime;
 
     use super::*;
-    use crate::server::{
-        kind::ServerKind,
-        tcp::{EncryptionSettings, ProtocolSettings},
-    };
+    use crate::server::tcp::TcpSettings;
     use std::time::Duration;
 
     fn dummy_settings() -> ServerSettings {
         ServerSettings {
             port: 0,
             address: "127.0.0.1".into(),
-            kind: ServerKind::Tcp {
-                protocol: ProtocolSettings::default(),
-                flush_interval: Duration::from_millis(10),
-                encryption: EncryptionSettings::default(),
+            kind: TcpSettings {
                 channel_capacity: 16,
                 max_buffer_size: 256,
                 max_connections: 5,
-                rate_burst: 50,
-            },
+                ..TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         }
     }
 
     fn make_manager() -> (NetworkManager, Arc<Runtime>, Channel) {
-        let runtime = Arc::new(Runtime::new().expect("failed to build test runtime"));
+        let runtime = crate::test_runtime();
         let channel = Channel::default();
         let buffer_pool = crate::test_buffer_pool();
         let manager = NetworkManager::new(runtime.clone(), channel.clone(), buffer_pool);
@@ -218,6 +246,32 @@ mod tests {
         assert!(manager.is_running(8888));
     }
 
+    #[test]
+    fn listen_address_reports_the_ephemeral_port() {
+        let (mut manager, runtime, _) = make_manager();
+        let connection_manager = Arc::new(ConnectionManager::new(0));
+        manager
+            .spawn_server(dummy_settings(), connection_manager)
+            .expect("test server spawn should succeed");
+
+        let listen_address = manager
+            .listen_address(0)
+            .expect("spawned server should have a listen address");
+        let bound = runtime.block_on(async {
+            tokio::time::timeout(Duration::from_secs(1), listen_address.wait())
+                .await
+                .expect("listener should bind promptly")
+        });
+        assert_ne!(bound.port(), 0);
+        assert_eq!(manager.status()[0].local_addr, Some(bound));
+
+        runtime.block_on(async {
+            tokio::net::TcpStream::connect(bound)
+                .await
+                .expect("should connect to the reported address")
+        });
+    }
+
     #[test]
     fn is_running_returns_false_for_unknown_port() {
         let (manager, ..) = make_manager();
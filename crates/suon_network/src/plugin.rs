@@ -7,28 +7,51 @@ use suon_lua::LuaVm;
 use tracing::error;
 
 use crate::{
-    connection::manager::ConnectionManager, connections::Connections, manager::NetworkManager,
-    pool::NetworkBufferPool, settings::NetworkSettings,
+    connection::{
+        budget::BufferBudget, manager::ConnectionManager, resumption::SessionTokens, tap::PacketTap,
+    },
+    connections::Connections,
+    manager::NetworkManager,
+    pool::NetworkBufferPool,
+    server::accept_filter::AcceptFilter,
+    settings::NetworkSettings,
 };
 
-pub struct NetworkPlugin;
+/// Starts the configured servers and registers the connection bindings.
+///
+/// By default the settings are loaded from the `NetworkSettings` file in
+/// the working directory; [`with_settings`](Self::with_settings) uses the
+/// given ones instead and never touches the file.
+#[derive(Default)]
+pub struct NetworkPlugin {
+    settings: Option<NetworkSettings>,
+}
 
 impl Plugin for NetworkPlugin {
     fn build(&self, app: &mut App) {
-        let settings = NetworkSettings::load();
+        let settings = self.settings();
 
-        let connection_manager = Arc::new(ConnectionManager::new(0));
+        let packet_tap = PacketTap::new(settings.packet_tap_capacity);
+        let session_tokens = SessionTokens::default();
+        let connection_manager = Arc::new(
+            ConnectionManager::new(0)
+                .with_packet_tap(packet_tap.clone())
+                .with_buffer_budget(BufferBudget::new(settings.max_total_buffer_bytes))
+                .with_session_tokens(session_tokens.clone()),
+        );
+        if let Some(filter) = app.try_get_resource::<AcceptFilter>() {
+            connection_manager.set_accept_filter(filter.clone());
+        }
         let connections = Connections {
             manager: connection_manager.clone(),
         };
         app.add_resource(connections.clone());
+        app.add_resource(packet_tap);
+        app.add_resource(session_tokens);
 
         let runtime = Arc::new(
-            tokio::runtime::Builder::new_multi_thread()
-                .worker_threads(settings.worker_threads)
-                .enable_io()
-                .enable_time()
-                .build()
+            settings
+                .build_runtime()
                 .expect("failed to build network tokio runtime"),
         );
 
@@ -38,6 +61,7 @@ impl Plugin for NetworkPlugin {
         ));
 
         let mut manager = NetworkManager::new(runtime, app.channel(), buffer_pool.clone());
+        manager.spawn_reaper(connection_manager.clone());
         app.add_resource(NetworkBufferPool(buffer_pool));
 
         for server_settings in settings.server {
@@ -57,6 +81,18 @@ impl Plugin for NetworkPlugin {
 }
 
 impl NetworkPlugin {
+    /// A plugin that runs with `settings` rather than loading them from
+    /// disk.
+    pub fn with_settings(settings: NetworkSettings) -> Self {
+        NetworkPlugin {
+            settings: Some(settings),
+        }
+    }
+
+    fn settings(&self) -> NetworkSettings {
+        self.settings.clone().unwrap_or_else(NetworkSettings::load)
+    }
+
     fn register_connection_bindings(app: &mut App, connections: Connections) {
         let vm = app.get_resource::<LuaVm>();
 
@@ -89,6 +125,27 @@ impl NetworkPlugin {
                 error!(target: "App", "Failed to register Connection:send: {err}");
             }
 
+            let send_and_close_fn = {
+                let connection_send = connections.clone();
+                match lua.create_function(move |_, (table, data): (Table, String)| {
+                    let id: u64 = table.raw_get("_id")?;
+                    let bytes = data.as_bytes().to_vec();
+                    connection_send.send_and_close(id, bytes).map_err(|e| {
+                        Error::external(format!("Connection:sendAndClose failed: {e}"))
+                    })
+                }) {
+                    Ok(func) => func,
+                    Err(err) => {
+                        error!(target: "App", "Failed to create Connection:sendAndClose function: {err}");
+                        return;
+                    }
+                }
+            };
+
+            if let Err(err) = connection.set("sendAndClose", send_and_close_fn) {
+                error!(target: "App", "Failed to register Connection:sendAndClose: {err}");
+            }
+
             let close_fn = {
                 let connection_close = connections.clone();
                 match lua.create_function(move |_, table: Table| {
@@ -110,7 +167,7 @@ impl NetworkPlugin {
             }
 
             let send_raw_fn = {
-                let connection_send_raw = connections;
+                let connection_send_raw = connections.clone();
                 match lua.create_function(move |_, (table, data): (Table, String)| {
                     let id: u64 = table.raw_get("_id")?;
                     let bytes = data.as_bytes().to_vec();
@@ -129,6 +186,157 @@ impl NetworkPlugin {
             if let Err(err) = connection.set("sendRaw", send_raw_fn) {
                 error!(target: "App", "Failed to register Connection:sendRaw: {err}");
             }
+
+            let complete_handshake_fn = {
+                let connection_handshake = connections.clone();
+                match lua.create_function(move |_, table: Table| {
+                    let id: u64 = table.raw_get("_id")?;
+                    connection_handshake.complete_handshake(id).map_err(|e| {
+                        Error::external(format!("Connection:completeHandshake failed: {e}"))
+                    })
+                }) {
+                    Ok(func) => func,
+                    Err(err) => {
+                        error!(target: "App", "Failed to create Connection:completeHandshake function: {err}");
+                        return;
+                    }
+                }
+            };
+
+            if let Err(err) = connection.set("completeHandshake", complete_handshake_fn) {
+                error!(target: "App", "Failed to register Connection:completeHandshake: {err}");
+            }
+
+            let authenticate_fn = {
+                let connection_auth = connections.clone();
+                match lua.create_function(move |_, table: Table| {
+                    let id: u64 = table.raw_get("_id")?;
+                    connection_auth.authenticate(id).map_err(|e| {
+                        Error::external(format!("Connection:markAuthenticated failed: {e}"))
+                    })
+                }) {
+                    Ok(func) => func,
+                    Err(err) => {
+                        error!(target: "App", "Failed to create Connection:markAuthenticated function: {err}");
+                        return;
+                    }
+                }
+            };
+
+            if let Err(err) = connection.set("markAuthenticated", authenticate_fn) {
+                error!(target: "App", "Failed to register Connection:markAuthenticated: {err}");
+            }
+
+            let issue_resumption_token_fn = {
+                let connection_tokens = connections.clone();
+                match lua.create_function(move |lua, table: Table| {
+                    let id: u64 = table.raw_get("_id")?;
+                    let token = connection_tokens.issue_resumption_token(id).map_err(|e| {
+                        Error::external(format!("Connection:issueResumptionToken failed: {e}"))
+                    })?;
+                    lua.create_string(token.0)
+                }) {
+                    Ok(func) => func,
+                    Err(err) => {
+                        error!(target: "App", "Failed to create Connection:issueResumptionToken function: {err}");
+                        return;
+                    }
+                }
+            };
+
+            if let Err(err) = connection.set("issueResumptionToken", issue_resumption_token_fn) {
+                error!(target: "App", "Failed to register Connection:issueResumptionToken: {err}");
+            }
+
+            let set_protocol_version_fn = {
+                let connection_version = connections;
+                match lua.create_function(move |_, (table, version): (Table, u16)| {
+                    let id: u64 = table.raw_get("_id")?;
+                    connection_version
+                        .set_protocol_version(id, version)
+                        .map_err(|e| {
+                            Error::external(format!("Connection:setProtocolVersion failed: {e}"))
+                        })
+                }) {
+                    Ok(func) => func,
+                    Err(err) => {
+                        error!(target: "App", "Failed to create Connection:setProtocolVersion function: {err}");
+                        return;
+                    }
+                }
+            };
+
+            if let Err(err) = connection.set("setProtocolVersion", set_protocol_version_fn) {
+                error!(target: "App", "Failed to register Connection:setProtocolVersion: {err}");
+            }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+    use crate::server::{listen_address::ListenAddress, tcp::TcpSettings};
+
+    #[test]
+    fn with_settings_skips_the_settings_file() {
+        let settings = NetworkSettings::builder()
+            .tcp_server(17171, TcpSettings::default())
+            .build()
+            .expect("custom settings should be valid");
+
+        let kind = settings.server[0].kind.clone();
+
+        let mut app = App::new();
+        app.add_resource(LuaVm::new());
+        app.add_plugin(NetworkPlugin::with_settings(settings));
+
+        let manager = app.get_resource::<NetworkManager>();
+        let status = manager.status();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].port, 17171);
+        assert_eq!(status[0].kind, kind);
+        assert!(manager.listen_address(7171).is_none());
+        let listen_address = manager
+            .listen_address(17171)
+            .expect("the configured server should have been spawned");
+        assert_eq!(wait_for_bind(&listen_address).port(), 17171);
+    }
+
+    #[test]
+    fn port_zero_server_reports_its_bound_port() {
+        let settings = NetworkSettings::builder()
+            .tcp_server(0, TcpSettings::default())
+            .build()
+            .expect("port 0 should be valid");
+
+        let mut app = App::new();
+        app.add_resource(LuaVm::new());
+        app.add_plugin(NetworkPlugin::with_settings(settings));
+
+        let listen_address = app
+            .get_resource::<NetworkManager>()
+            .listen_address(0)
+            .expect("the port 0 server should have been spawned");
+        let bound = wait_for_bind(&listen_address);
+
+        assert_ne!(bound.port(), 0);
+        std::net::TcpStream::connect(bound).expect("bound port should accept connections");
+    }
+
+    fn wait_for_bind(listen_address: &ListenAddress) -> SocketAddr {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        loop {
+            if let Some(bound) = listen_address.get() {
+                return bound;
+            }
+            assert!(Instant::now() < deadline, "listener should bind promptly");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
@@ -1,14 +1,44 @@
+use tokio::sync::oneshot;
+
+use crate::connection::handle::WriteError;
+
 pub enum Command {
     /// Encrypt and frame the data using the current protocol settings.
+    ///
+    /// An empty payload still produces a frame (a header announcing zero
+    /// bytes of payload).
     Send(Vec<u8>),
+    /// Frame every payload back to back, as with [`Command::Send`], and
+    /// only then consider flushing, so the group is never split across
+    /// threshold-triggered flushes. If one payload does not fit a frame,
+    /// none of the group is sent.
+    SendBatch(Vec<Vec<u8>>),
     /// Send raw bytes without any framing or encryption.
+    ///
+    /// An empty payload writes nothing.
     SendRaw(Vec<u8>),
     /// Replace the XTEA encryption key.
+    ///
+    /// Packets are encrypted as they are framed, in queue order, so those
+    /// queued before this command (such as the login response) go out in
+    /// plaintext even when they share a flush with encrypted ones.
     SetXteaKey([u32; 4]),
     /// Enable or disable XTEA encryption.
     SetEncryptionEnabled(bool),
+    /// Enable or disable the adler32 checksum for both directions.
+    SetChecksumEnabled(bool),
     /// Change the minimum payload size that triggers compression.
     SetCompressionThreshold(usize),
+    /// Write and flush everything buffered so far, whatever the
+    /// connection's flush policy.
+    Flush,
+    /// Frame the data as with [`Command::Send`], flush it straight away
+    /// and report the outcome once it has been written to the socket.
+    SendFlushed(Vec<u8>, oneshot::Sender<Result<(), WriteError>>),
+    /// Frame the data as with [`Command::Send`], then close the
+    /// connection as with [`Command::Close`], so the packet is written and
+    /// flushed before the socket shuts down.
+    SendAndClose(Vec<u8>),
     /// Close the connection gracefully.
     Close,
     /// Close the connection with a human-readable reason.
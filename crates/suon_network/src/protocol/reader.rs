@@ -5,12 +5,16 @@ use suon_rsa::Rsa;
 use suon_xtea::{ExpandedKey, expand};
 
 use crate::server::tcp::protocol::{
-    MIN_XTEA_BODY, ProtocolSettings, SEQUENCE_FIELD_LEN, XTEA_KEY_BYTES,
+    self, FRAGMENT_HEADER_LEN, FRAGMENT_MORE, FRAGMENT_OPCODE, MAX_WIDE_FRAME_BODY, MIN_XTEA_BODY,
+    ProtocolSettings, SEQUENCE_FIELD_LEN, XTEA_KEY_BYTES,
 };
 
 /// Bit flag indicating the packet payload is zlib-compressed.
 const COMPRESSION_FLAG: u32 = 0x8000_0000;
 
+/// XTEA block size; the padding byte is always smaller than this.
+const XTEA_BLOCK_LEN: usize = 8;
+
 #[derive(Debug, thiserror::Error)]
 pub enum ProcessError {
     #[error("invalid packet size")]
@@ -19,10 +23,33 @@ pub enum ProcessError {
     ChecksumMismatch { expected: u32, actual: u32 },
     #[error("RSA decryption failed")]
     RsaError,
-    #[error("XTEA decryption failed")]
+    #[error("XTEA decryption failed: no key set")]
     XteaError,
+    #[error("XTEA decryption failed: {0}")]
+    Xtea(#[from] suon_xtea::XteaError),
     #[error("not enough data")]
     NotEnoughData,
+    #[error("invalid XTEA padding length {padding}")]
+    InvalidPadding { padding: usize },
+    #[error("fragmented packet exceeds the {limit} byte limit")]
+    FragmentedTooLarge { limit: usize },
+}
+
+impl ProcessError {
+    /// A likely cause worth logging next to the error, where the error
+    /// alone leaves operators guessing.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ProcessError::Xtea(suon_xtea::XteaError::InvalidDataLength(_)) => {
+                Some("body is not block-aligned, the framing is probably corrupt")
+            }
+            ProcessError::InvalidPadding { .. } => {
+                Some("padding decrypted to garbage, the XTEA key is probably wrong")
+            }
+            ProcessError::XteaError => Some("encrypted packet arrived before the key exchange"),
+            _ => None,
+        }
+    }
 }
 
 /// Outcome of [`PacketReader::process_in_place`].
@@ -31,27 +58,47 @@ pub enum ProcessOutcome {
     /// The buffer now contains the unwrapped payload (may be shorter
     /// than the original).  Ready to dispatch.
     Complete,
-    /// Intermediate state (e.g. RSA handshake); the buffer is
-    /// unchanged and should be skipped this iteration.
+    /// Intermediate state (e.g. RSA handshake, or a fragment whose packet
+    /// is not complete yet); the buffer should be skipped this iteration.
     Skip,
 }
 
+/// Whether the last processed packet carried an adler32 checksum.
+///
+/// A checksum of zero means "no checksum" on the wire, so it is reported
+/// as [`Absent`](Self::Absent) just like a frame without the field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    /// No checksum was sent, or the frame kind has no checksum field.
+    Absent,
+    /// The checksum was present and matched the payload.
+    Verified(u32),
+}
+
 pub struct PacketReader {
     protocol: ProtocolSettings,
+    checksum_status: ChecksumStatus,
     xtea_key: Option<ExpandedKey>,
     xtea_enabled: bool,
     rsa_key: Option<Rsa>,
     rsa_done: bool,
+    fragmentation: bool,
+    fragment_limit: usize,
+    fragments: Vec<u8>,
 }
 
 impl PacketReader {
     pub fn new(protocol: ProtocolSettings) -> Self {
         PacketReader {
             protocol,
+            checksum_status: ChecksumStatus::Absent,
             xtea_key: None,
             xtea_enabled: protocol.uses_xtea,
             rsa_key: None,
             rsa_done: !protocol.uses_rsa,
+            fragmentation: false,
+            fragment_limit: MAX_WIDE_FRAME_BODY,
+            fragments: Vec::new(),
         }
     }
 
@@ -75,6 +122,16 @@ impl PacketReader {
         self
     }
 
+    pub fn with_fragmentation(mut self, allow: bool) -> Self {
+        self.fragmentation = allow;
+        self
+    }
+
+    pub fn with_fragment_limit(mut self, limit: usize) -> Self {
+        self.fragment_limit = limit;
+        self
+    }
+
     pub fn set_rsa_done(&mut self, done: bool) {
         self.rsa_done = done;
     }
@@ -91,6 +148,73 @@ impl PacketReader {
         self.xtea_key = Some(expand(&key));
     }
 
+    /// When enabled, packets opening with [`FRAGMENT_OPCODE`] are
+    /// fragments of a larger packet, as framed by
+    /// [`PacketWriter`](crate::protocol::PacketWriter): they are collected
+    /// until the last one arrives and then completed as the joined packet.
+    pub fn set_fragmentation(&mut self, allow: bool) {
+        self.fragmentation = allow;
+    }
+
+    /// Largest packet fragments may be joined into, [`MAX_WIDE_FRAME_BODY`]
+    /// unless set.
+    pub fn set_fragment_limit(&mut self, limit: usize) {
+        self.fragment_limit = limit;
+    }
+
+    /// Expect (or stop expecting) the adler32 prefix on non-XTEA packets.
+    pub fn set_checksum_enabled(&mut self, enabled: bool) {
+        self.protocol.has_checksum = enabled;
+    }
+
+    /// Checksum outcome of the last packet
+    /// [`process_in_place`](Self::process_in_place) completed. Only a
+    /// packet that went through checksum framing can be
+    /// [`Verified`](ChecksumStatus::Verified); XTEA, RSA and plain frames
+    /// are always [`Absent`](ChecksumStatus::Absent).
+    pub fn checksum_status(&self) -> ChecksumStatus {
+        self.checksum_status
+    }
+
+    /// Opcode and length of the payload `body` will decode to, worked out
+    /// from the frame without decoding it, so size limits can be enforced
+    /// before the frame is decrypted. An XTEA frame only has its first
+    /// block decrypted for this.
+    ///
+    /// Returns `None` when the payload cannot be told up front: during
+    /// the RSA handshake, for compressed frames and for fragments.
+    pub fn peek_payload(&self, body: &[u8]) -> Option<(u8, usize)> {
+        if !self.rsa_done {
+            return None;
+        }
+
+        let (opcode, len) = if self.xtea_enabled && self.protocol.uses_xtea {
+            let seq_field = read_u32_le(body, 0).ok()?;
+            if seq_field & COMPRESSION_FLAG != 0 {
+                return None;
+            }
+
+            let key = self.xtea_key.as_ref()?;
+            let mut block: [u8; XTEA_BLOCK_LEN] = body
+                .get(SEQUENCE_FIELD_LEN..SEQUENCE_FIELD_LEN + XTEA_BLOCK_LEN)?
+                .try_into()
+                .ok()?;
+            suon_xtea::decrypt(&mut block, key).ok()?;
+
+            let padding = block[0] as usize;
+            let len = (body.len() - SEQUENCE_FIELD_LEN).checked_sub(1 + padding)?;
+            (block[1], len)
+        } else if self.protocol.has_checksum {
+            let len = body.len().checked_sub(SEQUENCE_FIELD_LEN)?;
+            (*body.get(SEQUENCE_FIELD_LEN)?, len)
+        } else {
+            (*body.first()?, body.len())
+        };
+
+        let fragment = self.fragmentation && opcode == FRAGMENT_OPCODE;
+        (len > 0 && !fragment).then_some((opcode, len))
+    }
+
     /// Process a packet in-place, leaving `body` with the decrypted payload.
     ///
     /// # Errors
@@ -98,10 +222,27 @@ impl PacketReader {
     /// Returns [`ProcessError::InvalidSize`] if the body is empty or the
     /// unpadded result is empty, [`ProcessError::ChecksumMismatch`] if
     /// the adler32 checksum doesn't match, [`ProcessError::RsaError`] if
-    /// RSA decryption fails, [`ProcessError::XteaError`] if XTEA
-    /// decryption fails, or [`ProcessError::NotEnoughData`] if the body
-    /// is too short for the expected protocol step.
+    /// RSA decryption fails, [`ProcessError::XteaError`] if no XTEA key
+    /// is set, [`ProcessError::Xtea`] if the encrypted part is not
+    /// block-aligned, or [`ProcessError::NotEnoughData`] if the body
+    /// is too short for the expected protocol step. With fragmentation
+    /// enabled, a malformed fragment returns [`ProcessError::InvalidSize`]
+    /// and a packet joined past the
+    /// [fragment limit](Self::set_fragment_limit) returns
+    /// [`ProcessError::FragmentedTooLarge`].
     pub fn process_in_place(&mut self, body: &mut Vec<u8>) -> Result<ProcessOutcome, ProcessError> {
+        let outcome = self.unwrap_in_place(body)?;
+        if outcome == ProcessOutcome::Complete
+            && self.fragmentation
+            && body.first() == Some(&FRAGMENT_OPCODE)
+        {
+            return self.reassemble_in_place(body);
+        }
+        Ok(outcome)
+    }
+
+    fn unwrap_in_place(&mut self, body: &mut Vec<u8>) -> Result<ProcessOutcome, ProcessError> {
+        self.checksum_status = ChecksumStatus::Absent;
         if body.is_empty() {
             return Err(ProcessError::InvalidSize);
         }
@@ -121,25 +262,45 @@ impl PacketReader {
         Ok(ProcessOutcome::Complete)
     }
 
+    /// Collect the fragment in `body`, leaving the joined packet in it
+    /// once the last fragment is in.
+    fn reassemble_in_place(&mut self, body: &mut Vec<u8>) -> Result<ProcessOutcome, ProcessError> {
+        let Some(&flag) = body.get(1) else {
+            self.fragments.clear();
+            return Err(ProcessError::InvalidSize);
+        };
+
+        let chunk = &body[FRAGMENT_HEADER_LEN..];
+        if self.fragments.len() + chunk.len() > self.fragment_limit {
+            self.fragments.clear();
+            return Err(ProcessError::FragmentedTooLarge {
+                limit: self.fragment_limit,
+            });
+        }
+        self.fragments.extend_from_slice(chunk);
+        if flag == FRAGMENT_MORE {
+            return Ok(ProcessOutcome::Skip);
+        }
+
+        if self.fragments.is_empty() {
+            return Err(ProcessError::InvalidSize);
+        }
+        std::mem::swap(body, &mut self.fragments);
+        self.fragments.clear();
+        Ok(ProcessOutcome::Complete)
+    }
+
     /// Strip and verify the checksum prefix, shifting payload in-place.
     fn process_checksum_in_place(
-        &self,
+        &mut self,
         body: &mut Vec<u8>,
     ) -> Result<ProcessOutcome, ProcessError> {
-        if body.len() < SEQUENCE_FIELD_LEN {
-            return Err(ProcessError::NotEnoughData);
-        }
-
-        let stored_checksum = u32::from_le_bytes(
-            body[..SEQUENCE_FIELD_LEN]
-                .try_into()
-                .expect("SEQ_FIELD_LEN is 4 bytes"),
-        );
+        let stored_checksum = read_u32_le(body, 0)?;
 
         let payload_len = body.len() - SEQUENCE_FIELD_LEN;
 
         if stored_checksum != 0 {
-            let computed = suon_adler32::generate(&body[SEQUENCE_FIELD_LEN..]);
+            let computed = suon_adler32::generate(protocol::checksummed_region(body));
             if stored_checksum != computed {
                 return Err(ProcessError::ChecksumMismatch {
                     expected: stored_checksum,
@@ -154,6 +315,9 @@ impl PacketReader {
 
         body.copy_within(SEQUENCE_FIELD_LEN.., 0);
         body.truncate(payload_len);
+        if stored_checksum != 0 {
+            self.checksum_status = ChecksumStatus::Verified(stored_checksum);
+        }
         Ok(ProcessOutcome::Complete)
     }
 
@@ -173,12 +337,13 @@ impl PacketReader {
 
             // First byte is 0 → XTEA key exchange.
             if decrypted.len() > XTEA_KEY_BYTES {
-                let k0 = u32::from_le_bytes(decrypted[1..5].try_into().expect("XTEA_KEY_BYTES=16"));
-                let k1 = u32::from_le_bytes(decrypted[5..9].try_into().expect("5..9 is 4 bytes"));
-                let k2 = u32::from_le_bytes(decrypted[9..13].try_into().expect("9..13 is 4 bytes"));
-                let k3 =
-                    u32::from_le_bytes(decrypted[13..17].try_into().expect("13..17 is 4 bytes"));
-                self.set_xtea_key([k0, k1, k2, k3]);
+                let key = [
+                    read_u32_le(&decrypted, 1)?,
+                    read_u32_le(&decrypted, 5)?,
+                    read_u32_le(&decrypted, 9)?,
+                    read_u32_le(&decrypted, 13)?,
+                ];
+                self.set_xtea_key(key);
             }
 
             self.rsa_done = true;
@@ -199,36 +364,34 @@ impl PacketReader {
             return Err(ProcessError::NotEnoughData);
         }
 
-        let seq_field = u32::from_le_bytes(
-            body[..SEQUENCE_FIELD_LEN]
-                .try_into()
-                .expect("SEQ_FIELD_LEN is 4 bytes"),
-        );
+        let seq_field = read_u32_le(body, 0)?;
 
         let encrypted_len = body.len() - SEQUENCE_FIELD_LEN;
-        if encrypted_len == 0 || !encrypted_len.is_multiple_of(8) {
+        if encrypted_len == 0 {
             return Err(ProcessError::NotEnoughData);
         }
 
         let key = self.xtea_key.as_ref().ok_or(ProcessError::XteaError)?;
-        suon_xtea::decrypt(&mut body[SEQUENCE_FIELD_LEN..], key)
-            .map_err(|_| ProcessError::XteaError)?;
+        suon_xtea::decrypt(&mut body[SEQUENCE_FIELD_LEN..], key)?;
 
+        // `xtea_pad` never adds a whole block of padding, so anything past
+        // 7 means a corrupt frame or the wrong key.
         let padding = body[SEQUENCE_FIELD_LEN] as usize;
+        if padding >= XTEA_BLOCK_LEN {
+            return Err(ProcessError::InvalidPadding { padding });
+        }
 
-        let data_end = body.len().saturating_sub(padding);
-        if data_end <= SEQUENCE_FIELD_LEN + 1 {
+        let unpadded_len = encrypted_len
+            .checked_sub(1 + padding)
+            .ok_or(ProcessError::InvalidPadding { padding })?;
+        if unpadded_len == 0 {
             return Err(ProcessError::InvalidSize);
         }
 
-        let unpadded_len = data_end - SEQUENCE_FIELD_LEN - 1;
-        body.copy_within(SEQUENCE_FIELD_LEN + 1..data_end, 0);
+        let data_start = SEQUENCE_FIELD_LEN + 1;
+        body.copy_within(data_start..data_start + unpadded_len, 0);
         body.truncate(unpadded_len);
 
-        if body.is_empty() {
-            return Err(ProcessError::InvalidSize);
-        }
-
         // Optional zlib decompression (only allocation in this path).
         if seq_field & COMPRESSION_FLAG != 0 {
             let mut decoder = DeflateDecoder::new(&body[..]);
@@ -243,6 +406,17 @@ impl PacketReader {
     }
 }
 
+/// Reads a little-endian `u32` at `offset`, or [`ProcessError::NotEnoughData`]
+/// if `bytes` ends before it. Never panics, whatever the input length.
+fn read_u32_le(bytes: &[u8], offset: usize) -> Result<u32, ProcessError> {
+    offset
+        .checked_add(size_of::<u32>())
+        .and_then(|end| bytes.get(offset..end))
+        .and_then(|field| field.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or(ProcessError::NotEnoughData)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -313,6 +487,57 @@ mod tests {
         assert_eq!(&buf[..], b"hello");
     }
 
+    #[test]
+    fn writer_and_reader_checksum_the_same_region() {
+        let settings = ProtocolSettings {
+            header_size: 2,
+            has_checksum: true,
+            uses_xtea: false,
+            uses_rsa: false,
+        };
+        let mut writer = crate::protocol::PacketWriter::new(settings, 1024);
+        writer.send(b"region");
+        let framed = writer.take_buffer();
+
+        // Drop the size header, as the reader session does.
+        let mut body = framed[protocol::SIZE_FIELD_LEN..].to_vec();
+        let stored = read_u32_le(&body, 0).expect("framed body should carry a checksum");
+        assert_eq!(
+            stored,
+            suon_adler32::generate(protocol::checksummed_region(&body))
+        );
+
+        let mut reader = PacketReader::new(settings);
+        assert_eq!(
+            reader
+                .process_in_place(&mut body)
+                .expect("reader should accept the writer's checksum"),
+            ProcessOutcome::Complete
+        );
+        assert_eq!(&body[..], b"region");
+        assert_eq!(reader.checksum_status(), ChecksumStatus::Verified(stored));
+    }
+
+    #[test]
+    fn checksum_can_be_disabled_at_runtime() {
+        let mut reader = PacketReader::new(ProtocolSettings {
+            header_size: 2,
+            has_checksum: true,
+            uses_xtea: false,
+            uses_rsa: false,
+        });
+        reader.set_checksum_enabled(false);
+
+        let mut buf = b"hello".to_vec();
+        assert_eq!(
+            reader
+                .process_in_place(&mut buf)
+                .expect("reader should pass the payload through without a checksum"),
+            ProcessOutcome::Complete
+        );
+        assert_eq!(&buf[..], b"hello");
+    }
+
     #[test]
     fn status_passthrough_binary() {
         let mut reader = PacketReader::new(ProtocolSettings {
@@ -390,6 +615,7 @@ mod tests {
             ProcessOutcome::Complete
         );
         assert_eq!(&proc_buf[..], &data[..]);
+        assert_eq!(reader.checksum_status(), ChecksumStatus::Verified(checksum));
     }
 
     #[test]
@@ -430,6 +656,54 @@ mod tests {
             ProcessOutcome::Complete
         );
         assert_eq!(&proc_buf[..], &body[4..]);
+        assert_eq!(reader.checksum_status(), ChecksumStatus::Absent);
+    }
+
+    #[test]
+    fn checksum_status_is_reset_for_every_packet() {
+        let mut reader = PacketReader::new(ProtocolSettings {
+            header_size: 2,
+            has_checksum: true,
+            uses_xtea: false,
+            uses_rsa: false,
+        });
+        let checksum = suon_adler32::generate(b"a");
+        let mut checksummed = checksum.to_le_bytes().to_vec();
+        checksummed.push(b'a');
+
+        reader
+            .process_in_place(&mut checksummed)
+            .expect("checksummed packet should process");
+        assert_eq!(reader.checksum_status(), ChecksumStatus::Verified(checksum));
+
+        reader
+            .process_in_place(&mut vec![0, 0, 0, 0, b'b'])
+            .expect("zero-checksum packet should process");
+        assert_eq!(reader.checksum_status(), ChecksumStatus::Absent);
+
+        reader.set_checksum_enabled(false);
+        reader
+            .process_in_place(&mut checksummed.clone())
+            .expect("checksum-less packet should process");
+        assert_eq!(reader.checksum_status(), ChecksumStatus::Absent);
+    }
+
+    #[test]
+    fn xtea_packets_report_absent_checksum() {
+        let key = test_key();
+        let mut reader = PacketReader::new(ProtocolSettings {
+            header_size: 6,
+            has_checksum: true,
+            uses_xtea: true,
+            uses_rsa: false,
+        })
+        .with_xtea_key(key);
+
+        let mut body = build_xtea_body(&key, b"move", 0);
+        reader
+            .process_in_place(&mut body)
+            .expect("XTEA packet should process");
+        assert_eq!(reader.checksum_status(), ChecksumStatus::Absent);
     }
 
     #[test]
@@ -500,6 +774,47 @@ mod tests {
         assert_eq!(&proc_buf[..], b"test data!");
     }
 
+    #[test]
+    fn peek_payload_matches_the_processed_payload() {
+        let key = test_key();
+        let mut reader = PacketReader::new(ProtocolSettings {
+            header_size: 6,
+            has_checksum: true,
+            uses_xtea: true,
+            uses_rsa: true,
+        })
+        .with_rsa_done(true);
+        assert_eq!(
+            reader.peek_payload(&build_xtea_body(&key, b"test data!", 0)),
+            None,
+            "nothing can be decrypted before the key is set"
+        );
+
+        reader.set_xtea_key(key);
+        for plaintext in [&b"x"[..], b"test data!", &[0x64; 300]] {
+            let mut body = build_xtea_body(&key, plaintext, 0);
+            let peeked = reader.peek_payload(&body);
+            reader
+                .process_in_place(&mut body)
+                .expect("reader should process XTEA data");
+            assert_eq!(peeked, Some((body[0], body.len())));
+        }
+
+        let compressed = build_xtea_body(&key, b"test data!", COMPRESSION_FLAG);
+        assert_eq!(reader.peek_payload(&compressed), None);
+
+        reader.set_xtea_enabled(false);
+        assert_eq!(
+            reader.peek_payload(&[0, 0, 0, 0, 0x1E, 1, 2]),
+            Some((0x1E, 3))
+        );
+        reader.set_fragmentation(true);
+        assert_eq!(
+            reader.peek_payload(&[0, 0, 0, 0, FRAGMENT_OPCODE, FRAGMENT_MORE]),
+            None
+        );
+    }
+
     #[test]
     fn game_xtea_with_seq_field() {
         let key = test_key();
@@ -550,6 +865,39 @@ mod tests {
         assert_eq!(&proc_buf[..], &plaintext[..]);
     }
 
+    #[test]
+    fn checksum_body_one_byte_short_is_not_enough() {
+        let mut reader = PacketReader::new(ProtocolSettings {
+            header_size: 2,
+            has_checksum: true,
+            uses_xtea: false,
+            uses_rsa: false,
+        });
+
+        assert!(matches!(
+            reader.process_in_place(&mut vec![0xFF; SEQUENCE_FIELD_LEN - 1]),
+            Err(ProcessError::NotEnoughData)
+        ));
+    }
+
+    #[test]
+    fn read_u32_le_rejects_out_of_range_offsets() {
+        let bytes = [0x78, 0x56, 0x34, 0x12, 0xFF];
+
+        assert_eq!(
+            read_u32_le(&bytes, 0).expect("field fits the buffer"),
+            0x1234_5678
+        );
+        assert!(matches!(
+            read_u32_le(&bytes, 2),
+            Err(ProcessError::NotEnoughData)
+        ));
+        assert!(matches!(
+            read_u32_le(&bytes, usize::MAX),
+            Err(ProcessError::NotEnoughData)
+        ));
+    }
+
     #[test]
     fn xtea_body_too_short() {
         let mut reader = PacketReader::new(ProtocolSettings {
@@ -648,10 +996,101 @@ mod tests {
         let mut proc_buf = body.clone();
         assert!(matches!(
             reader.process_in_place(&mut proc_buf),
+            Err(ProcessError::InvalidPadding { .. })
+        ));
+    }
+
+    #[test]
+    fn xtea_misaligned_body_is_told_apart_from_a_wrong_key() {
+        let misaligned = xtea_reader(test_key())
+            .process_in_place(&mut vec![0; SEQUENCE_FIELD_LEN + 5])
+            .expect_err("a 5-byte encrypted part is not block-aligned");
+        assert!(matches!(
+            misaligned,
+            ProcessError::Xtea(suon_xtea::XteaError::InvalidDataLength(5))
+        ));
+
+        let mut body = build_xtea_body(&[1, 2, 3, 4], b"secret", 0);
+        let wrong_key = xtea_reader([5, 6, 7, 8])
+            .process_in_place(&mut body)
+            .expect_err("the wrong key should garble the padding");
+        assert!(matches!(wrong_key, ProcessError::InvalidPadding { .. }));
+
+        assert!(misaligned.to_string().contains("not a multiple of 8"));
+        assert_ne!(misaligned.to_string(), wrong_key.to_string());
+        let (framing, key) = (misaligned.hint(), wrong_key.hint());
+        assert!(framing.is_some_and(|hint| hint.contains("framing")));
+        assert!(key.is_some_and(|hint| hint.contains("key")));
+        assert_eq!(ProcessError::NotEnoughData.hint(), None);
+    }
+
+    /// Encrypts `block` as the only XTEA block of a frame body.
+    fn build_raw_xtea_body(key: &Key, mut block: [u8; 8]) -> Vec<u8> {
+        encrypt(&mut block, &expand(key)).expect("one block is block-aligned");
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&block);
+        body
+    }
+
+    fn xtea_reader(key: Key) -> PacketReader {
+        let mut reader = PacketReader::new(ProtocolSettings {
+            header_size: 6,
+            has_checksum: true,
+            uses_xtea: true,
+            uses_rsa: true,
+        });
+        reader.set_xtea_key(key);
+        reader.rsa_done = true;
+        reader
+    }
+
+    #[test]
+    fn xtea_padding_of_a_whole_block_is_invalid() {
+        let key = test_key();
+        let mut body = build_raw_xtea_body(&key, [8, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert!(matches!(
+            xtea_reader(key).process_in_place(&mut body),
+            Err(ProcessError::InvalidPadding { padding: 8 })
+        ));
+    }
+
+    #[test]
+    fn xtea_padding_past_the_buffer_is_invalid() {
+        let key = test_key();
+        let mut body = build_raw_xtea_body(&key, [0xFF, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert!(matches!(
+            xtea_reader(key).process_in_place(&mut body),
+            Err(ProcessError::InvalidPadding { padding: 255 })
+        ));
+    }
+
+    #[test]
+    fn xtea_padding_covering_all_data_is_invalid_size() {
+        let key = test_key();
+        let mut body = build_raw_xtea_body(&key, [7, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33]);
+
+        assert!(matches!(
+            xtea_reader(key).process_in_place(&mut body),
             Err(ProcessError::InvalidSize)
         ));
     }
 
+    #[test]
+    fn xtea_max_padding_keeps_single_byte() {
+        let key = test_key();
+        let mut body = build_raw_xtea_body(&key, [6, b'x', 0x33, 0x33, 0x33, 0x33, 0x33, 0x33]);
+
+        assert_eq!(
+            xtea_reader(key)
+                .process_in_place(&mut body)
+                .expect("one byte of data should decode"),
+            ProcessOutcome::Complete
+        );
+        assert_eq!(body, b"x");
+    }
+
     #[test]
     fn xtea_seq_field_ignored() {
         let key = test_key();
@@ -1017,4 +1456,74 @@ tO6vywVbLiOFudajEttnKgRV7AWJENyfTbhcuW1AXJvlEA==
         };
         check_process_invariants(settings, b"");
     }
+
+    #[test]
+    fn fragments_are_joined_only_when_fragmentation_is_enabled() {
+        let settings = ProtocolSettings {
+            header_size: 2,
+            has_checksum: false,
+            uses_xtea: false,
+            uses_rsa: false,
+        };
+        let first = [FRAGMENT_OPCODE, FRAGMENT_MORE, b'a', b'b'];
+        let last = [FRAGMENT_OPCODE, 0, b'c'];
+
+        let mut plain = PacketReader::new(settings);
+        let mut body = first.to_vec();
+        assert_eq!(
+            plain.process_in_place(&mut body).expect("plain fragment"),
+            ProcessOutcome::Complete
+        );
+        assert_eq!(body, first);
+
+        let mut reader = PacketReader::new(settings).with_fragmentation(true);
+        let mut body = first.to_vec();
+        assert_eq!(
+            reader.process_in_place(&mut body).expect("first fragment"),
+            ProcessOutcome::Skip
+        );
+        let mut body = last.to_vec();
+        assert_eq!(
+            reader.process_in_place(&mut body).expect("last fragment"),
+            ProcessOutcome::Complete
+        );
+        assert_eq!(body, b"abc");
+
+        let mut body = vec![FRAGMENT_OPCODE];
+        assert!(matches!(
+            reader.process_in_place(&mut body),
+            Err(ProcessError::InvalidSize)
+        ));
+    }
+
+    #[test]
+    fn fragments_joined_past_the_limit_are_rejected() {
+        let settings = ProtocolSettings {
+            header_size: 2,
+            has_checksum: false,
+            uses_xtea: false,
+            uses_rsa: false,
+        };
+        let mut reader = PacketReader::new(settings)
+            .with_fragmentation(true)
+            .with_fragment_limit(4);
+
+        let mut body = vec![FRAGMENT_OPCODE, FRAGMENT_MORE, b'a', b'b', b'c'];
+        assert_eq!(
+            reader.process_in_place(&mut body).expect("first fragment"),
+            ProcessOutcome::Skip
+        );
+        let mut body = vec![FRAGMENT_OPCODE, 0, b'd', b'e'];
+        assert!(matches!(
+            reader.process_in_place(&mut body),
+            Err(ProcessError::FragmentedTooLarge { limit: 4 })
+        ));
+
+        let mut body = vec![FRAGMENT_OPCODE, 0, b'f'];
+        assert_eq!(
+            reader.process_in_place(&mut body).expect("fresh packet"),
+            ProcessOutcome::Complete
+        );
+        assert_eq!(body, b"f");
+    }
 }
@@ -4,7 +4,10 @@ use flate2::{Compression, write::DeflateEncoder};
 use suon_xtea::ExpandedKey;
 use tracing::error;
 
-use crate::server::tcp::protocol::{self, ProtocolSettings, SEQUENCE_FIELD_LEN, SIZE_FIELD_LEN};
+use crate::server::tcp::protocol::{
+    self, FRAGMENT_HEADER_LEN, FRAGMENT_MORE, FRAGMENT_OPCODE, ProtocolSettings,
+    SEQUENCE_FIELD_LEN, SizeField,
+};
 
 /// Bit flag indicating the packet payload is zlib-compressed.
 const COMPRESSION_FLAG: u32 = 0x8000_0000;
@@ -12,6 +15,18 @@ const COMPRESSION_FLAG: u32 = 0x8000_0000;
 /// Minimum plaintext size (in bytes) before compression is attempted.
 const COMPRESSION_THRESHOLD: usize = 128;
 
+#[derive(Debug, thiserror::Error)]
+pub enum WriteError {
+    #[error("packet too large: {size} byte frame body exceeds the {limit} byte limit")]
+    PacketTooLarge { size: usize, limit: usize },
+    #[error("patch of {len} bytes at offset {offset} exceeds the {buffer_len} byte buffer")]
+    PatchOutOfBounds {
+        offset: usize,
+        len: usize,
+        buffer_len: usize,
+    },
+}
+
 pub struct PacketWriter {
     protocol: ProtocolSettings,
     xtea_key: Option<ExpandedKey>,
@@ -19,6 +34,8 @@ pub struct PacketWriter {
     buffer: Vec<u8>,
     max_buffer_size: usize,
     sequence_id: u32,
+    allow_fragmentation: bool,
+    size_field: SizeField,
 }
 
 impl PacketWriter {
@@ -30,6 +47,8 @@ impl PacketWriter {
             buffer: Vec::with_capacity(max_buffer_size),
             max_buffer_size,
             sequence_id: 0,
+            allow_fragmentation: false,
+            size_field: SizeField::U16,
         }
     }
 
@@ -48,6 +67,16 @@ impl PacketWriter {
         self
     }
 
+    pub fn with_fragmentation(mut self, allow: bool) -> Self {
+        self.allow_fragmentation = allow;
+        self
+    }
+
+    pub fn with_size_field(mut self, size_field: SizeField) -> Self {
+        self.size_field = size_field;
+        self
+    }
+
     pub fn set_xtea_key(&mut self, key: [u32; 4]) {
         self.xtea_key = Some(suon_xtea::expand(&key));
     }
@@ -56,6 +85,28 @@ impl PacketWriter {
         self.xtea_enabled = enabled;
     }
 
+    /// Write (or stop writing) the adler32 prefix on non-XTEA packets.
+    pub fn set_checksum_enabled(&mut self, enabled: bool) {
+        self.protocol.has_checksum = enabled;
+    }
+
+    /// When enabled, a packet too large for one frame is split into
+    /// consecutive frames of at most [`max_payload_len`](Self::max_payload_len)
+    /// bytes each, in order, instead of being rejected. Every fragment
+    /// opens with a [`FRAGMENT_HEADER_LEN`] byte header so the peer can
+    /// join them again, as [`PacketReader`](crate::protocol::PacketReader)
+    /// does. A packet that itself opens with [`FRAGMENT_OPCODE`] is sent
+    /// as fragments whatever its size, so the peer never mistakes it for
+    /// one.
+    pub fn set_fragmentation(&mut self, allow: bool) {
+        self.allow_fragmentation = allow;
+    }
+
+    /// Width of the size prefix written in front of every frame.
+    pub fn set_size_field(&mut self, size_field: SizeField) {
+        self.size_field = size_field;
+    }
+
     pub fn set_max_buffer_size(&mut self, size: usize) {
         self.max_buffer_size = size;
     }
@@ -64,6 +115,11 @@ impl PacketWriter {
         self.buffer.len()
     }
 
+    /// The framed bytes waiting to be written.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+
     pub fn is_empty(&self) -> bool {
         self.buffer.is_empty()
     }
@@ -72,20 +128,124 @@ impl PacketWriter {
         self.buffer.len() >= self.max_buffer_size
     }
 
+    /// Frames `plaintext` and appends it to the outgoing buffer.
+    ///
+    /// An empty `plaintext` is a present-but-empty payload: the frame header
+    /// is still written, so the peer sees a zero-length packet. A packet
+    /// too large for the frame header is logged and dropped; use
+    /// [`try_send`](Self::try_send) to handle that case.
     pub fn send(&mut self, plaintext: &[u8]) {
-        let framed = self.frame_packet(plaintext);
-        self.buffer.extend_from_slice(&framed);
+        if let Err(e) = self.try_send(plaintext) {
+            error!(target: "Writer", "Dropping outgoing packet: {e}");
+        }
+    }
+
+    /// Like [`send`](Self::send), but returns [`WriteError::PacketTooLarge`]
+    /// instead of dropping a packet whose framed body exceeds what the size
+    /// prefix can describe. The buffer is left untouched on error.
+    pub fn try_send(&mut self, plaintext: &[u8]) -> Result<(), WriteError> {
+        if self.allow_fragmentation && plaintext.first() == Some(&FRAGMENT_OPCODE) {
+            let mark = self.mark();
+            return self
+                .frame_fragments(plaintext)
+                .inspect_err(|_| self.rewind(mark));
+        }
+        match self.frame_packet(plaintext) {
+            Err(WriteError::PacketTooLarge { .. }) if self.allow_fragmentation => {
+                let mark = self.mark();
+                self.frame_fragments(plaintext)
+                    .inspect_err(|_| self.rewind(mark))
+            }
+            result => result,
+        }
+    }
+
+    /// Frames every payload of `payloads` back to back, as with
+    /// [`try_send`](Self::try_send), or none of them: if one does not
+    /// fit, the buffer and sequence number are rewound to where they were
+    /// and its error is returned.
+    pub fn try_send_all(&mut self, payloads: &[Vec<u8>]) -> Result<(), WriteError> {
+        let mark = self.mark();
+        for payload in payloads {
+            if let Err(e) = self.try_send(payload) {
+                self.rewind(mark);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Largest plaintext that always fits a single frame with the current
+    /// framing mode, before any compression.
+    pub fn max_payload_len(&self) -> usize {
+        let max_body = self.size_field.max_body();
+        let xtea = self.xtea_enabled && self.protocol.uses_xtea;
+        if xtea && self.xtea_key.is_some() {
+            // The padding byte plus padding must still fit whole blocks.
+            (max_body - SEQUENCE_FIELD_LEN) / 8 * 8 - 1
+        } else if xtea || self.protocol.has_checksum {
+            max_body - SEQUENCE_FIELD_LEN
+        } else {
+            max_body
+        }
     }
 
+    /// Appends `data` as-is. Empty `data` leaves the buffer untouched.
     pub fn send_raw(&mut self, data: &[u8]) {
         self.buffer.extend_from_slice(data);
     }
 
+    /// Overwrites already-buffered bytes starting at `offset`, for fields
+    /// such as a length or checksum that are only known once the bytes
+    /// after them have been written with [`send_raw`](Self::send_raw).
+    /// Offsets are positions in the pending buffer, as reported by
+    /// [`buffer_len`](Self::buffer_len) before the placeholder was written.
+    ///
+    /// Returns [`WriteError::PatchOutOfBounds`] and leaves the buffer
+    /// untouched if `bytes` would extend past the end of the buffer.
+    pub fn patch(&mut self, offset: usize, bytes: &[u8]) -> Result<(), WriteError> {
+        let buffer_len = self.buffer.len();
+        let target = offset
+            .checked_add(bytes.len())
+            .filter(|&end| end <= buffer_len)
+            .map(|end| &mut self.buffer[offset..end])
+            .ok_or(WriteError::PatchOutOfBounds {
+                offset,
+                len: bytes.len(),
+                buffer_len,
+            })?;
+        target.copy_from_slice(bytes);
+        Ok(())
+    }
+
     pub fn take_buffer(&mut self) -> Vec<u8> {
         std::mem::take(&mut self.buffer)
     }
 
-    fn frame_packet(&mut self, plaintext: &[u8]) -> Vec<u8> {
+    /// Frames `plaintext` as consecutive fragments, each holding the
+    /// fragment header and as much of the packet as fits one frame.
+    fn frame_fragments(&mut self, plaintext: &[u8]) -> Result<(), WriteError> {
+        let chunk_len = self.max_payload_len() - FRAGMENT_HEADER_LEN;
+        let mut fragment = Vec::with_capacity(self.max_payload_len());
+        let mut chunks = plaintext.chunks(chunk_len).peekable();
+        while let Some(chunk) = chunks.next() {
+            let more = if chunks.peek().is_some() {
+                FRAGMENT_MORE
+            } else {
+                0
+            };
+            fragment.clear();
+            fragment.extend_from_slice(&[FRAGMENT_OPCODE, more]);
+            fragment.extend_from_slice(chunk);
+            self.frame_packet(&fragment)?;
+        }
+        Ok(())
+    }
+
+    /// Frames `plaintext` straight onto the end of the outgoing buffer, so
+    /// a packet costs no allocation beyond growing the buffer itself
+    /// (plus the deflate output when compression kicks in).
+    fn frame_packet(&mut self, plaintext: &[u8]) -> Result<(), WriteError> {
         if self.xtea_enabled && self.protocol.uses_xtea {
             self.frame_xtea_packet(plaintext)
         } else if self.protocol.has_checksum {
@@ -95,67 +255,82 @@ impl PacketWriter {
         }
     }
 
-    fn frame_plain_packet(&self, plaintext: &[u8]) -> Vec<u8> {
-        let size = plaintext.len() as u16;
-        let mut out = Vec::with_capacity(SIZE_FIELD_LEN + plaintext.len());
-        out.extend_from_slice(&size.to_le_bytes());
-        out.extend_from_slice(plaintext);
-        out
+    fn frame_plain_packet(&mut self, plaintext: &[u8]) -> Result<(), WriteError> {
+        let size = self.frame_body_size(plaintext.len())?;
+        self.buffer.reserve(self.size_field.width() + size);
+        self.size_field.write(&mut self.buffer, size);
+        self.buffer.extend_from_slice(plaintext);
+        Ok(())
     }
 
-    fn frame_checksum_packet(&self, plaintext: &[u8]) -> Vec<u8> {
-        let checksum = suon_adler32::generate(plaintext);
-        let size = (SEQUENCE_FIELD_LEN + plaintext.len()) as u16;
-        let mut out = Vec::with_capacity(SIZE_FIELD_LEN + SEQUENCE_FIELD_LEN + plaintext.len());
-        out.extend_from_slice(&size.to_le_bytes());
-        out.extend_from_slice(&checksum.to_le_bytes());
-        out.extend_from_slice(plaintext);
-        out
+    fn frame_checksum_packet(&mut self, plaintext: &[u8]) -> Result<(), WriteError> {
+        let size = self.frame_body_size(SEQUENCE_FIELD_LEN + plaintext.len())?;
+        self.buffer.reserve(self.size_field.width() + size);
+        self.size_field.write(&mut self.buffer, size);
+
+        // Sum the region exactly as the reader will see it, then fill in
+        // the placeholder.
+        let body_start = self.buffer.len();
+        self.buffer.extend_from_slice(&[0; SEQUENCE_FIELD_LEN]);
+        self.buffer.extend_from_slice(plaintext);
+        let checksum =
+            suon_adler32::generate(protocol::checksummed_region(&self.buffer[body_start..]));
+        self.buffer[body_start..body_start + SEQUENCE_FIELD_LEN]
+            .copy_from_slice(&checksum.to_le_bytes());
+        Ok(())
     }
 
-    fn frame_xtea_packet(&mut self, plaintext: &[u8]) -> Vec<u8> {
-        let seq_field = self.next_sequence_id();
-        let Some(key) = self.xtea_key.as_ref() else {
+    fn frame_xtea_packet(&mut self, plaintext: &[u8]) -> Result<(), WriteError> {
+        // Without a key yet (e.g. before the handshake) fall back to plain
+        // checksum framing; the sequence number is still consumed.
+        if self.xtea_key.is_none() {
+            self.next_sequence_id();
             return self.frame_checksum_packet(plaintext);
-        };
-
-        let payload = if plaintext.len() >= COMPRESSION_THRESHOLD {
-            let compressed = {
-                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
-                if let Err(e) = encoder.write_all(plaintext) {
-                    error!(target: "Writer", "Deflate compression error during XTEA packet framing: {e}");
-                }
-
-                encoder.finish().ok()
-            };
+        }
 
-            if let Some(ref compressed) = compressed {
-                if compressed.len() < plaintext.len() {
-                    let mut padded = protocol::xtea_pad(compressed);
-                    suon_xtea::encrypt(&mut padded, key).ok();
-                    (padded, seq_field | COMPRESSION_FLAG)
-                } else {
-                    let mut padded = protocol::xtea_pad(plaintext);
-                    suon_xtea::encrypt(&mut padded, key).ok();
-                    (padded, seq_field)
-                }
-            } else {
-                let mut padded = protocol::xtea_pad(plaintext);
-                suon_xtea::encrypt(&mut padded, key).ok();
-                (padded, seq_field)
+        let compressed = if plaintext.len() >= COMPRESSION_THRESHOLD {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            if let Err(e) = encoder.write_all(plaintext) {
+                error!(target: "Writer", "Deflate compression error during XTEA packet framing: {e}");
             }
+
+            encoder
+                .finish()
+                .ok()
+                .filter(|compressed| compressed.len() < plaintext.len())
         } else {
-            let mut padded = protocol::xtea_pad(plaintext);
-            suon_xtea::encrypt(&mut padded, key).ok();
-            (padded, seq_field)
+            None
         };
 
-        let total_body = SEQUENCE_FIELD_LEN + payload.0.len();
-        let mut out = Vec::with_capacity(SIZE_FIELD_LEN + total_body);
-        out.extend_from_slice(&(total_body as u16).to_le_bytes());
-        out.extend_from_slice(&payload.1.to_le_bytes());
-        out.extend_from_slice(&payload.0);
-        out
+        let body = compressed.as_deref().unwrap_or(plaintext);
+        let total_body = SEQUENCE_FIELD_LEN + protocol::xtea_padded_len(body.len());
+        let size = self.frame_body_size(total_body)?;
+
+        let mut seq_field = self.next_sequence_id();
+        if compressed.is_some() {
+            seq_field |= COMPRESSION_FLAG;
+        }
+
+        self.buffer.reserve(self.size_field.width() + size);
+        self.size_field.write(&mut self.buffer, size);
+        self.buffer.extend_from_slice(&seq_field.to_le_bytes());
+
+        let encrypted_start = self.buffer.len();
+        protocol::xtea_pad_into(&mut self.buffer, body);
+        if let Some(key) = &self.xtea_key {
+            suon_xtea::encrypt(&mut self.buffer[encrypted_start..], key).ok();
+        }
+        Ok(())
+    }
+
+    /// Buffer length and sequence number to [`rewind`](Self::rewind) to.
+    fn mark(&self) -> (usize, u32) {
+        (self.buffer.len(), self.sequence_id)
+    }
+
+    fn rewind(&mut self, (len, sequence_id): (usize, u32)) {
+        self.buffer.truncate(len);
+        self.sequence_id = sequence_id;
     }
 
     fn next_sequence_id(&mut self) -> u32 {
@@ -163,6 +338,15 @@ impl PacketWriter {
         self.sequence_id = self.sequence_id.wrapping_add(1);
         seq
     }
+
+    /// Checks that a frame body of `len` bytes fits the size prefix.
+    fn frame_body_size(&self, len: usize) -> Result<usize, WriteError> {
+        let limit = self.size_field.max_body();
+        if len > limit {
+            return Err(WriteError::PacketTooLarge { size: len, limit });
+        }
+        Ok(len)
+    }
 }
 
 #[cfg(test)]
@@ -380,6 +564,379 @@ mod tests {
         assert!(unpadded.is_empty());
     }
 
+    #[test]
+    fn plain_empty_payload_writes_zero_length_frame() {
+        let mut writer = PacketWriter::new(
+            ProtocolSettings {
+                header_size: 2,
+                has_checksum: false,
+                uses_xtea: false,
+                uses_rsa: false,
+            },
+            4096,
+        );
+        writer.send(b"");
+
+        assert_eq!(writer.take_buffer(), [0, 0]);
+    }
+
+    #[test]
+    fn checksum_empty_payload_writes_header_only_frame() {
+        let mut writer = PacketWriter::new(
+            ProtocolSettings {
+                header_size: 2,
+                has_checksum: true,
+                uses_xtea: false,
+                uses_rsa: false,
+            },
+            4096,
+        );
+        writer.send(b"");
+
+        let framed = writer.take_buffer();
+        assert_eq!(framed.len(), 2 + 4);
+        assert_eq!(u16::from_le_bytes([framed[0], framed[1]]), 4);
+        assert_eq!(
+            u32::from_le_bytes([framed[2], framed[3], framed[4], framed[5]]),
+            suon_adler32::generate(b"")
+        );
+    }
+
+    #[test]
+    fn send_raw_empty_writes_nothing() {
+        let mut writer = PacketWriter::new(ProtocolSettings::default(), 4096);
+        writer.send_raw(b"");
+
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn send_raw_bypasses_xtea_framing() {
+        let key = test_key();
+        let mut writer = PacketWriter::new(
+            ProtocolSettings {
+                header_size: 6,
+                has_checksum: true,
+                uses_xtea: true,
+                uses_rsa: true,
+            },
+            4096,
+        );
+        writer.set_xtea_key(key);
+
+        // Already framed bytes, e.g. replayed from a capture.
+        let captured = [0x0A, 0x00, 0x01, 0x00, 0x00, 0x00, 0xDE, 0xAD, 0xBE, 0xEF];
+        writer.send(b"first");
+        writer.send_raw(&captured);
+        writer.send(b"second");
+
+        let buffer = writer.take_buffer();
+        let first_len = 2 + u16::from_le_bytes([buffer[0], buffer[1]]) as usize;
+        assert_eq!(&buffer[first_len..first_len + captured.len()], &captured);
+
+        // Raw bytes do not consume a sequence number.
+        let second = &buffer[first_len + captured.len()..];
+        let seq = u32::from_le_bytes([second[2], second[3], second[4], second[5]]);
+        assert_eq!(seq, 1);
+        assert_eq!(decrypt_xtea_framed(second, key), b"second");
+    }
+
+    #[test]
+    fn patch_overwrites_placeholder_after_body_is_known() {
+        let mut writer = PacketWriter::new(ProtocolSettings::default(), 4096);
+        writer.send_raw(b"head");
+
+        let length_offset = writer.buffer_len();
+        writer.send_raw(&[0, 0]);
+        let body_start = writer.buffer_len();
+        writer.send_raw(b"body bytes");
+        let body_len = (writer.buffer_len() - body_start) as u16;
+
+        writer
+            .patch(length_offset, &body_len.to_le_bytes())
+            .expect("patch inside the buffer should succeed");
+
+        assert_eq!(writer.take_buffer(), b"head\x0a\x00body bytes");
+    }
+
+    #[test]
+    fn patch_out_of_bounds_leaves_buffer_untouched() {
+        let mut writer = PacketWriter::new(ProtocolSettings::default(), 4096);
+        writer.send_raw(b"abcd");
+
+        assert!(matches!(
+            writer.patch(3, b"xy"),
+            Err(WriteError::PatchOutOfBounds {
+                offset: 3,
+                len: 2,
+                buffer_len: 4
+            })
+        ));
+        assert!(matches!(
+            writer.patch(usize::MAX, b"x"),
+            Err(WriteError::PatchOutOfBounds { .. })
+        ));
+        writer
+            .patch(4, b"")
+            .expect("empty patch at the end is in bounds");
+
+        assert_eq!(writer.take_buffer(), b"abcd");
+    }
+
+    /// Bytes deflate cannot shrink, so XTEA frames stay uncompressed.
+    fn incompressible(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checksum_oversized_payload_is_rejected() {
+        let mut writer = PacketWriter::new(ProtocolSettings::default(), 4096);
+        writer.send(b"queued");
+        let queued = writer.buffer_len();
+
+        let result = writer.try_send(&vec![0xABu8; 70 * 1024]);
+
+        assert!(matches!(
+            result,
+            Err(WriteError::PacketTooLarge { size, .. }) if size == 4 + 70 * 1024
+        ));
+        assert_eq!(writer.buffer_len(), queued, "buffer must be untouched");
+    }
+
+    #[test]
+    fn plain_payload_at_u16_max_fits() {
+        let mut writer = PacketWriter::new(
+            ProtocolSettings {
+                header_size: 2,
+                has_checksum: false,
+                uses_xtea: false,
+                uses_rsa: false,
+            },
+            4096,
+        );
+
+        writer
+            .try_send(&vec![0u8; u16::MAX as usize])
+            .expect("a u16::MAX payload fits the size header");
+        assert!(writer.try_send(&vec![0u8; u16::MAX as usize + 1]).is_err());
+    }
+
+    #[test]
+    fn xtea_oversized_payload_is_rejected_without_consuming_sequence() {
+        let key = test_key();
+        let mut writer = PacketWriter::new(
+            ProtocolSettings {
+                header_size: 6,
+                has_checksum: true,
+                uses_xtea: true,
+                uses_rsa: true,
+            },
+            4096,
+        );
+        writer.set_xtea_key(key);
+
+        assert!(matches!(
+            writer.try_send(&incompressible(70 * 1024)),
+            Err(WriteError::PacketTooLarge { .. })
+        ));
+        assert!(writer.is_empty());
+
+        writer.send(b"next");
+        let framed = writer.take_buffer();
+        let seq = u32::from_le_bytes([framed[2], framed[3], framed[4], framed[5]]);
+        assert_eq!(seq, 0);
+        assert_eq!(decrypt_xtea_framed(&framed, key), b"next");
+    }
+
+    #[test]
+    fn xtea_compressible_payload_over_u16_max_fits() {
+        let key = test_key();
+        let data = vec![0xABu8; 70 * 1024];
+        let mut writer = PacketWriter::new(
+            ProtocolSettings {
+                header_size: 6,
+                has_checksum: true,
+                uses_xtea: true,
+                uses_rsa: true,
+            },
+            4096,
+        );
+        writer.set_xtea_key(key);
+
+        writer
+            .try_send(&data)
+            .expect("the compressed frame fits the size header");
+        assert_eq!(decrypt_xtea_framed(&writer.take_buffer(), key), data);
+    }
+
+    /// Splits a buffer of checksum frames into their payloads.
+    fn checksum_payloads(mut framed: &[u8]) -> Vec<&[u8]> {
+        let mut payloads = Vec::new();
+        while !framed.is_empty() {
+            let size = u16::from_le_bytes([framed[0], framed[1]]) as usize;
+            let checksum = u32::from_le_bytes([framed[2], framed[3], framed[4], framed[5]]);
+            let payload = &framed[2 + 4..2 + size];
+            assert_eq!(checksum, suon_adler32::generate(payload));
+            payloads.push(payload);
+            framed = &framed[2 + size..];
+        }
+        payloads
+    }
+
+    #[test]
+    fn fragmentation_splits_oversized_payload_into_ordered_frames() {
+        let data = incompressible(150_000);
+        let mut writer =
+            PacketWriter::new(ProtocolSettings::default(), 4096).with_fragmentation(true);
+
+        writer
+            .try_send(&data)
+            .expect("fragmentation should accept oversized payloads");
+
+        let framed = writer.take_buffer();
+        let payloads = checksum_payloads(&framed);
+        assert_eq!(payloads.len(), 3);
+        assert!(payloads.iter().all(|p| p.len() <= writer.max_payload_len()));
+        assert!(payloads.iter().all(|p| p[0] == FRAGMENT_OPCODE));
+        assert_eq!(
+            payloads.iter().map(|p| p[1]).collect::<Vec<_>>(),
+            [FRAGMENT_MORE, FRAGMENT_MORE, 0]
+        );
+        let joined: Vec<u8> = payloads
+            .iter()
+            .flat_map(|p| p[FRAGMENT_HEADER_LEN..].iter().copied())
+            .collect();
+        assert_eq!(joined, data);
+    }
+
+    #[test]
+    fn fragments_are_joined_again_by_the_packet_reader() {
+        let settings = ProtocolSettings::default();
+        let data = incompressible(150_000);
+        let mut writer = PacketWriter::new(settings, 4096).with_fragmentation(true);
+        writer
+            .try_send(&data)
+            .expect("fragmentation should accept oversized payloads");
+        writer.send(b"after");
+
+        let packets = read_fragmented(settings, &writer.take_buffer());
+
+        assert_eq!(packets, [data, b"after".to_vec()]);
+    }
+
+    #[test]
+    fn packets_opening_with_the_fragment_opcode_survive_fragmentation() {
+        let settings = ProtocolSettings::default();
+        let packet = [FRAGMENT_OPCODE, FRAGMENT_MORE, b'x'];
+        let mut writer = PacketWriter::new(settings, 4096).with_fragmentation(true);
+        writer.send(&packet);
+        writer.send(b"after");
+
+        let packets = read_fragmented(settings, &writer.take_buffer());
+
+        assert_eq!(packets, [packet.to_vec(), b"after".to_vec()]);
+    }
+
+    /// Reads a buffer of checksum frames back through a fragmenting
+    /// [`PacketReader`](crate::protocol::PacketReader), returning the
+    /// completed packets.
+    fn read_fragmented(settings: ProtocolSettings, framed: &[u8]) -> Vec<Vec<u8>> {
+        let mut reader = crate::protocol::PacketReader::new(settings).with_fragmentation(true);
+        let mut rest = framed;
+        let mut packets = Vec::new();
+        while !rest.is_empty() {
+            let size = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+            let mut body = rest[2..2 + size].to_vec();
+            rest = &rest[2 + size..];
+            match reader
+                .process_in_place(&mut body)
+                .expect("reader should accept every fragment")
+            {
+                crate::protocol::ProcessOutcome::Complete => packets.push(body),
+                crate::protocol::ProcessOutcome::Skip => {}
+            }
+        }
+        packets
+    }
+
+    #[test]
+    fn try_send_all_frames_all_payloads_or_none() {
+        let mut writer = PacketWriter::new(ProtocolSettings::default(), 4096);
+        writer.send(b"kept");
+        let kept = writer.buffer_len();
+
+        let result = writer.try_send_all(&[b"first".to_vec(), vec![0; u16::MAX as usize]]);
+
+        assert!(matches!(result, Err(WriteError::PacketTooLarge { .. })));
+        assert_eq!(writer.buffer_len(), kept);
+        writer
+            .try_send_all(&[b"a".to_vec(), b"b".to_vec()])
+            .expect("small payloads should fit");
+        assert_eq!(
+            checksum_payloads(&writer.take_buffer()),
+            [&b"kept"[..], b"a", b"b"]
+        );
+    }
+
+    #[test]
+    fn fragmentation_leaves_small_payloads_whole() {
+        let mut writer =
+            PacketWriter::new(ProtocolSettings::default(), 4096).with_fragmentation(true);
+        writer.send(b"small");
+
+        assert_eq!(checksum_payloads(&writer.take_buffer()), [b"small"]);
+    }
+
+    #[test]
+    fn xtea_fragments_decrypt_in_order() {
+        let key = test_key();
+        let data = incompressible(100_000);
+        let mut writer = PacketWriter::new(
+            ProtocolSettings {
+                header_size: 6,
+                has_checksum: true,
+                uses_xtea: true,
+                uses_rsa: true,
+            },
+            4096,
+        )
+        .with_xtea_key(key)
+        .with_fragmentation(true);
+
+        writer
+            .try_send(&data)
+            .expect("fragmentation should accept oversized payloads");
+
+        let framed = writer.take_buffer();
+        let first_len = 2 + u16::from_le_bytes([framed[0], framed[1]]) as usize;
+        let (first, second) = framed.split_at(first_len);
+
+        let first = decrypt_xtea_framed(first, key);
+        let second = decrypt_xtea_framed(second, key);
+        assert_eq!(first.len(), writer.max_payload_len());
+        assert_eq!(
+            first[..FRAGMENT_HEADER_LEN],
+            [FRAGMENT_OPCODE, FRAGMENT_MORE]
+        );
+        assert_eq!(second[..FRAGMENT_HEADER_LEN], [FRAGMENT_OPCODE, 0]);
+        assert_eq!(
+            [
+                &first[FRAGMENT_HEADER_LEN..],
+                &second[FRAGMENT_HEADER_LEN..]
+            ]
+            .concat(),
+            data
+        );
+    }
+
     #[test]
     fn xtea_without_key_falls_back_to_checksum() {
         let mut writer = PacketWriter::new(
@@ -597,6 +1154,40 @@ mod tests {
         assert_eq!(writer.buffer_len(), (2 + 4 + 2) + (2 + 4 + 3));
     }
 
+    #[test]
+    fn send_frames_in_place_without_reallocating() {
+        for protocol in [
+            ProtocolSettings {
+                header_size: 2,
+                has_checksum: false,
+                uses_xtea: false,
+                uses_rsa: false,
+            },
+            ProtocolSettings {
+                header_size: 2,
+                has_checksum: true,
+                uses_xtea: false,
+                uses_rsa: false,
+            },
+            ProtocolSettings {
+                header_size: 6,
+                has_checksum: true,
+                uses_xtea: true,
+                uses_rsa: true,
+            },
+        ] {
+            let mut writer = PacketWriter::new(protocol, 4096).with_xtea_key(test_key());
+            let before = writer.buffer.as_ptr();
+
+            for _ in 0..8 {
+                writer.send(b"fits in the reserved buffer");
+            }
+
+            assert_eq!(writer.buffer.as_ptr(), before, "{protocol}");
+            assert_eq!(writer.buffer.capacity(), 4096, "{protocol}");
+        }
+    }
+
     #[test]
     fn xtea_compression_roundtrip() {
         let key = test_key();
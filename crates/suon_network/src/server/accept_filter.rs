@@ -0,0 +1,59 @@
+use std::{fmt, net::SocketAddr, sync::Arc};
+
+use suon_macros::Resource;
+
+type Predicate = dyn Fn(&SocketAddr) -> bool + Send + Sync;
+
+/// Decides from the peer address alone whether an accepted TCP socket may
+/// proceed, so allowlists and geolocation rules can live in user code.
+///
+/// Insert it as a resource before adding the
+/// [`NetworkPlugin`](crate::NetworkPlugin). It is consulted right after
+/// `accept()`, ahead of the per-IP throttle, and a rejected socket is
+/// dropped without a reply.
+#[derive(Clone, Resource)]
+pub struct AcceptFilter(Arc<Predicate>);
+
+impl AcceptFilter {
+    /// Wraps `predicate`; it returns `false` for addresses to refuse.
+    pub fn new(predicate: impl Fn(&SocketAddr) -> bool + Send + Sync + 'static) -> Self {
+        AcceptFilter(Arc::new(predicate))
+    }
+
+    /// Whether a connection from `address` may be accepted.
+    pub fn allows(&self, address: &SocketAddr) -> bool {
+        (self.0)(address)
+    }
+}
+
+impl Default for AcceptFilter {
+    /// Accepts every address.
+    fn default() -> Self {
+        AcceptFilter::new(|_| true)
+    }
+}
+
+impl fmt::Debug for AcceptFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AcceptFilter").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_filter_allows_everything() {
+        let filter = AcceptFilter::default();
+        assert!(filter.allows(&"127.0.0.1:7171".parse().expect("valid test address")));
+        assert!(filter.allows(&"[::1]:7171".parse().expect("valid test address")));
+    }
+
+    #[test]
+    fn filter_consults_the_predicate() {
+        let filter = AcceptFilter::new(|address| address.ip().is_loopback());
+        assert!(filter.allows(&"127.0.0.1:7171".parse().expect("valid test address")));
+        assert!(!filter.allows(&"10.0.0.1:7171".parse().expect("valid test address")));
+    }
+}
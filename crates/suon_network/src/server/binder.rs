@@ -1,12 +1,18 @@
-use std::{sync::Arc, time::Duration};
+use std::{io, sync::Arc, time::Duration};
 
 use suon_channel::{BufferPool, Channel};
-use tokio::{net::TcpListener, runtime::Runtime};
+use tokio::{
+    net::{TcpListener, TcpSocket},
+    runtime::Runtime,
+};
 use tracing::warn;
 
 use crate::{
     connection::manager::ConnectionManager,
-    server::{runner::BoundServer, settings::ServerSettings, shutdown::Shutdown},
+    server::{
+        listen_address::ListenAddress, runner::BoundServer, settings::ServerSettings,
+        shutdown::Shutdown,
+    },
 };
 
 pub(crate) struct Binder {
@@ -17,6 +23,7 @@ pub(crate) struct Binder {
     settings: ServerSettings,
     shutdown: Shutdown,
     retry_delay: Duration,
+    listen_address: ListenAddress,
 }
 
 impl Binder {
@@ -37,9 +44,17 @@ impl Binder {
             settings,
             shutdown,
             retry_delay,
+            listen_address: ListenAddress::new(),
         }
     }
 
+    /// Publishes the bound address into `listen_address` once the
+    /// listener is up.
+    pub fn with_listen_address(mut self, listen_address: ListenAddress) -> Self {
+        self.listen_address = listen_address;
+        self
+    }
+
     pub fn launch(self) {
         if self.shutdown.is_triggered() {
             return;
@@ -55,10 +70,18 @@ impl Binder {
         let retry_delay = self.retry_delay;
         let runtime = self.runtime.clone();
         let handle = runtime.handle().clone();
+        let listen_address = self.listen_address.clone();
 
         handle.spawn(async move {
-            match TcpListener::bind(&address).await {
+            match bind(&address, settings.backlog).await {
                 Ok(listener) => {
+                    match listener.local_addr() {
+                        Ok(bound) => listen_address.publish(bound),
+                        Err(e) => {
+                            warn!(target: "Binder", "Could not read bound address for port {port}: {e}", port = settings.port);
+                        }
+                    }
+
                     BoundServer::new(
                         listener,
                         channel,
@@ -85,6 +108,7 @@ impl Binder {
                             buffer_pool,
                             connection_manager,
                         )
+                        .with_listen_address(listen_address)
                         .launch();
                     });
                 }
@@ -93,16 +117,42 @@ impl Binder {
     }
 }
 
+/// Binds a listener on the first address `address` resolves to that
+/// accepts it, queueing up to `backlog` pending connections.
+async fn bind(address: &str, backlog: u32) -> io::Result<TcpListener> {
+    let mut last_error = None;
+    for addr in tokio::net::lookup_host(address).await? {
+        let socket = if addr.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+
+        // Matches `TcpListener::bind`, so a restart can reclaim the port
+        // while old connections sit in TIME_WAIT.
+        #[cfg(not(windows))]
+        socket.set_reuseaddr(true)?;
+
+        match socket.bind(addr).and_then(|()| socket.listen(backlog)) {
+            Ok(listener) => return Ok(listener),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{address} did not resolve to any address"),
+        )
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
         connection::manager::ConnectionManager,
-        server::{
-            kind::ServerKind,
-            settings::ServerSettings,
-            tcp::{EncryptionSettings, ProtocolSettings},
-        },
+        server::{kind::ServerKind, settings::ServerSettings, tcp::TcpSettings},
     };
     use std::sync::Arc;
 
@@ -110,16 +160,15 @@ mod tests {
         ServerSettings {
             port: 0,
             address: "127.0.0.1".into(),
-            kind: ServerKind::Tcp {
-                protocol: ProtocolSettings::default(),
-                flush_interval: Duration::from_millis(10),
-                encryption: EncryptionSettings::default(),
+            kind: TcpSettings {
                 channel_capacity: 16,
                 max_buffer_size: 256,
                 max_connections: 5,
-                rate_burst: 50,
-            },
+                ..TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         }
     }
 
@@ -129,9 +178,7 @@ mod tests {
 
     #[test]
     fn binder_does_not_panic_on_launch() {
-        let runtime = Arc::new(
-            tokio::runtime::Runtime::new().expect("failed to create tokio runtime for test"),
-        );
+        let runtime = crate::test_runtime();
         let channel = Channel::default();
         let shutdown = Shutdown::new();
         let settings = dummy_settings();
@@ -150,9 +197,7 @@ mod tests {
 
     #[test]
     fn binder_skips_launch_if_triggered() {
-        let runtime = Arc::new(
-            tokio::runtime::Runtime::new().expect("failed to create tokio runtime for test"),
-        );
+        let runtime = crate::test_runtime();
         let channel = Channel::default();
         let shutdown = Shutdown::new();
         let settings = dummy_settings();
@@ -172,9 +217,7 @@ mod tests {
 
     #[test]
     fn binder_launch_with_http_settings() {
-        let runtime = Arc::new(
-            tokio::runtime::Runtime::new().expect("failed to create tokio runtime for test"),
-        );
+        let runtime = crate::test_runtime();
         let channel = Channel::default();
         let shutdown = Shutdown::new();
         let settings = ServerSettings {
@@ -186,6 +229,7 @@ mod tests {
                 max_headers: 32,
             },
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         };
 
         Binder::new(
@@ -205,24 +249,21 @@ mod tests {
         let occupied =
             std::net::TcpListener::bind("127.0.0.1:9999").expect("failed to occupy port for test");
 
-        let runtime = Arc::new(
-            tokio::runtime::Runtime::new().expect("failed to create tokio runtime for test"),
-        );
+        let runtime = crate::test_runtime();
         let channel = Channel::default();
         let shutdown = Shutdown::new();
         let settings = ServerSettings {
             port: 9999,
             address: "127.0.0.1".into(),
-            kind: ServerKind::Tcp {
-                protocol: ProtocolSettings::default(),
-                flush_interval: Duration::from_millis(10),
-                encryption: EncryptionSettings::default(),
+            kind: TcpSettings {
                 channel_capacity: 16,
                 max_buffer_size: 256,
                 max_connections: 5,
-                rate_burst: 50,
-            },
+                ..TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(50),
+            backlog: 1024,
         };
 
         Binder::new(
@@ -244,24 +285,21 @@ mod tests {
         let occupied =
             std::net::TcpListener::bind("127.0.0.1:9898").expect("failed to occupy port for test");
 
-        let runtime = Arc::new(
-            tokio::runtime::Runtime::new().expect("failed to create tokio runtime for test"),
-        );
+        let runtime = crate::test_runtime();
         let channel = Channel::default();
         let shutdown = Shutdown::new();
         let settings = ServerSettings {
             port: 9898,
             address: "127.0.0.1".into(),
-            kind: ServerKind::Tcp {
-                protocol: ProtocolSettings::default(),
-                flush_interval: Duration::from_millis(10),
-                encryption: EncryptionSettings::default(),
+            kind: TcpSettings {
                 channel_capacity: 16,
                 max_buffer_size: 256,
                 max_connections: 5,
-                rate_burst: 50,
-            },
+                ..TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(50),
+            backlog: 1024,
         };
 
         Binder::new(
@@ -278,4 +316,22 @@ mod tests {
         drop(occupied);
         std::thread::sleep(Duration::from_millis(10));
     }
+
+    #[tokio::test]
+    async fn bind_accepts_a_custom_backlog() {
+        let listener = bind("127.0.0.1:0", 16)
+            .await
+            .expect("binding with a custom backlog should succeed");
+        let addr = listener.local_addr().expect("failed to read bound address");
+
+        let (client, accepted) =
+            tokio::join!(tokio::net::TcpStream::connect(addr), listener.accept());
+        client.expect("client should connect");
+        accepted.expect("listener should accept");
+    }
+
+    #[tokio::test]
+    async fn bind_reports_an_unresolvable_address() {
+        assert!(bind("not an address", 16).await.is_err());
+    }
 }
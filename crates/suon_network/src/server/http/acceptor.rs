@@ -57,6 +57,7 @@ mod http_settings_tests {
                 max_headers: 64,
             },
             retry_delay: Duration::from_millis(15000),
+            backlog: 1024,
         };
         let http = HttpSettings::from_settings(&settings);
         assert_eq!(http.max_connections, 200);
@@ -76,6 +77,7 @@ mod http_settings_tests {
                 max_headers: 32,
             },
             retry_delay: Duration::from_millis(15000),
+            backlog: 1024,
         };
         let http = HttpSettings::from_settings(&settings);
         assert_eq!(http.max_connections, 100);
@@ -89,16 +91,15 @@ mod http_settings_tests {
         let settings = ServerSettings {
             port: 7171,
             address: "0.0.0.0".into(),
-            kind: ServerKind::Tcp {
+            kind: crate::server::tcp::TcpSettings {
                 protocol: crate::server::tcp::ProtocolSettings::default(),
-                flush_interval: Duration::from_millis(10),
                 encryption: crate::server::tcp::EncryptionSettings::default(),
                 channel_capacity: 128,
-                max_buffer_size: 4096,
-                max_connections: 100,
-                rate_burst: 50,
-            },
+                ..crate::server::tcp::TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(15000),
+            backlog: 1024,
         };
         HttpSettings::from_settings(&settings);
     }
@@ -196,6 +197,7 @@ mod http_acceptor_tests {
                 max_headers: 32,
             },
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         }
     }
 
@@ -1,11 +1,17 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
 use serde::{Deserialize, Serialize};
 
-use crate::server::tcp::{EncryptionSettings, ProtocolSettings};
+use crate::{
+    connection::client_kind::ClientKind,
+    server::tcp::{
+        DecodeFailurePolicy, EncryptionSettings, FlushPolicy, ProtocolSettings, SizeField,
+    },
+};
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
 #[serde(tag = "type", rename_all = "lowercase")]
+#[expect(clippy::large_enum_variant)]
 pub enum ServerKind {
     Tcp {
         protocol: ProtocolSettings,
@@ -14,8 +20,69 @@ pub enum ServerKind {
         encryption: EncryptionSettings,
         channel_capacity: usize,
         max_buffer_size: usize,
+        #[serde(default)]
+        flush_threshold: usize,
         max_connections: u32,
         rate_burst: u32,
+        #[serde(default = "default_accept_queue_capacity")]
+        accept_queue_capacity: usize,
+        #[serde(
+            rename = "write_timeout_ms",
+            with = "suon_serde::duration_ms",
+            default = "default_write_timeout"
+        )]
+        write_timeout: Duration,
+        #[serde(default = "default_write_retries")]
+        write_retries: u32,
+        #[serde(default)]
+        allow_fragmentation: bool,
+        #[serde(default = "default_max_fragmented_size")]
+        max_fragmented_size: usize,
+        #[serde(default, with = "suon_serde::string_keys")]
+        packet_size_limits: BTreeMap<u8, usize>,
+        #[serde(default)]
+        auto_keep_alive: bool,
+        #[serde(default)]
+        flush_policy: FlushPolicy,
+        #[serde(default = "default_max_pending_packets")]
+        max_pending_packets: usize,
+        #[serde(default)]
+        max_send_rate: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status_motd: Option<String>,
+        #[serde(default, with = "suon_serde::string_keys")]
+        client_kind_opcodes: BTreeMap<u8, ClientKind>,
+        #[serde(
+            default,
+            rename = "handshake_timeout_ms",
+            with = "suon_serde::duration_ms::option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        handshake_timeout: Option<Duration>,
+        #[serde(
+            default,
+            rename = "read_timeout_ms",
+            with = "suon_serde::duration_ms::option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        read_timeout: Option<Duration>,
+        #[serde(
+            default,
+            rename = "key_timeout_ms",
+            with = "suon_serde::duration_ms::option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        key_timeout: Option<Duration>,
+        #[serde(default)]
+        require_authentication: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        decode_failure_policy: Option<DecodeFailurePolicy>,
+        #[serde(default)]
+        size_field: SizeField,
+        #[serde(default)]
+        send_disconnect_packets: bool,
+        #[serde(default)]
+        allow_resumption: bool,
     },
     Http {
         max_connections: u32,
@@ -24,6 +91,26 @@ pub enum ServerKind {
     },
 }
 
+fn default_accept_queue_capacity() -> usize {
+    64
+}
+
+fn default_write_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_write_retries() -> u32 {
+    3
+}
+
+fn default_max_fragmented_size() -> usize {
+    1 << 20
+}
+
+fn default_max_pending_packets() -> usize {
+    256
+}
+
 impl Default for ServerKind {
     fn default() -> Self {
         ServerKind::Tcp {
@@ -32,8 +119,29 @@ impl Default for ServerKind {
             encryption: EncryptionSettings::default(),
             channel_capacity: 1024,
             max_buffer_size: 4096,
+            flush_threshold: 0,
             max_connections: 100,
             rate_burst: 50,
+            accept_queue_capacity: 64,
+            write_timeout: Duration::from_secs(5),
+            write_retries: 3,
+            allow_fragmentation: false,
+            max_fragmented_size: 1 << 20,
+            packet_size_limits: Default::default(),
+            auto_keep_alive: false,
+            flush_policy: Default::default(),
+            max_pending_packets: 256,
+            max_send_rate: 0,
+            status_motd: None,
+            client_kind_opcodes: Default::default(),
+            handshake_timeout: None,
+            read_timeout: None,
+            key_timeout: None,
+            require_authentication: false,
+            decode_failure_policy: None,
+            size_field: Default::default(),
+            send_disconnect_packets: false,
+            allow_resumption: false,
         }
     }
 }
@@ -0,0 +1,64 @@
+use std::net::SocketAddr;
+
+use tokio::sync::watch;
+
+/// The address a server's listener actually bound to.
+///
+/// Published once the bind succeeds, so callers can wait until a server
+/// is accepting connections and learn the ephemeral port picked for a
+/// `port = 0` setting. Clones share the same state.
+#[derive(Clone)]
+pub struct ListenAddress {
+    sender: watch::Sender<Option<SocketAddr>>,
+}
+
+impl ListenAddress {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(None);
+        ListenAddress { sender }
+    }
+
+    pub(crate) fn publish(&self, address: SocketAddr) {
+        self.sender.send_replace(Some(address));
+    }
+
+    /// The bound address, or `None` while the listener is not bound yet.
+    pub fn get(&self) -> Option<SocketAddr> {
+        *self.sender.borrow()
+    }
+
+    /// Waits until the listener is bound and returns its address.
+    pub async fn wait(&self) -> SocketAddr {
+        let mut receiver = self.sender.subscribe();
+        let address = receiver
+            .wait_for(Option::is_some)
+            .await
+            .expect("the sender lives as long as `self`");
+        address.expect("`wait_for` only returns once an address is set")
+    }
+}
+
+impl Default for ListenAddress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    #[tokio::test]
+    async fn wait_returns_once_published() {
+        let listen_address = ListenAddress::new();
+        assert_eq!(listen_address.get(), None);
+
+        let address = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 7171));
+        let publisher = listen_address.clone();
+        tokio::spawn(async move { publisher.publish(address) });
+
+        assert_eq!(listen_address.wait().await, address);
+        assert_eq!(listen_address.get(), Some(address));
+    }
+}
@@ -1,6 +1,8 @@
+pub mod accept_filter;
 pub(crate) mod binder;
 pub mod http;
 pub mod kind;
+pub mod listen_address;
 pub mod runner;
 pub mod settings;
 pub(crate) mod shutdown;
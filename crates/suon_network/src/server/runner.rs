@@ -49,14 +49,14 @@ impl BoundServer {
         );
 
         match self.settings.kind {
-            ServerKind::Tcp { .. } => ActiveServer::Tcp(TcpAcceptor::new(
+            ServerKind::Tcp { .. } => ActiveServer::Tcp(Box::new(TcpAcceptor::new(
                 self.listener,
                 self.channel,
                 &self.settings,
                 self.shutdown,
                 self.buffer_pool,
                 self.connection_manager,
-            )),
+            ))),
             ServerKind::Http { .. } => ActiveServer::Http(HttpAcceptor::new(
                 self.listener,
                 self.channel,
@@ -68,7 +68,7 @@ impl BoundServer {
 }
 
 pub(crate) enum ActiveServer {
-    Tcp(TcpAcceptor),
+    Tcp(Box<TcpAcceptor>),
     Http(HttpAcceptor),
 }
 
@@ -87,7 +87,7 @@ mod bound_server_tests {
     use crate::server::{
         kind::ServerKind,
         settings::ServerSettings,
-        tcp::{EncryptionSettings, ProtocolSettings},
+        tcp::{EncryptionSettings, ProtocolSettings, TcpSettings},
     };
     use std::time::Duration;
 
@@ -95,7 +95,7 @@ mod bound_server_tests {
         ServerSettings {
             port: 0,
             address: "127.0.0.1".into(),
-            kind: ServerKind::Tcp {
+            kind: TcpSettings {
                 protocol: ProtocolSettings {
                     header_size: 2,
                     has_checksum: true,
@@ -110,9 +110,11 @@ mod bound_server_tests {
                 channel_capacity: 16,
                 max_buffer_size: 256,
                 max_connections: 5,
-                rate_burst: 50,
-            },
+                ..TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         }
     }
 
@@ -126,6 +128,7 @@ mod bound_server_tests {
                 max_headers: 32,
             },
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         }
     }
 
@@ -229,7 +232,7 @@ mod tests {
         connection::manager::ConnectionManager,
         server::{
             kind::ServerKind,
-            tcp::{EncryptionSettings, ProtocolSettings},
+            tcp::{EncryptionSettings, ProtocolSettings, TcpSettings},
         },
     };
     use std::{sync::Arc, time::Duration};
@@ -240,6 +243,7 @@ mod tests {
             address: "127.0.0.1".into(),
             kind,
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         }
     }
 
@@ -256,23 +260,26 @@ mod tests {
         let channel = suon_channel::Channel::default();
         let shutdown = Shutdown::new();
 
-        let settings = test_settings(ServerKind::Tcp {
-            protocol: ProtocolSettings {
-                header_size: 2,
-                has_checksum: true,
-                uses_xtea: false,
-                uses_rsa: false,
-            },
-            flush_interval: Duration::from_millis(50),
-            encryption: EncryptionSettings {
-                incoming: false,
-                outgoing: false,
-            },
-            channel_capacity: 16,
-            max_buffer_size: 256,
-            max_connections: 5,
-            rate_burst: 50,
-        });
+        let settings = test_settings(
+            TcpSettings {
+                protocol: ProtocolSettings {
+                    header_size: 2,
+                    has_checksum: true,
+                    uses_xtea: false,
+                    uses_rsa: false,
+                },
+                flush_interval: Duration::from_millis(50),
+                encryption: EncryptionSettings {
+                    incoming: false,
+                    outgoing: false,
+                },
+                channel_capacity: 16,
+                max_buffer_size: 256,
+                max_connections: 5,
+                ..TcpSettings::default()
+            }
+            .into(),
+        );
 
         BoundServer::new(
             listener,
@@ -12,6 +12,13 @@ pub struct ServerSettings {
     pub kind: ServerKind,
     #[serde(rename = "retry_delay_ms", with = "suon_serde::duration_ms")]
     pub retry_delay: Duration,
+    /// Pending connections the OS queues before they are accepted.
+    #[serde(default = "default_backlog")]
+    pub backlog: u32,
+}
+
+fn default_backlog() -> u32 {
+    1024
 }
 
 #[cfg(test)]
@@ -41,9 +48,43 @@ mod tests {
         assert_eq!(settings.address, "0.0.0.0");
         assert_eq!(settings.port, 7171);
         assert_eq!(settings.retry_delay, Duration::from_millis(15000));
+        assert_eq!(settings.backlog, 1024);
         assert!(matches!(settings.kind, ServerKind::Tcp { .. }));
     }
 
+    #[test]
+    fn server_settings_deserialize_client_kind_opcodes() {
+        let toml_str = r#"
+            port = 7171
+            address = "0.0.0.0"
+            type = "tcp"
+            protocol = { header_size = 6, has_checksum = true, uses_xtea = false, uses_rsa = false }
+            encryption = { incoming = true, outgoing = true }
+            flush_interval_ms = 10
+            channel_capacity = 1024
+            max_buffer_size = 4096
+            max_connections = 100
+            rate_burst = 50
+            retry_delay_ms = 15000
+            client_kind_opcodes = { 20 = "login" }
+        "#;
+
+        let settings: ServerSettings =
+            toml::from_str(toml_str).expect("failed to deserialize TCP settings from TOML");
+
+        let ServerKind::Tcp {
+            client_kind_opcodes,
+            ..
+        } = settings.kind
+        else {
+            panic!("expected TCP settings");
+        };
+        assert_eq!(
+            client_kind_opcodes,
+            [(0x14, crate::connection::client_kind::ClientKind::Login)].into()
+        );
+    }
+
     #[test]
     fn server_settings_deserialize_http() {
         let toml_str = r#"
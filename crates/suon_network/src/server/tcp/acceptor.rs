@@ -1,17 +1,22 @@
 use std::{sync::Arc, time::Duration};
 use suon_channel::{BufferPool, Channel};
 use tokio::net::TcpListener;
-use tracing::info;
+use tracing::{debug, info};
 
 use crate::{connection::manager::ConnectionManager, server::tcp::settings::TcpSettings};
 
 use super::connection_accept::AcceptOutcome;
 
-use super::{connection::Connection, connection_begin::ConnectionBegin};
+use super::{
+    connection::Connection,
+    connection_begin::ConnectionBegin,
+    connection_ready::ConnectionReady,
+    reject::{SERVER_FULL, SERVER_SHUTTING_DOWN, TOO_MANY_ATTEMPTS, on_reject},
+};
 use crate::server::{
     settings::ServerSettings,
     shutdown::Shutdown,
-    throttle::{ConnectionLimiter, PacketRateLimiter},
+    throttle::{AcceptQueue, ConnectionLimiter, PacketRateLimiter},
 };
 
 pub(crate) struct TcpAcceptor {
@@ -22,6 +27,7 @@ pub(crate) struct TcpAcceptor {
     config: TcpSettings,
     limiter: ConnectionLimiter,
     rate_limiter: PacketRateLimiter,
+    accept_queue: AcceptQueue,
     shutdown: Shutdown,
 }
 
@@ -37,6 +43,7 @@ impl TcpAcceptor {
         let config = TcpSettings::from_settings(settings);
         let limiter = ConnectionLimiter::new(config.max_connections as usize);
         let rate_limiter = PacketRateLimiter::new(config.rate_burst);
+        let accept_queue = AcceptQueue::new(config.accept_queue_capacity);
 
         info!(target: "TCP", "TCP server started on port {} [protocol: {}]", settings.port, config.protocol);
 
@@ -48,6 +55,7 @@ impl TcpAcceptor {
             config,
             limiter,
             rate_limiter,
+            accept_queue,
             shutdown,
         }
     }
@@ -59,6 +67,16 @@ impl TcpAcceptor {
     async fn accept_loop(self) {
         let mut rx = self.shutdown.receiver();
         loop {
+            // Hold off on `accept()` while too many connections are still
+            // waiting for Lua; the kernel backlog absorbs the burst.
+            let slot = tokio::select! {
+                _ = rx.changed() => {
+                    if *rx.borrow() { break; }
+                    continue;
+                }
+                slot = self.accept_queue.reserve(&self.manager) => slot,
+            };
+
             tokio::select! {
                 _ = rx.changed() => {
                     if *rx.borrow() { break; }
@@ -68,11 +86,25 @@ impl TcpAcceptor {
                         continue
                     };
 
+                    if *rx.borrow() {
+                        on_reject(stream, &self.config, SERVER_SHUTTING_DOWN);
+                        break;
+                    }
+
+                    if !self.manager.accepts(&address) {
+                        debug!(target: "TCP", "Refused connection from {address}: rejected by the accept filter");
+                        continue;
+                    }
+
                     if !self.rate_limiter.allow(address) {
+                        self.manager.stats().record_throttled();
+                        on_reject(stream, &self.config, TOO_MANY_ATTEMPTS);
                         continue;
                     }
 
                     let Ok(permit) = self.limiter.try_acquire() else {
+                        self.manager.stats().record_throttled();
+                        on_reject(stream, &self.config, SERVER_FULL);
                         continue;
                     };
 
@@ -88,7 +120,7 @@ impl TcpAcceptor {
                         response: Some(begin_response_sender),
                     });
 
-                    let outcome = super::connection_accept::ConnectionAccept {
+                    let accept = super::connection_accept::ConnectionAccept {
                         id,
                         address,
                         stream,
@@ -99,32 +131,48 @@ impl TcpAcceptor {
                         connection_timeout: Duration::from_secs(
                             self.config.connection_timeout_secs,
                         ),
-                    }
-                    .decide()
-                    .await;
-
-                    match outcome {
-                        AcceptOutcome::Spawn {
-                            stream,
-                            command_receiver,
-                            permit,
-                        } => {
-                            Connection::spawn(
+                    };
+
+                    let channel = self.channel.clone();
+                    let manager = self.manager.clone();
+                    let config = self.config.clone();
+                    let shutdown = self.shutdown.clone();
+                    let buffer_pool = self.buffer_pool.clone();
+
+                    // Decisions run in their own task so up to
+                    // `accept_queue_capacity` sockets can wait for Lua at
+                    // once; awaiting here would cap the queue at one and
+                    // make the setting meaningless. The slot is held until
+                    // the decision arrives, which is what bounds the queue.
+                    tokio::spawn(async move {
+                        let outcome = accept.decide().await;
+                        drop(slot);
+
+                        match outcome {
+                            AcceptOutcome::Spawn {
                                 stream,
                                 command_receiver,
-                                self.channel.clone(),
-                                self.manager.clone(),
-                                self.config,
-                                self.shutdown.clone(),
-                                id,
                                 permit,
-                                self.buffer_pool.clone(),
-                            );
+                            } => {
+                                Connection::spawn(
+                                    stream,
+                                    command_receiver,
+                                    channel.clone(),
+                                    manager,
+                                    config,
+                                    shutdown,
+                                    id,
+                                    permit,
+                                    buffer_pool,
+                                );
+
+                                channel.send(ConnectionReady { id, address });
+                            }
+                            AcceptOutcome::Reject => {
+                                // stream + permit already dropped by decide()
+                            }
                         }
-                        AcceptOutcome::Reject => {
-                            // stream + permit already dropped by decide()
-                        }
-                    }
+                    });
                 }
             }
         }
@@ -158,7 +206,7 @@ mod tests {
         let settings = ServerSettings {
             port: 0,
             address: "127.0.0.1".into(),
-            kind: ServerKind::Tcp {
+            kind: TcpSettings {
                 protocol: ProtocolSettings {
                     header_size: 2,
                     has_checksum: true,
@@ -173,9 +221,11 @@ mod tests {
                 channel_capacity: 64,
                 max_buffer_size: 256,
                 max_connections: 5,
-                rate_burst: 50,
-            },
+                ..TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         };
 
         TcpAcceptor::new(
@@ -195,6 +245,198 @@ mod tests {
         tokio::time::sleep(Duration::from_millis(15)).await;
     }
 
+    #[tokio::test]
+    async fn tcp_accepted_connection_emits_ready_event() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind TCP listener for ready event test");
+
+        let addr = listener
+            .local_addr()
+            .expect("failed to get listener local address");
+
+        let channel = Channel::default();
+        let shutdown = Shutdown::new();
+        let settings = ServerSettings {
+            port: 0,
+            address: "127.0.0.1".into(),
+            kind: ServerKind::default(),
+            retry_delay: Duration::from_millis(100),
+            backlog: 1024,
+        };
+
+        TcpAcceptor::new(
+            listener,
+            channel.clone(),
+            &settings,
+            shutdown.clone(),
+            crate::test_buffer_pool(),
+            Arc::new(ConnectionManager::new(0)),
+        )
+        .spawn();
+
+        let client = tokio::net::TcpStream::connect(addr)
+            .await
+            .expect("failed to connect test client");
+        let client_addr = client
+            .local_addr()
+            .expect("failed to get client local address");
+
+        let vm = suon_lua::LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(
+                r#"
+                ConnectionBeginEvent = { trigger = function() return true end }
+                ConnectionReadyEvent = { trigger = function(_, id, ip, port) ready = { id, ip, port }; return true end }
+                "#,
+            )
+            .exec()
+            .expect("failed to define test connection events");
+        });
+        let mut resources = Resources::default();
+        resources.insert(vm);
+
+        // First drain runs ConnectionBegin, second picks up ConnectionReady
+        for _ in 0..2 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let mut buf = Vec::new();
+            channel.wait_and_drain(&mut buf);
+            for mut task in buf {
+                task.run(&mut resources);
+            }
+        }
+
+        let (ip, port): (String, u16) = resources.get::<suon_lua::LuaVm>().execute(|lua| {
+            let ready: mlua::Table = lua
+                .globals()
+                .get("ready")
+                .expect("ConnectionReadyEvent should have fired");
+            (
+                ready.get(2).expect("ip should be set"),
+                ready.get(3).expect("port should be set"),
+            )
+        });
+        assert_eq!(ip, client_addr.ip().to_string());
+        assert_eq!(port, client_addr.port());
+
+        drop(client);
+        shutdown.trigger();
+    }
+
+    #[tokio::test]
+    async fn tcp_accept_queue_bounds_pending_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind TCP listener for accept queue test");
+
+        let addr = listener
+            .local_addr()
+            .expect("failed to get listener local address");
+
+        let channel = Channel::default();
+        let shutdown = Shutdown::new();
+        let manager = Arc::new(ConnectionManager::new(0));
+        let settings = ServerSettings {
+            port: 0,
+            address: "127.0.0.1".into(),
+            kind: TcpSettings {
+                flush_interval: Duration::from_millis(50),
+                channel_capacity: 64,
+                max_buffer_size: 256,
+                accept_queue_capacity: 2,
+                ..TcpSettings::default()
+            }
+            .into(),
+            retry_delay: Duration::from_millis(100),
+            backlog: 1024,
+        };
+
+        TcpAcceptor::new(
+            listener,
+            channel.clone(),
+            &settings,
+            shutdown.clone(),
+            crate::test_buffer_pool(),
+            manager.clone(),
+        )
+        .spawn();
+
+        // Nobody drains the channel, so no onConnect decision ever arrives.
+        let mut clients = Vec::new();
+        for _ in 0..6 {
+            clients.push(
+                tokio::net::TcpStream::connect(addr)
+                    .await
+                    .expect("failed to connect flooding client"),
+            );
+        }
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(channel.pending_count(), 2);
+        assert_eq!(
+            manager
+                .stats()
+                .pending_accepts
+                .load(std::sync::atomic::Ordering::Relaxed),
+            2
+        );
+
+        drop(clients);
+        shutdown.trigger();
+    }
+
+    #[tokio::test]
+    async fn tcp_accept_filter_drops_refused_addresses() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind TCP listener for accept filter test");
+
+        let addr = listener
+            .local_addr()
+            .expect("failed to get listener local address");
+
+        let channel = Channel::default();
+        let shutdown = Shutdown::new();
+        let manager = Arc::new(ConnectionManager::new(0));
+        manager.set_accept_filter(crate::AcceptFilter::new(|address| {
+            !address.ip().is_loopback()
+        }));
+        let settings = ServerSettings {
+            port: 0,
+            address: "127.0.0.1".into(),
+            kind: ServerKind::default(),
+            retry_delay: Duration::from_millis(100),
+            backlog: 1024,
+        };
+
+        TcpAcceptor::new(
+            listener,
+            channel.clone(),
+            &settings,
+            shutdown.clone(),
+            crate::test_buffer_pool(),
+            manager.clone(),
+        )
+        .spawn();
+
+        let mut client = tokio::net::TcpStream::connect(addr)
+            .await
+            .expect("failed to connect test client");
+
+        let mut buf = [0u8; 16];
+        let read = tokio::time::timeout(Duration::from_secs(1), client.read(&mut buf))
+            .await
+            .expect("a refused socket should be closed straight away");
+        assert!(matches!(read, Ok(0) | Err(_)));
+        assert_eq!(channel.pending_count(), 0);
+        assert_eq!(manager.count(), 0);
+
+        shutdown.trigger();
+    }
+
     #[tokio::test]
     async fn tcp_rate_limit_rejects_excess() {
         let listener = TcpListener::bind("127.0.0.1:0")
@@ -207,11 +449,11 @@ mod tests {
 
         let channel = Channel::default();
         let shutdown = Shutdown::new();
-        use crate::server::{kind::ServerKind, settings::ServerSettings};
+        use crate::server::settings::ServerSettings;
         let settings = ServerSettings {
             port: 0,
             address: "127.0.0.1".into(),
-            kind: ServerKind::Tcp {
+            kind: TcpSettings {
                 protocol: ProtocolSettings {
                     header_size: 2,
                     has_checksum: true,
@@ -226,9 +468,11 @@ mod tests {
                 channel_capacity: 64,
                 max_buffer_size: 256,
                 max_connections: 1, // only 1 connection
-                rate_burst: 50,
-            },
+                ..TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         };
 
         TcpAcceptor::new(
@@ -285,11 +529,11 @@ mod tests {
 
         let channel = Channel::default();
         let shutdown = Shutdown::new();
-        use crate::server::{kind::ServerKind, settings::ServerSettings};
+        use crate::server::settings::ServerSettings;
         let settings = ServerSettings {
             port: 0,
             address: "127.0.0.1".into(),
-            kind: ServerKind::Tcp {
+            kind: TcpSettings {
                 protocol: ProtocolSettings {
                     header_size: 2,
                     has_checksum: true,
@@ -304,9 +548,11 @@ mod tests {
                 channel_capacity: 64,
                 max_buffer_size: 256,
                 max_connections: 0, // reject all
-                rate_burst: 50,
-            },
+                ..TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(100),
+            backlog: 1024,
         };
 
         TcpAcceptor::new(
@@ -330,4 +576,117 @@ mod tests {
         drop(client);
         shutdown.trigger();
     }
+
+    #[tokio::test]
+    async fn tcp_full_server_sends_rejection_before_close() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind TCP listener for rejection test");
+
+        let addr = listener
+            .local_addr()
+            .expect("failed to get listener local address");
+
+        let shutdown = Shutdown::new();
+        let settings = ServerSettings {
+            port: 0,
+            address: "127.0.0.1".into(),
+            kind: TcpSettings {
+                flush_interval: Duration::from_millis(50),
+                channel_capacity: 64,
+                max_buffer_size: 256,
+                max_connections: 0,
+                ..TcpSettings::default()
+            }
+            .into(),
+            retry_delay: Duration::from_millis(100),
+            backlog: 1024,
+        };
+
+        TcpAcceptor::new(
+            listener,
+            Channel::default(),
+            &settings,
+            shutdown.clone(),
+            crate::test_buffer_pool(),
+            Arc::new(ConnectionManager::new(0)),
+        )
+        .spawn();
+
+        let mut client = tokio::net::TcpStream::connect(addr)
+            .await
+            .expect("failed to connect test client");
+
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), client.read_to_end(&mut received))
+            .await
+            .expect("rejected connection should be closed")
+            .expect("failed to read rejection");
+
+        let expected = crate::server::tcp::reject::rejection_packet(
+            ProtocolSettings::default(),
+            crate::server::tcp::SizeField::U16,
+            SERVER_FULL,
+        );
+        assert_eq!(received, expected);
+
+        shutdown.trigger();
+    }
+
+    #[tokio::test]
+    async fn tcp_rate_limited_connection_gets_a_rejection_before_close() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind TCP listener for rate limit rejection test");
+
+        let addr = listener
+            .local_addr()
+            .expect("failed to get listener local address");
+
+        let shutdown = Shutdown::new();
+        let settings = ServerSettings {
+            port: 0,
+            address: "127.0.0.1".into(),
+            kind: TcpSettings {
+                rate_burst: 0, // throttle every attempt
+                ..TcpSettings::default()
+            }
+            .into(),
+            retry_delay: Duration::from_millis(100),
+            backlog: 1024,
+        };
+
+        TcpAcceptor::new(
+            listener,
+            Channel::default(),
+            &settings,
+            shutdown.clone(),
+            crate::test_buffer_pool(),
+            Arc::new(ConnectionManager::new(0)),
+        )
+        .spawn();
+
+        let mut client = tokio::net::TcpStream::connect(addr)
+            .await
+            .expect("failed to connect test client");
+
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), client.read_to_end(&mut received))
+            .await
+            .expect("throttled connection should be closed")
+            .expect("failed to read rejection");
+
+        let expected = crate::server::tcp::reject::rejection_packet(
+            ProtocolSettings::default(),
+            crate::server::tcp::SizeField::U16,
+            TOO_MANY_ATTEMPTS,
+        );
+        assert_eq!(received, expected);
+
+        shutdown.trigger();
+    }
 }
@@ -1,8 +1,11 @@
-use std::sync::Arc;
+use std::sync::{Arc, atomic::AtomicBool};
 use tracing::trace;
 
 use suon_channel::{BufferPool, Channel};
-use tokio::net::TcpStream;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
 
 use crate::{
     connection::{id::ConnectionId, manager::ConnectionManager},
@@ -33,29 +36,96 @@ impl Connection {
         }
 
         let (reader_half, writer_half) = stream.into_split();
+        Self::spawn_io(
+            reader_half,
+            writer_half,
+            command_receiver,
+            channel,
+            manager,
+            config,
+            shutdown,
+            handle_id,
+            permit,
+            buffer_pool,
+        );
+    }
+
+    /// Spawns the reader and writer sessions over any pair of stream
+    /// halves, such as the two ends of [`mock_transport`] in tests.
+    pub fn spawn_io<R, W>(
+        reader_half: R,
+        writer_half: W,
+        command_receiver: crossbeam_channel::Receiver<Command>,
+        channel: Channel,
+        manager: Arc<ConnectionManager>,
+        config: TcpSettings,
+        shutdown: Shutdown,
+        handle_id: ConnectionId,
+        permit: ConnectionPermit,
+        buffer_pool: Arc<BufferPool>,
+    ) where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let stats = manager.stats_handle();
+        let packet_tap = manager.packet_tap().clone();
+        let handle = manager.get(handle_id);
+        let budget_charge = handle
+            .as_ref()
+            .map(|handle| handle.budget_charge().clone())
+            .unwrap_or_default();
+        let wake = handle
+            .map(|handle| handle.writer_wake().clone())
+            .unwrap_or_default();
+        let checksum_enabled = Arc::new(AtomicBool::new(config.protocol.has_checksum));
 
         ReaderSession::new(
             handle_id,
             reader_half,
             channel,
-            config,
+            config.clone(),
             shutdown.clone(),
             manager,
             permit,
             buffer_pool.clone(),
         )
+        .with_checksum_flag(checksum_enabled.clone())
         .spawn();
 
-        WriterSession::new(command_receiver, writer_half, config, shutdown, buffer_pool).spawn();
+        WriterSession::new(command_receiver, writer_half, config, shutdown, buffer_pool)
+            .with_stats(stats)
+            .with_checksum_flag(checksum_enabled)
+            .with_packet_tap(handle_id, packet_tap)
+            .with_budget_charge(budget_charge)
+            .with_wake(wake)
+            .spawn();
     }
 }
 
+/// In-memory stand-in for a TCP connection: the client end plus the read
+/// and write halves of the server end, to pass to
+/// [`Connection::spawn_io`]. Bytes written to one end are read from the
+/// other, with no sockets or ports involved.
+#[cfg(test)]
+pub(crate) fn mock_transport() -> (
+    tokio::io::DuplexStream,
+    tokio::io::ReadHalf<tokio::io::DuplexStream>,
+    tokio::io::WriteHalf<tokio::io::DuplexStream>,
+) {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let (reader_half, writer_half) = tokio::io::split(server);
+    (client, reader_half, writer_half)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::server::throttle::ConnectionLimiter;
     use std::{sync::Arc, time::Duration};
-    use tokio::net::TcpListener;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
 
     fn make_config() -> TcpSettings {
         TcpSettings {
@@ -73,8 +143,7 @@ mod tests {
             channel_capacity: 64,
             max_buffer_size: 256,
             max_connections: 5,
-            connection_timeout_secs: 10,
-            rate_burst: 50,
+            ..TcpSettings::default()
         }
     }
 
@@ -159,7 +228,7 @@ mod tests {
                     rx,
                     channel.clone(),
                     manager.clone(),
-                    config,
+                    config.clone(),
                     shutdown.clone(),
                     ConnectionId::new(0, 1),
                     permit,
@@ -179,4 +248,854 @@ mod tests {
         }
         drop(accept.await);
     }
+
+    #[tokio::test]
+    async fn checksum_mode_applies_to_both_directions() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind TCP listener for checksum mode test");
+
+        let addr = listener
+            .local_addr()
+            .expect("failed to get listener local address");
+
+        let channel = Channel::default();
+        let manager = Arc::new(ConnectionManager::new(0));
+        let config = make_config();
+        let limiter = ConnectionLimiter::new(5);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for checksum mode test");
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let accept_channel = channel.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener
+                .accept()
+                .await
+                .expect("failed to accept incoming connection");
+
+            Connection::spawn(
+                stream,
+                rx,
+                accept_channel,
+                manager,
+                config,
+                Shutdown::new(),
+                ConnectionId::new(0, 1),
+                permit,
+                crate::test_buffer_pool(),
+            );
+        });
+
+        let mut client = tokio::net::TcpStream::connect(addr)
+            .await
+            .expect("failed to connect test client");
+
+        tx.send(Command::SetChecksumEnabled(false))
+            .expect("failed to queue checksum mode change");
+        tx.send(Command::Send(b"out".to_vec()))
+            .expect("failed to queue outgoing packet");
+
+        // Outgoing: no checksum prefix after the size header.
+        let mut outgoing = [0u8; 5];
+        tokio::time::timeout(Duration::from_secs(2), client.read_exact(&mut outgoing))
+            .await
+            .expect("timed out waiting for outgoing packet")
+            .expect("failed to read outgoing packet");
+        assert_eq!(&outgoing, b"\x03\x00out");
+
+        // Incoming: a two-byte body would be too short to carry a checksum.
+        client
+            .write_all(b"\x02\x00in")
+            .await
+            .expect("failed to write incoming packet");
+
+        for _ in 0..100 {
+            if channel.pending_count() >= 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let vm = suon_lua::LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(
+                "RawPacketEvent = { trigger = function(_, _, data) packet = data; return true end \
+                 }",
+            )
+            .exec()
+            .expect("failed to define test event handler");
+        });
+        let mut resources = suon_resource::Resources::default();
+        resources.insert(vm);
+        resources.insert(crate::pool::NetworkBufferPool(crate::test_buffer_pool()));
+
+        let mut tasks = Vec::new();
+        channel.wait_and_drain(&mut tasks);
+        for task in &mut tasks {
+            task.run(&mut resources);
+        }
+
+        let packet: Vec<u8> = resources
+            .get::<suon_lua::LuaVm>()
+            .execute(|lua| lua.globals().get("packet"))
+            .expect("checksum-less incoming packet should be dispatched");
+        assert_eq!(packet, b"in");
+    }
+
+    #[tokio::test]
+    async fn mock_transport_carries_packets_both_ways() {
+        let (mut client, reader_half, writer_half) = mock_transport();
+        let channel = Channel::default();
+        let limiter = ConnectionLimiter::new(1);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for mock transport test");
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        Connection::spawn_io(
+            reader_half,
+            writer_half,
+            rx,
+            channel.clone(),
+            Arc::new(ConnectionManager::new(0)),
+            make_config(),
+            Shutdown::new(),
+            ConnectionId::new(0, 1),
+            permit,
+            crate::test_buffer_pool(),
+        );
+
+        tx.send(Command::Send(b"pong".to_vec()))
+            .expect("failed to queue outgoing packet");
+
+        let mut outgoing = [0u8; 10];
+        client
+            .read_exact(&mut outgoing)
+            .await
+            .expect("failed to read outgoing packet");
+        assert_eq!(&outgoing[..2], &8u16.to_le_bytes());
+        assert_eq!(
+            &outgoing[2..6],
+            &suon_adler32::generate(b"pong").to_le_bytes()
+        );
+        assert_eq!(&outgoing[6..], b"pong");
+
+        // size=8, zero ("no") checksum, payload "ping".
+        client
+            .write_all(b"\x08\x00\x00\x00\x00\x00ping")
+            .await
+            .expect("failed to write incoming packet");
+        drop(client);
+
+        // The packet is queued ahead of the end-of-stream disconnect.
+        while channel.pending_count() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let vm = suon_lua::LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(
+                "RawPacketEvent = { trigger = function(_, _, data) packet = data; return true end \
+                 }; ConnectionEndEvent = { trigger = function(_, _, reason) end_reason = reason; \
+                 return true end }",
+            )
+            .exec()
+            .expect("failed to define test event handlers");
+        });
+        let mut resources = suon_resource::Resources::default();
+        resources.insert(vm);
+        resources.insert(crate::pool::NetworkBufferPool(crate::test_buffer_pool()));
+
+        let mut tasks = Vec::new();
+        channel.wait_and_drain(&mut tasks);
+        for task in &mut tasks {
+            task.run(&mut resources);
+        }
+
+        let (packet, end_reason): (Vec<u8>, String) = resources
+            .get::<suon_lua::LuaVm>()
+            .execute(|lua| {
+                let globals = lua.globals();
+                Ok::<_, mlua::Error>((globals.get("packet")?, globals.get("end_reason")?))
+            })
+            .expect("both the packet and the disconnect should be dispatched");
+        assert_eq!(packet, b"ping");
+        assert_eq!(end_reason, "closed");
+    }
+
+    #[tokio::test]
+    async fn pre_840_connection_frames_packets_without_a_checksum() {
+        let (mut client, reader_half, writer_half) = mock_transport();
+        let channel = Channel::default();
+        let limiter = ConnectionLimiter::new(1);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for version test");
+        let config = make_config();
+
+        let manager = Arc::new(ConnectionManager::new(0));
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, tx);
+        let handle = manager.get(id).expect("connection should be registered");
+        Connection::spawn_io(
+            reader_half,
+            writer_half,
+            rx,
+            channel.clone(),
+            manager,
+            config,
+            Shutdown::new(),
+            id,
+            permit,
+            crate::test_buffer_pool(),
+        );
+
+        handle
+            .set_protocol_version(810)
+            .expect("failed to queue protocol version");
+        handle
+            .send(b"pong".to_vec())
+            .expect("failed to queue outgoing packet");
+        assert_eq!(handle.protocol_version(), Some(810));
+
+        // size=4 followed directly by the payload, no checksum field.
+        let mut outgoing = [0u8; 6];
+        client
+            .read_exact(&mut outgoing)
+            .await
+            .expect("failed to read outgoing packet");
+        assert_eq!(&outgoing, b"\x04\x00pong");
+
+        client
+            .write_all(b"\x04\x00ping")
+            .await
+            .expect("failed to write incoming packet");
+        drop(client);
+
+        while channel.pending_count() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let vm = suon_lua::LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(
+                "RawPacketEvent = { trigger = function(_, _, data) packet = data; return true end \
+                 }; ConnectionEndEvent = { trigger = function() return true end }",
+            )
+            .exec()
+            .expect("failed to define test event handlers");
+        });
+        let mut resources = suon_resource::Resources::default();
+        resources.insert(vm);
+        resources.insert(crate::pool::NetworkBufferPool(crate::test_buffer_pool()));
+
+        let mut tasks = Vec::new();
+        channel.wait_and_drain(&mut tasks);
+        for task in &mut tasks {
+            task.run(&mut resources);
+        }
+
+        let packet: Vec<u8> = resources
+            .get::<suon_lua::LuaVm>()
+            .execute(|lua| lua.globals().get("packet"))
+            .expect("the packet should be dispatched");
+        assert_eq!(packet, b"ping");
+    }
+
+    #[tokio::test]
+    async fn mock_transport_answers_a_status_query() {
+        let (mut client, reader_half, writer_half) = mock_transport();
+        let channel = Channel::default();
+        let limiter = ConnectionLimiter::new(1);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for status test");
+        let config = TcpSettings {
+            status_motd: Some("Welcome".to_string()),
+            ..make_config()
+        };
+
+        let manager = Arc::new(ConnectionManager::new(0));
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, tx);
+        Connection::spawn_io(
+            reader_half,
+            writer_half,
+            rx,
+            channel.clone(),
+            manager,
+            config,
+            Shutdown::new(),
+            id,
+            permit,
+            crate::test_buffer_pool(),
+        );
+
+        // size=6, zero ("no") checksum, payload: status opcode and one byte.
+        client
+            .write_all(b"\x06\x00\x00\x00\x00\x00\xFF\x01")
+            .await
+            .expect("failed to write status query");
+
+        // The writer closes the connection once the answer is out.
+        let mut answer = Vec::new();
+        client
+            .read_to_end(&mut answer)
+            .await
+            .expect("failed to read status answer");
+
+        let payload = &answer[6..];
+        assert_eq!(&answer[..2], &((payload.len() + 4) as u16).to_le_bytes());
+        assert_eq!(
+            crate::server::tcp::ServerStatusPacket::decode(payload),
+            Some(crate::server::tcp::ServerStatusPacket {
+                players_online: 1,
+                max_players: 5,
+                motd: "Welcome".to_string(),
+            })
+        );
+        assert_eq!(channel.pending_count(), 0, "the query must not reach Lua");
+    }
+
+    #[tokio::test]
+    async fn packet_tap_captures_frames_in_both_directions() {
+        use crate::connection::{CapturedPacket, PacketDirection, PacketTap};
+
+        let (mut client, reader_half, writer_half) = mock_transport();
+        let limiter = ConnectionLimiter::new(1);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for packet tap test");
+        let config = TcpSettings {
+            status_motd: Some("Tapped".to_string()),
+            ..make_config()
+        };
+
+        let tap = PacketTap::new(16);
+        let manager = Arc::new(ConnectionManager::new(0).with_packet_tap(tap.clone()));
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, tx);
+        Connection::spawn_io(
+            reader_half,
+            writer_half,
+            rx,
+            Channel::default(),
+            manager,
+            config,
+            Shutdown::new(),
+            id,
+            permit,
+            crate::test_buffer_pool(),
+        );
+
+        let query = b"\x06\x00\x00\x00\x00\x00\xFF\x01";
+        client
+            .write_all(query)
+            .await
+            .expect("failed to write status query");
+
+        let mut answer = Vec::new();
+        client
+            .read_to_end(&mut answer)
+            .await
+            .expect("failed to read status answer");
+
+        assert_eq!(
+            tap.dump(id),
+            [
+                CapturedPacket {
+                    id,
+                    direction: PacketDirection::Incoming,
+                    data: query.to_vec(),
+                },
+                CapturedPacket {
+                    id,
+                    direction: PacketDirection::Outgoing,
+                    data: answer,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn send_and_close_flushes_the_packet_before_teardown() {
+        let (mut client, reader_half, writer_half) = mock_transport();
+        let limiter = ConnectionLimiter::new(1);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for send_and_close test");
+        let config = TcpSettings {
+            // Only the close itself can push the packet out.
+            flush_interval: Duration::from_secs(60),
+            ..make_config()
+        };
+
+        let manager = Arc::new(ConnectionManager::new(0));
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, tx);
+        let handle = manager.get(id).expect("connection should be registered");
+        Connection::spawn_io(
+            reader_half,
+            writer_half,
+            rx,
+            Channel::default(),
+            manager.clone(),
+            config,
+            Shutdown::new(),
+            id,
+            permit,
+            crate::test_buffer_pool(),
+        );
+
+        handle
+            .send_and_close(b"bye".to_vec())
+            .expect("failed to queue send_and_close");
+        drop(handle);
+
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), client.read_to_end(&mut received))
+            .await
+            .expect("the server should close its side")
+            .expect("failed to read the final packet");
+        assert!(received.ends_with(b"bye"));
+        assert!(
+            manager.get(id).is_some(),
+            "removed before the client saw the packet"
+        );
+
+        drop(client);
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while manager.get(id).is_some() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("the connection should be removed once the client hangs up");
+    }
+
+    #[tokio::test]
+    async fn repeated_decode_failures_disconnect_the_client() {
+        let (mut client, reader_half, writer_half) = mock_transport();
+        let channel = Channel::default();
+        let limiter = ConnectionLimiter::new(1);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for decode failure test");
+        let config = TcpSettings {
+            decode_failure_policy: Some(crate::server::tcp::DecodeFailurePolicy {
+                max_failures: 2,
+                window: Duration::from_secs(60),
+            }),
+            ..make_config()
+        };
+
+        let manager = Arc::new(ConnectionManager::new(0));
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, tx);
+        Connection::spawn_io(
+            reader_half,
+            writer_half,
+            rx,
+            channel.clone(),
+            manager,
+            config,
+            Shutdown::new(),
+            id,
+            permit,
+            crate::test_buffer_pool(),
+        );
+
+        // size=5, zero ("no") checksum, a lone opcode the handler rejects.
+        for _ in 0..3 {
+            client
+                .write_all(b"\x05\x00\x00\x00\x00\x00\x64")
+                .await
+                .expect("failed to write malformed packet");
+        }
+        while channel.pending_count() < 3 {
+            tokio::task::yield_now().await;
+        }
+
+        // The shipped event and packet modules, with a handler that
+        // rejects the truncated packet the way game code would.
+        let modules = concat!(env!("CARGO_MANIFEST_DIR"), "/../../modules");
+        let vm = suon_lua::LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(format!(
+                "package.path = package.path .. \";{modules}/?/init.lua;{modules}/?.lua\""
+            ))
+            .exec()
+            .expect("failed to extend package.path");
+            lua.load(format!(
+                r#"
+                require("events.priority")
+                require("events.event")
+                require("network.connection")
+                require("events.cancellable")
+                require("events.network.connection")
+                require("events.network.cancellable_connection")
+                require("network")
+                Connection({}, "127.0.0.1", 7172)
+                PacketEvent:onAny(0x64, function(_, msg)
+                    if msg:eof() then
+                        error("truncated packet")
+                    end
+                end)
+                "#,
+                id.as_u64()
+            ))
+            .exec()
+            .expect("failed to load the event and network modules");
+        });
+        let mut resources = suon_resource::Resources::default();
+        resources.insert(vm);
+        resources.insert(crate::pool::NetworkBufferPool(crate::test_buffer_pool()));
+
+        let mut tasks = Vec::new();
+        channel.wait_and_drain(&mut tasks);
+        for task in &mut tasks {
+            task.run(&mut resources);
+        }
+
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), client.read_to_end(&mut received))
+            .await
+            .expect("the server should close the connection")
+            .expect("failed to read until the server closed");
+        assert!(received.is_empty());
+    }
+
+    #[tokio::test]
+    async fn u32_size_field_carries_frames_past_64_kib() {
+        let (mut client, reader_half, writer_half) = mock_transport();
+        let channel = Channel::default();
+        let limiter = ConnectionLimiter::new(1);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for wide frame test");
+        let config = TcpSettings {
+            size_field: crate::server::tcp::SizeField::U32,
+            ..make_config()
+        };
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        Connection::spawn_io(
+            reader_half,
+            writer_half,
+            rx,
+            channel.clone(),
+            Arc::new(ConnectionManager::new(0)),
+            config,
+            Shutdown::new(),
+            ConnectionId::new(0, 1),
+            permit,
+            crate::test_buffer_pool(),
+        );
+
+        let payload: Vec<u8> = (0..70 * 1024).map(|i| (i % 251) as u8).collect();
+        tx.send(Command::Send(payload.clone()))
+            .expect("failed to queue outgoing packet");
+
+        let mut size = [0u8; 4];
+        client
+            .read_exact(&mut size)
+            .await
+            .expect("failed to read the size prefix");
+        let mut body = vec![0u8; u32::from_le_bytes(size) as usize];
+        client
+            .read_exact(&mut body)
+            .await
+            .expect("failed to read the frame body");
+        assert_eq!(body.len(), 4 + payload.len());
+        assert_eq!(&body[..4], &suon_adler32::generate(&payload).to_le_bytes());
+        assert_eq!(&body[4..], payload.as_slice());
+
+        // The same frame, sent back by the client, must read as one packet.
+        client
+            .write_all(&[&size[..], &body].concat())
+            .await
+            .expect("failed to write incoming frame");
+        drop(client);
+        while channel.pending_count() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let vm = suon_lua::LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(
+                "RawPacketEvent = { trigger = function(_, _, data) packet = data; return true end \
+                 }",
+            )
+            .exec()
+            .expect("failed to define test event handlers");
+        });
+        let mut resources = suon_resource::Resources::default();
+        resources.insert(vm);
+        resources.insert(crate::pool::NetworkBufferPool(crate::test_buffer_pool()));
+
+        let mut tasks = Vec::new();
+        channel.wait_and_drain(&mut tasks);
+        for task in &mut tasks {
+            task.run(&mut resources);
+        }
+
+        let packet: Vec<u8> = resources
+            .get::<suon_lua::LuaVm>()
+            .execute(|lua| lua.globals().get("packet"))
+            .expect("the wide packet should be dispatched");
+        assert_eq!(packet, payload);
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_sends_a_disconnect_packet_before_closing() {
+        use crate::server::tcp::DisconnectPacket;
+
+        let (mut client, reader_half, writer_half) = mock_transport();
+        let limiter = ConnectionLimiter::new(1);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for disconnect packet test");
+        let config = TcpSettings {
+            send_disconnect_packets: true,
+            allow_resumption: false,
+            ..make_config()
+        };
+
+        let manager = Arc::new(ConnectionManager::new(0));
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, tx);
+        Connection::spawn_io(
+            reader_half,
+            writer_half,
+            rx,
+            Channel::default(),
+            manager,
+            config,
+            Shutdown::new(),
+            id,
+            permit,
+            crate::test_buffer_pool(),
+        );
+
+        // size=5, checksum that cannot match the single payload byte.
+        client
+            .write_all(&[0x05, 0x00, 0xEF, 0xBE, 0xAD, 0xDE, 0x01])
+            .await
+            .expect("failed to write packet with bad checksum");
+
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), client.read_to_end(&mut received))
+            .await
+            .expect("the server should close the connection")
+            .expect("failed to read the disconnect packet");
+
+        let size = u16::from_le_bytes([received[0], received[1]]) as usize;
+        assert_eq!(received.len(), 2 + size, "nothing may follow the packet");
+        let packet = DisconnectPacket::decode(&received[6..])
+            .expect("the server should send a disconnect packet");
+        assert_eq!(packet.code, DisconnectPacket::PROTOCOL_ERROR);
+        assert_eq!(packet.message, DisconnectPacket::PROTOCOL_ERROR_MESSAGE);
+    }
+
+    #[tokio::test]
+    async fn resume_session_packet_redeems_valid_tokens_and_rejects_used_ones() {
+        use crate::{
+            connection::{auth_state::AuthState, client_kind::ClientKind},
+            server::tcp::ResumeSessionPacket,
+        };
+
+        let config = TcpSettings {
+            allow_resumption: true,
+            ..make_config()
+        };
+        let manager = Arc::new(ConnectionManager::new(0));
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+
+        let (previous_tx, _previous_rx) = crossbeam_channel::bounded(16);
+        let previous = manager.register(peer, config.protocol, previous_tx);
+        let previous_handle = manager
+            .get(previous)
+            .expect("previous connection registered");
+        previous_handle.resolve_client_kind(ClientKind::Game);
+        previous_handle
+            .set_protocol_version(1098)
+            .expect("failed to queue protocol version");
+        previous_handle
+            .set_xtea_key([1, 2, 3, 4])
+            .expect("failed to queue XTEA key");
+        let token = manager
+            .session_tokens()
+            .issue(&previous_handle)
+            .expect("failed to issue a token");
+        manager.unregister(previous);
+
+        let limiter = ConnectionLimiter::new(2);
+        let resume = async |token| {
+            let (mut client, reader_half, writer_half) = mock_transport();
+            let (tx, rx) = crossbeam_channel::bounded(16);
+            let id = manager.register(peer, config.protocol, tx);
+            let permit = limiter
+                .try_acquire()
+                .expect("failed to acquire connection permit for resumption test");
+            Connection::spawn_io(
+                reader_half,
+                writer_half,
+                rx,
+                Channel::default(),
+                manager.clone(),
+                config.clone(),
+                Shutdown::new(),
+                id,
+                permit,
+                crate::test_buffer_pool(),
+            );
+
+            // Zero checksum, which the reader accepts unverified.
+            let payload = ResumeSessionPacket { token }.encode();
+            let frame = [
+                &((4 + payload.len()) as u16).to_le_bytes()[..],
+                &[0; 4],
+                &payload,
+            ]
+            .concat();
+            client
+                .write_all(&frame)
+                .await
+                .expect("failed to write resume packet");
+            (client, id)
+        };
+
+        let (_client, id) = resume(token).await;
+        let handle = manager.get(id).expect("resumed connection registered");
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while handle.auth_state() != AuthState::Authenticated {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("a valid token should authenticate the connection");
+        assert!(handle.is_handshake_complete());
+        assert_eq!(handle.client_kind(), Some(ClientKind::Game));
+        assert_eq!(handle.protocol_version(), Some(1098));
+        assert_eq!(handle.xtea_key(), Some([1, 2, 3, 4]));
+
+        // The token was used up, so presenting it again is a violation.
+        let (mut client, id) = resume(token).await;
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), client.read_to_end(&mut received))
+            .await
+            .expect("an invalid token should close the connection")
+            .expect("failed to read until the connection closed");
+        assert!(manager.get(id).is_none());
+    }
+
+    #[tokio::test]
+    async fn resume_session_packet_restores_the_xtea_key_on_encrypted_ports() {
+        use crate::server::tcp::{ResumeSessionPacket, protocol};
+
+        let key = [0x0123_4567, 0x89AB_CDEF, 0xFEDC_BA98, 0x7654_3210];
+        let config = TcpSettings {
+            protocol: crate::server::tcp::ProtocolSettings {
+                header_size: 6,
+                has_checksum: true,
+                uses_xtea: true,
+                uses_rsa: true,
+            },
+            encryption: crate::server::tcp::EncryptionSettings {
+                incoming: true,
+                outgoing: true,
+            },
+            allow_resumption: true,
+            ..make_config()
+        };
+        let manager = Arc::new(ConnectionManager::new(0));
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+
+        let (previous_tx, _previous_rx) = crossbeam_channel::bounded(16);
+        let previous = manager.register(peer, config.protocol, previous_tx);
+        let previous_handle = manager
+            .get(previous)
+            .expect("previous connection registered");
+        previous_handle
+            .set_xtea_key(key)
+            .expect("failed to queue XTEA key");
+        let token = manager
+            .session_tokens()
+            .issue(&previous_handle)
+            .expect("failed to issue a token");
+        manager.unregister(previous);
+
+        let (mut client, reader_half, writer_half) = mock_transport();
+        let channel = Channel::default();
+        let limiter = ConnectionLimiter::new(1);
+        let permit = limiter
+            .try_acquire()
+            .expect("failed to acquire connection permit for resumption test");
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let id = manager.register(peer, config.protocol, tx);
+        Connection::spawn_io(
+            reader_half,
+            writer_half,
+            rx,
+            channel.clone(),
+            manager,
+            config,
+            Shutdown::new(),
+            id,
+            permit,
+            crate::test_buffer_pool(),
+        );
+
+        // The resume packet goes in the clear, the next one under the
+        // restored key.
+        let resume = ResumeSessionPacket { token }.encode();
+        let resume_body = [&suon_adler32::generate(&resume).to_le_bytes()[..], &resume].concat();
+        let payload = b"\x64after resuming";
+        let mut encrypted = protocol::xtea_pad(payload);
+        suon_xtea::encrypt(&mut encrypted, &suon_xtea::expand(&key))
+            .expect("failed to encrypt the packet sent after resuming");
+        let encrypted_body = [&[0; 4][..], &encrypted].concat();
+        for body in [resume_body, encrypted_body] {
+            client
+                .write_all(&[&(body.len() as u16).to_le_bytes()[..], &body].concat())
+                .await
+                .expect("failed to write frame");
+        }
+        while channel.pending_count() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let vm = suon_lua::LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(
+                "SessionResumedEvent = { trigger = function() return true end }\nRawPacketEvent = \
+                 { trigger = function(_, _, data) packet = data; return true end }",
+            )
+            .exec()
+            .expect("failed to define test event handlers");
+        });
+        let mut resources = suon_resource::Resources::default();
+        resources.insert(vm);
+        resources.insert(crate::pool::NetworkBufferPool(crate::test_buffer_pool()));
+
+        let mut tasks = Vec::new();
+        channel.wait_and_drain(&mut tasks);
+        for task in &mut tasks {
+            task.run(&mut resources);
+        }
+
+        let packet: Vec<u8> = resources
+            .get::<suon_lua::LuaVm>()
+            .execute(|lua| lua.globals().get("packet"))
+            .expect("the encrypted packet should be dispatched");
+        assert_eq!(packet, payload);
+    }
 }
@@ -7,7 +7,7 @@ use suon_resource::Resources;
 use crate::connection::id::ConnectionId;
 
 /// Writes an IP address into a fixed stack buffer.
-fn fmt_ip(ip: IpAddr, buf: &mut [u8; 48]) -> &str {
+pub(super) fn fmt_ip(ip: IpAddr, buf: &mut [u8; 48]) -> &str {
     match ip {
         IpAddr::V4(v4) => fmt_ipv4(v4, buf),
         IpAddr::V6(v6) => fmt_ipv6(v6, buf),
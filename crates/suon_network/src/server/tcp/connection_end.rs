@@ -3,17 +3,25 @@ use suon_lua::LuaVm;
 use suon_macros::Task;
 use suon_resource::Resources;
 
-use crate::connection::id::ConnectionId;
+use crate::connection::{disconnect::DisconnectReason, id::ConnectionId};
 
+/// Task sent by the reader session once a TCP connection is gone,
+/// carrying the [`DisconnectReason`] for logging and Lua handlers.
 #[derive(Task)]
 pub(crate) struct ConnectionEnd {
     pub id: ConnectionId,
+    pub reason: DisconnectReason,
 }
 
 impl TaskHandler for ConnectionEnd {
     fn run(&mut self, resources: &mut Resources) {
+        tracing::debug!(target: "TCP", "Connection {} ended: {}", self.id, self.reason);
+
         let vm = resources.get::<LuaVm>();
-        if let Err(err) = vm.trigger_event("ConnectionEndEvent", (self.id.as_u64(),)) {
+        if let Err(err) = vm.trigger_event(
+            "ConnectionEndEvent",
+            (self.id.as_u64(), self.reason.as_str()),
+        ) {
             tracing::error!(target: "TCP", "ConnectionEnd error: {err}");
         }
     }
@@ -30,7 +38,40 @@ mod tests {
         resources.insert(suon_channel::Channel::default());
         let mut task = Box::new(ConnectionEnd {
             id: ConnectionId::new(0, 1),
+            reason: DisconnectReason::Closed,
+        });
+        task.run(&mut resources);
+    }
+
+    #[test]
+    fn connection_end_passes_reason_to_lua() {
+        let vm = LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(
+                "ConnectionEndEvent = { trigger = function(_, id, reason) end_id = id; end_reason \
+                 = reason; return true end }",
+            )
+            .exec()
+            .expect("failed to define test ConnectionEndEvent");
         });
+
+        let mut resources = suon_resource::Resources::default();
+        resources.insert(vm);
+        let mut task = ConnectionEnd {
+            id: ConnectionId::new(0, 7),
+            reason: DisconnectReason::Timeout,
+        };
         task.run(&mut resources);
+
+        let (id, reason): (u64, String) = resources.get::<LuaVm>().execute(|lua| {
+            (
+                lua.globals().get("end_id").expect("end_id should be set"),
+                lua.globals()
+                    .get("end_reason")
+                    .expect("end_reason should be set"),
+            )
+        });
+        assert_eq!(id, ConnectionId::new(0, 7).as_u64());
+        assert_eq!(reason, "timeout");
     }
 }
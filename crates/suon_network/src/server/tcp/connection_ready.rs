@@ -0,0 +1,85 @@
+use std::net::SocketAddr;
+use suon_channel::TaskHandler;
+use suon_lua::LuaVm;
+use suon_macros::Task;
+use suon_resource::Resources;
+
+use crate::connection::id::ConnectionId;
+
+use super::connection_begin::fmt_ip;
+
+/// Task sent from the Tokio accept loop once a connection has been
+/// accepted and its reader/writer sessions are running.
+///
+/// Unlike [`ConnectionBegin`](super::connection_begin::ConnectionBegin),
+/// which fires before Lua has decided whether to keep the connection,
+/// this marks the point where packets can be sent to the peer.
+#[derive(Task)]
+pub(crate) struct ConnectionReady {
+    pub id: ConnectionId,
+    pub address: SocketAddr,
+}
+
+impl TaskHandler for ConnectionReady {
+    fn run(&mut self, resources: &mut Resources) {
+        let vm = resources.get::<LuaVm>();
+        let mut ip_buf = [0u8; 48];
+        let ip_str = fmt_ip(self.address.ip(), &mut ip_buf);
+        if let Err(err) = vm.trigger_event(
+            "ConnectionReadyEvent",
+            (self.id.as_u64(), ip_str, self.address.port()),
+        ) {
+            tracing::error!(target: "TCP", "ConnectionReady error: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn connection_ready_passes_id_and_address_to_lua() {
+        let vm = LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(
+                "ConnectionReadyEvent = { trigger = function(_, id, ip, port) ready = { id, ip, \
+                 port }; return true end }",
+            )
+            .exec()
+            .expect("failed to define test ConnectionReadyEvent");
+        });
+
+        let mut resources = Resources::default();
+        resources.insert(vm);
+        let mut task = ConnectionReady {
+            id: ConnectionId::new(0, 3),
+            address: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 7172),
+        };
+        task.run(&mut resources);
+
+        let (id, ip, port): (u64, String, u16) = resources.get::<LuaVm>().execute(|lua| {
+            let ready: mlua::Table = lua.globals().get("ready").expect("ready should be set");
+            (
+                ready.get(1).expect("id should be set"),
+                ready.get(2).expect("ip should be set"),
+                ready.get(3).expect("port should be set"),
+            )
+        });
+        assert_eq!(id, ConnectionId::new(0, 3).as_u64());
+        assert_eq!(ip, "10.0.0.2");
+        assert_eq!(port, 7172);
+    }
+
+    #[test]
+    fn connection_ready_task_run_without_handler_does_not_panic() {
+        let mut resources = Resources::default();
+        resources.insert(LuaVm::new());
+        let mut task = ConnectionReady {
+            id: ConnectionId::new(0, 1),
+            address: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 7171),
+        };
+        task.run(&mut resources);
+    }
+}
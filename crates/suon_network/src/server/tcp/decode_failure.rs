@@ -0,0 +1,126 @@
+use std::{collections::VecDeque, time::Duration};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::connection::handle::ConnectionHandle;
+
+/// How many packets a client may send that game code fails to handle
+/// before it is disconnected.
+///
+/// A packet counts as failed when one of its `RawPacketEvent` or
+/// `PacketEvent` handlers raises a Lua error, such as a handler calling
+/// `error()` on a field it rejects. Reading past the end of the packet
+/// is not enough, since `IncomingMessage` getters return 0 or "" there.
+/// A client is torn down once more than `max_failures` of its packets
+/// fail within any `window`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct DecodeFailurePolicy {
+    pub max_failures: u32,
+    #[serde(rename = "window_ms", with = "suon_serde::duration_ms")]
+    pub window: Duration,
+}
+
+/// One connection's recent decode failures, checked against the port's
+/// [`DecodeFailurePolicy`].
+pub(crate) struct DecodeFailures {
+    policy: DecodeFailurePolicy,
+    handle: ConnectionHandle,
+    failures: Mutex<VecDeque<Instant>>,
+}
+
+impl DecodeFailures {
+    pub fn new(policy: DecodeFailurePolicy, handle: ConnectionHandle) -> Self {
+        DecodeFailures {
+            policy,
+            handle,
+            failures: Mutex::default(),
+        }
+    }
+
+    /// Records a failure at `now` and closes the connection if it takes
+    /// the count within the window past the limit. Returns whether the
+    /// connection was closed.
+    pub fn record(&self, now: Instant) -> bool {
+        let mut failures = self.failures.lock();
+        while failures
+            .front()
+            .is_some_and(|&failed| now.duration_since(failed) >= self.policy.window)
+        {
+            failures.pop_front();
+        }
+        failures.push_back(now);
+
+        if failures.len() <= self.policy.max_failures as usize {
+            return false;
+        }
+
+        warn!(
+            target: "TCP",
+            "Connection {} failed to decode {} packets within {:?}, disconnecting",
+            self.handle.id(),
+            failures.len(),
+            self.policy.window
+        );
+        failures.clear();
+        let _ = self
+            .handle
+            .close_with_reason("too many malformed packets".to_string());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{connection::id::ConnectionId, protocol::command::Command};
+
+    fn counter(max_failures: u32) -> (DecodeFailures, crossbeam_channel::Receiver<Command>) {
+        let (sender, receiver) = crossbeam_channel::bounded(4);
+        let peer = "127.0.0.1:7171".parse().expect("valid test address");
+        let handle = ConnectionHandle::new(ConnectionId::new(0, 1), peer, sender);
+        let policy = DecodeFailurePolicy {
+            max_failures,
+            window: Duration::from_secs(1),
+        };
+        (DecodeFailures::new(policy, handle), receiver)
+    }
+
+    #[test]
+    fn closes_once_failures_exceed_the_limit() {
+        let (failures, receiver) = counter(2);
+        let now = Instant::now();
+
+        assert!(!failures.record(now));
+        assert!(!failures.record(now));
+        assert!(receiver.is_empty());
+
+        assert!(failures.record(now));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(Command::CloseWithReason(_))
+        ));
+    }
+
+    #[test]
+    fn failures_outside_the_window_are_forgotten() {
+        let (failures, receiver) = counter(1);
+        let start = Instant::now();
+
+        assert!(!failures.record(start));
+        assert!(!failures.record(start + Duration::from_secs(1)));
+        assert!(failures.record(start + Duration::from_millis(1500)));
+        assert!(receiver.try_recv().is_ok());
+    }
+
+    #[test]
+    fn policy_reads_its_window_in_milliseconds() {
+        let policy: DecodeFailurePolicy =
+            serde_json::from_str(r#"{"max_failures": 3, "window_ms": 250}"#)
+                .expect("failed to parse decode failure policy");
+        assert_eq!(policy.max_failures, 3);
+        assert_eq!(policy.window, Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,135 @@
+use super::protocol::{read_str, write_str};
+use crate::connection::{disconnect::DisconnectReason, handle::ConnectionHandle};
+
+/// Opcode of the packet telling a client why the server is dropping it.
+pub const DISCONNECT_OPCODE: u8 = 0x14;
+
+/// The reason sent to a client right before the server closes its
+/// connection over a protocol violation, so client-side logs can say more
+/// than "connection lost".
+///
+/// Encoded as the disconnect opcode, the code as a little-endian `u16`
+/// and the message as a `u16` length-prefixed string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisconnectPacket {
+    pub code: u16,
+    pub message: String,
+}
+
+impl DisconnectPacket {
+    /// The client sent a frame or payload the server could not process.
+    pub const PROTOCOL_ERROR: u16 = 1;
+    /// The client sent data that could not be decrypted.
+    pub const DECRYPTION_ERROR: u16 = 2;
+
+    /// Message sent with [`PROTOCOL_ERROR`](Self::PROTOCOL_ERROR).
+    pub const PROTOCOL_ERROR_MESSAGE: &str = "The client sent data the server could not process.";
+    /// Message sent with [`DECRYPTION_ERROR`](Self::DECRYPTION_ERROR).
+    pub const DECRYPTION_ERROR_MESSAGE: &str = "The client sent data the server could not decrypt.";
+
+    /// The packet describing `reason`, or `None` for reasons that are not
+    /// the client's fault. Each reason gets a fixed message; the detail
+    /// carried by the reason stays in the server log.
+    pub fn for_reason(reason: &DisconnectReason) -> Option<Self> {
+        let (code, message) = match reason {
+            DisconnectReason::Protocol(_) => (Self::PROTOCOL_ERROR, Self::PROTOCOL_ERROR_MESSAGE),
+            DisconnectReason::Decryption(_) => {
+                (Self::DECRYPTION_ERROR, Self::DECRYPTION_ERROR_MESSAGE)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            code,
+            message: message.to_string(),
+        })
+    }
+
+    /// Encodes the packet as a payload. A message longer than `u16::MAX`
+    /// bytes is cut at the last whole character that fits.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(5 + self.message.len());
+        payload.push(DISCONNECT_OPCODE);
+        payload.extend_from_slice(&self.code.to_le_bytes());
+        write_str(&mut payload, &self.message);
+        payload
+    }
+
+    /// Decodes a payload produced by [`DisconnectPacket::encode`], or
+    /// `None` if it is truncated, has trailing bytes or does not start
+    /// with the disconnect opcode.
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        let (&opcode, rest) = payload.split_first()?;
+        if opcode != DISCONNECT_OPCODE {
+            return None;
+        }
+
+        let (code, rest) = rest.split_first_chunk::<2>()?;
+        let (message, rest) = read_str(rest)?;
+        if !rest.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            code: u16::from_le_bytes(*code),
+            message: message.to_owned(),
+        })
+    }
+}
+
+/// Queues the disconnect packet for `reason` on `handle`, if there is
+/// one, then closes the connection once it has been written.
+pub(crate) fn notify(handle: &ConnectionHandle, reason: &DisconnectReason) {
+    let Some(packet) = DisconnectPacket::for_reason(reason) else {
+        return;
+    };
+    tracing::debug!(
+        target: "TCP",
+        "Connection {} sent disconnect code {} for: {reason:?}",
+        handle.id(),
+        packet.code
+    );
+    if let Err(e) = handle.send_and_close(packet.encode()) {
+        tracing::debug!(target: "TCP", "Connection {} disconnect packet not queued: {e}", handle.id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disconnect_packet_roundtrips_through_encode_and_decode() {
+        let packet = DisconnectPacket {
+            code: DisconnectPacket::PROTOCOL_ERROR,
+            message: "checksum mismatch".to_string(),
+        };
+        let payload = packet.encode();
+
+        assert_eq!(&payload[..5], &[DISCONNECT_OPCODE, 1, 0, 17, 0]);
+        assert_eq!(DisconnectPacket::decode(&payload), Some(packet));
+        assert_eq!(
+            DisconnectPacket::decode(&payload[..payload.len() - 1]),
+            None
+        );
+        assert_eq!(DisconnectPacket::decode(&[0x0B, 1, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn only_violations_have_a_disconnect_packet() {
+        assert_eq!(
+            DisconnectPacket::for_reason(&DisconnectReason::Decryption("bad key".into())),
+            Some(DisconnectPacket {
+                code: DisconnectPacket::DECRYPTION_ERROR,
+                message: DisconnectPacket::DECRYPTION_ERROR_MESSAGE.to_string(),
+            })
+        );
+        assert_eq!(
+            DisconnectPacket::for_reason(&DisconnectReason::Closed),
+            None
+        );
+        assert_eq!(
+            DisconnectPacket::for_reason(&DisconnectReason::Shutdown),
+            None
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// When a connection's writer session pushes buffered packets to the
+/// socket.
+///
+/// Independently of the policy, `flush_threshold` forces a flush once
+/// that many bytes are buffered, and closing or shutting down a
+/// connection always flushes what is left.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlushPolicy {
+    /// Flush on every `flush_interval_ms` tick.
+    #[default]
+    Interval,
+    /// Flush as soon as newly queued commands have been framed, trading
+    /// fewer coalesced writes for lower latency.
+    Immediate,
+    /// Only flush on an explicit `Command::Flush`.
+    Manual,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flush_policy_default_is_interval() {
+        assert_eq!(FlushPolicy::default(), FlushPolicy::Interval);
+    }
+
+    #[test]
+    fn flush_policy_uses_snake_case_names() {
+        let json = serde_json::to_string(&FlushPolicy::Immediate)
+            .expect("failed to serialize flush policy");
+        assert_eq!(json, "\"immediate\"");
+
+        let policy: FlushPolicy =
+            serde_json::from_str("\"manual\"").expect("failed to parse flush policy");
+        assert_eq!(policy, FlushPolicy::Manual);
+    }
+}
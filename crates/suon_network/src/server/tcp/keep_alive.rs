@@ -0,0 +1,48 @@
+use crate::connection::handle::ConnectionHandle;
+
+/// Opcode of a keep-alive packet. Clients send it while idle and expect
+/// the same single-byte packet back.
+pub const KEEP_ALIVE_OPCODE: u8 = 0x1E;
+
+/// Whether `payload` is a bare keep-alive packet.
+pub(crate) fn is_keep_alive(payload: &[u8]) -> bool {
+    payload == [KEEP_ALIVE_OPCODE]
+}
+
+/// Queues the server's keep-alive answer on `handle`.
+pub(crate) fn respond(handle: &ConnectionHandle) {
+    if let Err(e) = handle.send(vec![KEEP_ALIVE_OPCODE]) {
+        tracing::debug!(target: "TCP", "Connection {} keep-alive answer not queued: {e}", handle.id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{connection::id::ConnectionId, protocol::command::Command};
+
+    #[test]
+    fn only_a_bare_keep_alive_matches() {
+        assert!(is_keep_alive(&[KEEP_ALIVE_OPCODE]));
+        assert!(!is_keep_alive(&[KEEP_ALIVE_OPCODE, 0x00]));
+        assert!(!is_keep_alive(&[0x1D]));
+        assert!(!is_keep_alive(&[]));
+    }
+
+    #[test]
+    fn respond_queues_a_keep_alive_packet() {
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+        let handle = ConnectionHandle::new(
+            ConnectionId::new(0, 1),
+            "127.0.0.1:7172".parse().expect("valid test address"),
+            sender,
+        );
+
+        respond(&handle);
+
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(Command::Send(data)) if data == [KEEP_ALIVE_OPCODE]
+        ));
+    }
+}
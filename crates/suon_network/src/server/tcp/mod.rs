@@ -3,19 +3,37 @@ mod connection;
 mod connection_accept;
 mod connection_begin;
 mod connection_end;
+mod connection_ready;
+mod decode_failure;
+mod disconnect_packet;
 mod encryption;
+mod flush_policy;
+mod keep_alive;
 pub(crate) mod protocol;
 mod raw_packet;
 mod reader_session;
+pub(crate) mod reaper;
+mod reject;
+mod resume_session;
 mod session;
 mod settings;
-mod writer_session;
+mod status;
+pub(crate) mod writer_session;
 
 pub use self::{
+    decode_failure::DecodeFailurePolicy,
+    disconnect_packet::{DISCONNECT_OPCODE, DisconnectPacket},
     encryption::EncryptionSettings,
+    flush_policy::FlushPolicy,
+    keep_alive::KEEP_ALIVE_OPCODE,
     protocol::{
-        ProtocolSettings, RSA_KEY_SIZE, SEQUENCE_FIELD_LEN, SIZE_FIELD_LEN, XTEA_KEY_BYTES,
-        xtea_pad, xtea_unpad,
+        CHECKSUM_MIN_VERSION, FRAGMENT_HEADER_LEN, FRAGMENT_MORE, FRAGMENT_OPCODE,
+        MAX_WIDE_FRAME_BODY, ProtocolSettings, RSA_KEY_SIZE, SEQUENCE_FIELD_LEN, SIZE_FIELD_LEN,
+        SizeField, XTEA_KEY_BYTES, version_has_checksum, xtea_pad, xtea_pad_into, xtea_padded_len,
+        xtea_unpad,
     },
+    reject::REJECT_OPCODE,
+    resume_session::{RESUME_SESSION_OPCODE, ResumeSessionPacket},
     settings::TcpSettings,
+    status::ServerStatusPacket,
 };
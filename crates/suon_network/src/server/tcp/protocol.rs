@@ -5,9 +5,25 @@ use serde::{Deserialize, Serialize};
 /// Number of bytes in the packet size header (u16 length).
 pub const SIZE_FIELD_LEN: usize = 2;
 
+/// Largest frame body accepted with a [`SizeField::U32`] header. The
+/// field could describe 4 GiB, but a peer must not be able to make the
+/// reader reserve that much with a single size prefix.
+pub const MAX_WIDE_FRAME_BODY: usize = 16 * 1024 * 1024;
+
 /// Number of bytes in the crypto sequence / flags field.
 pub const SEQUENCE_FIELD_LEN: usize = 4;
 
+/// Opcode opening every frame of a packet that was split because it
+/// does not fit a single frame, on ports that allow fragmentation.
+pub const FRAGMENT_OPCODE: u8 = 0x0D;
+
+/// Bytes in front of each fragment: [`FRAGMENT_OPCODE`], then
+/// [`FRAGMENT_MORE`] on every fragment but the last, which carries 0.
+pub const FRAGMENT_HEADER_LEN: usize = 2;
+
+/// Continuation flag of a fragment that is followed by more.
+pub const FRAGMENT_MORE: u8 = 1;
+
 /// Minimum number of bytes in an XTEA frame body (= seq header + 1 encrypted byte).
 pub const MIN_XTEA_BODY: usize = SEQUENCE_FIELD_LEN + 1;
 
@@ -17,6 +33,68 @@ pub const XTEA_KEY_BYTES: usize = 16;
 /// 128 bytes = 1024-bit RSA key.
 pub const RSA_KEY_SIZE: usize = 128;
 
+/// First client protocol version (8.40) whose packets carry an adler32
+/// checksum.
+pub const CHECKSUM_MIN_VERSION: u16 = 840;
+
+/// Whether clients speaking protocol `version` checksum their packets.
+pub fn version_has_checksum(version: u16) -> bool {
+    version >= CHECKSUM_MIN_VERSION
+}
+
+/// Width of the little-endian size prefix in front of every frame.
+///
+/// Both directions of a connection use the same width, so peers that
+/// need frames past 64 KiB must be configured for [`U32`](Self::U32)
+/// on their port.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeField {
+    /// Two bytes, the standard framing.
+    #[default]
+    U16,
+    /// Four bytes, for frame bodies up to [`MAX_WIDE_FRAME_BODY`].
+    U32,
+}
+
+impl SizeField {
+    /// Number of bytes in the prefix.
+    pub const fn width(self) -> usize {
+        match self {
+            SizeField::U16 => SIZE_FIELD_LEN,
+            SizeField::U32 => 4,
+        }
+    }
+
+    /// Largest frame body the prefix may describe.
+    pub const fn max_body(self) -> usize {
+        match self {
+            SizeField::U16 => u16::MAX as usize,
+            SizeField::U32 => MAX_WIDE_FRAME_BODY,
+        }
+    }
+
+    /// Appends the prefix for a `len` byte body, which must not exceed
+    /// [`max_body`](Self::max_body).
+    pub fn write(self, out: &mut Vec<u8>, len: usize) {
+        debug_assert!(len <= self.max_body());
+        match self {
+            SizeField::U16 => out.extend_from_slice(&(len as u16).to_le_bytes()),
+            SizeField::U32 => out.extend_from_slice(&(len as u32).to_le_bytes()),
+        }
+    }
+
+    /// Reads a prefix of exactly [`width`](Self::width) bytes.
+    pub fn read(self, prefix: &[u8]) -> usize {
+        match self {
+            SizeField::U16 => u16::from_le_bytes([prefix[0], prefix[1]]) as usize,
+            SizeField::U32 => {
+                u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct ProtocolSettings {
     pub header_size: usize,
@@ -46,18 +124,44 @@ impl fmt::Display for ProtocolSettings {
     }
 }
 
+/// The part of a checksum-framed body that its adler32 checksum covers.
+///
+/// A checksum frame body is `[checksum: u32][payload]`; the checksum
+/// covers the payload only. Neither the u16 size header (outside the
+/// body) nor the checksum field itself is part of the region. Both the
+/// writer and the reader go through this function, so the two sides
+/// can't disagree on what is summed. Bodies shorter than the checksum
+/// field have an empty region.
+pub fn checksummed_region(body: &[u8]) -> &[u8] {
+    body.get(SEQUENCE_FIELD_LEN..).unwrap_or_default()
+}
+
 pub fn xtea_padding_byte() -> u8 {
     0x33
 }
 
 pub fn xtea_pad(data: &[u8]) -> Vec<u8> {
-    let padding = (8u8.wrapping_sub(((data.len() as u8) + 1) % 8)) % 8;
-    let padded_len = 1 + data.len() + padding as usize;
-    let mut out = Vec::with_capacity(padded_len);
+    let mut out = Vec::with_capacity(xtea_padded_len(data.len()));
+    xtea_pad_into(&mut out, data);
+    out
+}
+
+/// Length of `len` bytes once prefixed with the padding byte and padded to
+/// a whole number of XTEA blocks.
+pub fn xtea_padded_len(len: usize) -> usize {
+    (1 + len).next_multiple_of(8)
+}
+
+/// Appends the padded form of `data` to `out`, without an intermediate
+/// buffer.
+pub fn xtea_pad_into(out: &mut Vec<u8>, data: &[u8]) {
+    let padded_len = xtea_padded_len(data.len());
+    let padding = (padded_len - 1 - data.len()) as u8;
+    let end = out.len() + padded_len;
+    out.reserve(padded_len);
     out.push(padding);
     out.extend_from_slice(data);
-    out.resize(padded_len, xtea_padding_byte());
-    out
+    out.resize(end, xtea_padding_byte());
 }
 
 pub fn xtea_unpad(data: &[u8]) -> &[u8] {
@@ -95,6 +199,34 @@ pub fn read_u32_le(data: &[u8]) -> Option<(u32, &[u8])> {
     Some((value, &data[4..]))
 }
 
+/// Reads a U16-prefixed UTF-8 string without copying it.
+///
+/// The returned `&str` borrows straight from `data`, so large text
+/// fields can be inspected without allocating a `String`.
+pub fn read_str(data: &[u8]) -> Option<(&str, &[u8])> {
+    let (len, rest) = read_u16_le(data)?;
+    let len = len as usize;
+    if rest.len() < len {
+        return None;
+    }
+
+    let value = std::str::from_utf8(&rest[..len]).ok()?;
+    Some((value, &rest[len..]))
+}
+
+/// Appends `value` as a U16-prefixed string, the layout [`read_str`]
+/// expects. A string too long for the prefix is cut at the last char
+/// boundary that fits.
+pub fn write_str(out: &mut Vec<u8>, value: &str) {
+    let mut len = value.len().min(u16::MAX as usize);
+    while !value.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    out.extend_from_slice(&(len as u16).to_le_bytes());
+    out.extend_from_slice(&value.as_bytes()[..len]);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,6 +299,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn xtea_pad_into_appends_after_existing_bytes() {
+        for len in 0..16 {
+            let data = vec![b'x'; len];
+            let mut out = vec![0xEE, 0xFF];
+            xtea_pad_into(&mut out, &data);
+            assert_eq!(&out[..2], &[0xEE, 0xFF]);
+            assert_eq!(&out[2..], xtea_pad(&data).as_slice(), "len={len}");
+            assert_eq!(out.len() - 2, xtea_padded_len(len));
+        }
+    }
+
+    #[test]
+    fn checksummed_region_skips_the_checksum_field() {
+        assert_eq!(checksummed_region(b"\x01\x02\x03\x04hello"), b"hello");
+        assert_eq!(checksummed_region(b"\x01\x02\x03\x04"), b"");
+        assert_eq!(checksummed_region(b"\x01\x02"), b"");
+    }
+
     #[test]
     fn xtea_unpad_empty_data() {
         assert_eq!(xtea_unpad(b""), b"");
@@ -261,6 +412,68 @@ mod tests {
         assert_eq!(value, u32::MAX);
     }
 
+    #[test]
+    fn read_str_borrows_from_buffer() {
+        let data = [0x05, 0x00, b'h', b'e', b'l', b'l', b'o', 0xAA];
+        let (value, rest) = read_str(&data).expect("read_str should succeed with full string");
+        assert_eq!(value, "hello");
+        assert_eq!(rest, &[0xAA]);
+        assert_eq!(value.as_ptr(), data[2..].as_ptr());
+    }
+
+    #[test]
+    fn read_str_empty_string() {
+        let (value, rest) = read_str(&[0x00, 0x00]).expect("read_str should accept zero length");
+        assert_eq!(value, "");
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn read_str_truncated_body() {
+        assert!(read_str(&[0x04, 0x00, b'a', b'b']).is_none());
+    }
+
+    #[test]
+    fn read_str_invalid_utf8() {
+        assert!(read_str(&[0x02, 0x00, 0xC3, 0x28]).is_none());
+    }
+
+    #[test]
+    fn write_str_roundtrips_through_read_str() {
+        let mut data = Vec::new();
+        write_str(&mut data, "hé");
+        data.push(0xAA);
+        assert_eq!(data, [0x03, 0x00, b'h', 0xC3, 0xA9, 0xAA]);
+
+        let (value, rest) = read_str(&data).expect("written string should read back");
+        assert_eq!(value, "hé");
+        assert_eq!(rest, &[0xAA]);
+    }
+
+    #[test]
+    fn write_str_cuts_an_oversized_string_on_a_char_boundary() {
+        let value = "é".repeat(u16::MAX as usize);
+        let mut data = Vec::new();
+        write_str(&mut data, &value);
+
+        let (written, rest) = read_str(&data).expect("cut string should still be valid UTF-8");
+        assert_eq!(written.len(), u16::MAX as usize - 1);
+        assert!(value.starts_with(written));
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn size_field_roundtrips_in_little_endian() {
+        let mut out = Vec::new();
+        SizeField::U16.write(&mut out, 0x0102);
+        SizeField::U32.write(&mut out, 0x0001_0203);
+        assert_eq!(out, [0x02, 0x01, 0x03, 0x02, 0x01, 0x00]);
+
+        assert_eq!(SizeField::U16.read(&out[..2]), 0x0102);
+        assert_eq!(SizeField::U32.read(&out[2..]), 0x0001_0203);
+        assert_eq!(SizeField::default(), SizeField::U16);
+    }
+
     #[test]
     fn protocol_settings_game() {
         let cfg = ProtocolSettings {
@@ -289,6 +502,14 @@ mod tests {
         assert!(!cfg.uses_rsa);
     }
 
+    #[test]
+    fn checksums_start_at_version_840() {
+        assert!(!version_has_checksum(760));
+        assert!(!version_has_checksum(839));
+        assert!(version_has_checksum(CHECKSUM_MIN_VERSION));
+        assert!(version_has_checksum(1098));
+    }
+
     #[test]
     fn protocol_settings_status() {
         let cfg = ProtocolSettings {
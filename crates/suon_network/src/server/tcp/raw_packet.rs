@@ -1,27 +1,59 @@
+use std::sync::Arc;
+
 use suon_channel::TaskHandler;
 use suon_lua::LuaVm;
 use suon_macros::Task;
 use suon_resource::Resources;
+use tokio::{sync::OwnedSemaphorePermit, time::Instant};
+
+use crate::{
+    connection::{client_kind::ClientKind, id::ConnectionId},
+    pool::NetworkBufferPool,
+    protocol::ChecksumStatus,
+};
 
-use crate::{connection::id::ConnectionId, pool::NetworkBufferPool};
+use super::decode_failure::DecodeFailures;
 
 #[derive(Task)]
 pub struct RawPacket {
     pub id: ConnectionId,
     pub data: Vec<u8>,
+    /// Kind resolved from the connection's first packet.
+    pub kind: ClientKind,
+    /// Whether the packet arrived with a verified adler32 checksum.
+    pub checksum: ChecksumStatus,
+    /// Counts this packet against its connection's `max_pending_packets`
+    /// until it has been handled.
+    pub(crate) pending: Option<OwnedSemaphorePermit>,
+    /// The connection's failure count, when its port has a
+    /// `decode_failure_policy`.
+    pub(crate) decode_failures: Option<Arc<DecodeFailures>>,
 }
 
 impl TaskHandler for RawPacket {
     fn run(&mut self, resources: &mut Resources) {
         let vm = resources.get::<LuaVm>();
-        if let Err(err) =
-            vm.trigger_event("RawPacketEvent", (self.id.as_u64(), self.data.as_slice()))
-        {
+        if let Err(err) = vm.trigger_event(
+            "RawPacketEvent",
+            (
+                self.id.as_u64(),
+                self.data.as_slice(),
+                self.kind.as_str(),
+                match self.checksum {
+                    ChecksumStatus::Verified(checksum) => Some(checksum),
+                    ChecksumStatus::Absent => None,
+                },
+            ),
+        ) {
             tracing::error!(target: "TCP", "RawPacket error: {err}");
+            if let Some(failures) = &self.decode_failures {
+                failures.record(Instant::now());
+            }
         }
 
         let buffer_pool = &resources.get::<NetworkBufferPool>().0;
         buffer_pool.release(std::mem::take(&mut self.data));
+        self.pending.take();
     }
 }
 
@@ -38,6 +70,10 @@ mod tests {
         let packet = RawPacket {
             id: ConnectionId::new(0, 1),
             data: vec![0xAB, 0xCD],
+            kind: ClientKind::Game,
+            checksum: ChecksumStatus::Absent,
+            pending: None,
+            decode_failures: None,
         };
         assert_eq!(packet.id.sequence(), 1);
         assert_eq!(packet.data, vec![0xAB, 0xCD]);
@@ -53,6 +89,10 @@ mod tests {
         let mut task = Box::new(RawPacket {
             id: ConnectionId::new(0, 3),
             data: vec![0xAB],
+            kind: ClientKind::Unknown,
+            checksum: ChecksumStatus::Absent,
+            pending: None,
+            decode_failures: None,
         });
         task.run(&mut resources);
     }
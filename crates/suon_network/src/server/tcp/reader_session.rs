@@ -1,34 +1,68 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 use tracing::{error, trace};
 
 use suon_channel::{BufferPool, Channel};
-use tokio::io::AsyncReadExt;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, BufReader},
+    sync::{OwnedSemaphorePermit, Semaphore, watch},
+    time::Instant,
+};
 
 use crate::{
-    connection::{id::ConnectionId, manager::ConnectionManager},
+    connection::{
+        auth_state::AuthState, client_kind::ClientKind, disconnect::DisconnectReason,
+        handle::ConnectionHandle, id::ConnectionId, manager::ConnectionManager,
+        tap::PacketDirection,
+    },
     protocol::reader::{PacketReader, ProcessOutcome},
-    server::tcp::settings::TcpSettings,
+    server::tcp::{protocol::SizeField, settings::TcpSettings},
 };
 
-use super::{connection_end::ConnectionEnd, raw_packet::RawPacket};
+use super::{
+    connection_end::ConnectionEnd,
+    decode_failure::DecodeFailures,
+    disconnect_packet, keep_alive,
+    raw_packet::RawPacket,
+    resume_session::{self, ResumeSessionPacket, SessionResumed},
+    status::{self, ServerStatusPacket},
+};
 use crate::server::{shutdown::Shutdown, throttle::ConnectionPermit};
 
-pub(crate) struct ReaderSession {
+/// Reads and dispatches packets from the read half of a connection.
+///
+/// Generic over the transport so tests can drive it with an in-memory
+/// pipe; the server always uses a TCP read half.
+pub(crate) struct ReaderSession<R = tokio::net::tcp::OwnedReadHalf> {
     id: ConnectionId,
-    reader_half: tokio::net::tcp::OwnedReadHalf,
+    /// Buffered so that several small packets coalesced into one TCP
+    /// segment are drained from a single socket read.
+    reader_half: BufReader<R>,
     reader_channel: Channel,
     buffer_pool: Arc<BufferPool>,
     config: TcpSettings,
     shutdown: Shutdown,
     manager: Arc<ConnectionManager>,
     permit: Option<ConnectionPermit>,
+    checksum_enabled: Arc<AtomicBool>,
+    /// One permit per packet queued for Lua but not yet handled.
+    pending: Arc<Semaphore>,
 }
 
-impl ReaderSession {
+impl<R> ReaderSession<R>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
     #[expect(clippy::too_many_arguments)]
     pub fn new(
         id: ConnectionId,
-        reader_half: tokio::net::tcp::OwnedReadHalf,
+        reader_half: R,
         reader_channel: Channel,
         config: TcpSettings,
         shutdown: Shutdown,
@@ -36,18 +70,29 @@ impl ReaderSession {
         permit: ConnectionPermit,
         buffer_pool: Arc<BufferPool>,
     ) -> Self {
+        let checksum_enabled = Arc::new(AtomicBool::new(config.protocol.has_checksum));
+        let pending = Arc::new(Semaphore::new(config.max_pending_packets));
         ReaderSession {
             id,
-            reader_half,
+            reader_half: BufReader::with_capacity(buffer_pool.buffer_size(), reader_half),
             reader_channel,
             buffer_pool,
             config,
             shutdown,
             manager,
             permit: Some(permit),
+            checksum_enabled,
+            pending,
         }
     }
 
+    /// Follows the checksum mode the writer session negotiates instead of
+    /// the fixed protocol setting.
+    pub fn with_checksum_flag(mut self, checksum_enabled: Arc<AtomicBool>) -> Self {
+        self.checksum_enabled = checksum_enabled;
+        self
+    }
+
     pub fn spawn(self) {
         tokio::spawn(self.run());
     }
@@ -55,22 +100,52 @@ impl ReaderSession {
     async fn run(mut self) {
         let mut reader = PacketReader::new(self.config.protocol);
         reader.set_xtea_enabled(self.config.encryption.incoming);
+        reader.set_fragmentation(self.config.allow_fragmentation);
+        reader.set_fragment_limit(self.config.max_fragmented_size);
 
-        let mut size_buf = [0u8; 2];
+        let size_field = self.config.size_field;
+        let mut size_buf = [0u8; 4];
         let mut body_buf = self.buffer_pool.acquire();
         let mut rx = self.shutdown.receiver();
+        let mut client_kind = None;
+        let handle = self.manager.get(self.id);
+        let _alive = handle.as_ref().map(ConnectionHandle::reader_liveness);
+        let decode_failures = self
+            .config
+            .decode_failure_policy
+            .zip(handle.clone())
+            .map(|(policy, handle)| Arc::new(DecodeFailures::new(policy, handle)));
+        let size_limits = handle
+            .as_ref()
+            .map(|handle| handle.watch_packet_size_limits());
+        let handshake_deadline = self
+            .config
+            .handshake_timeout
+            .map(|timeout| Instant::now() + timeout);
+        let mut key_deadline = None;
         trace!(target: "TCP", "Reader session {} started", self.id);
 
-        loop {
+        let reason = loop {
+            let handshaking = !handle
+                .as_ref()
+                .is_some_and(|handle| handle.is_handshake_complete());
+            let deadline = match key_deadline.filter(|_| handshaking) {
+                Some(key_deadline) => earliest(handshake_deadline, Some(key_deadline)),
+                None => packet_deadline(
+                    handshake_deadline.filter(|_| handshaking),
+                    self.config.read_timeout,
+                ),
+            };
+
             let size = tokio::select! {
                 _ = rx.changed() => {
-                    if *rx.borrow() { break; }
+                    if *rx.borrow() { break DisconnectReason::Shutdown; }
                     continue;
                 }
-                result = self.reader_half.read(&mut size_buf) => {
+                result = within(deadline, read_size(&mut self.reader_half, size_field, &mut size_buf)) => {
                     match result {
-                        Ok(2) => u16::from_le_bytes(size_buf) as usize,
-                        _ => break,
+                        Ok(size) => size,
+                        Err(e) => break DisconnectReason::from(&e),
                     }
                 }
             };
@@ -78,47 +153,322 @@ impl ReaderSession {
             if size == 0 {
                 continue;
             }
-
-            body_buf.resize(size, 0);
-            let body_slice = &mut body_buf[..size];
+            if size > size_field.max_body() {
+                let detail = format!(
+                    "declared frame of {size} bytes exceeds the {} byte limit",
+                    size_field.max_body()
+                );
+                error!(target: "TCP", "Reader session {}: {detail}", self.id);
+                break DisconnectReason::Protocol(detail);
+            }
 
             tokio::select! {
                 _ = rx.changed() => {
-                    if *rx.borrow() { break; }
+                    if *rx.borrow() { break DisconnectReason::Shutdown; }
                 }
-                result = self.reader_half.read_exact(body_slice) => {
-                    if result.is_err() { break; }
+                result = within(deadline, read_body(&mut self.reader_half, &mut body_buf, size)) => {
+                    if let Err(e) = result { break DisconnectReason::from(&e); }
                 }
             }
 
+            let packet_tap = self.manager.packet_tap();
+            if packet_tap.is_enabled() {
+                let frame = [&size_buf[..size_field.width()], &body_buf].concat();
+                packet_tap.record(self.id, PacketDirection::Incoming, frame);
+            }
+
             trace!(target: "TCP", "Reader session {} processing {} bytes", self.id, size);
+            let checksum_enabled = self.checksum_enabled.load(Ordering::Acquire);
+            reader.set_checksum_enabled(checksum_enabled);
+
+            // Recognised before decryption: the client has no key yet,
+            // and restoring one is what the packet is for.
+            if client_kind.is_none()
+                && self.config.allow_resumption
+                && let Some(packet) = ResumeSessionPacket::from_frame(
+                    &body_buf,
+                    self.config.protocol,
+                    checksum_enabled,
+                )
+            {
+                let Some(session) = self.manager.session_tokens().redeem(&packet.token) else {
+                    let detail = "invalid or expired resumption token".to_string();
+                    error!(target: "TCP", "Reader session {}: {detail}", self.id);
+                    break DisconnectReason::Protocol(detail);
+                };
+
+                if let Some(key) = session.xtea_key {
+                    reader.set_xtea_key(key);
+                }
+                reader.set_rsa_done(true);
+                client_kind = Some(session.client_kind.unwrap_or(ClientKind::Unknown));
+                if let Some(handle) = &handle {
+                    resume_session::restore(handle, &session);
+                }
+                trace!(
+                    target: "TCP",
+                    "Reader session {} resumed the session of {}",
+                    self.id,
+                    session.previous
+                );
+                self.reader_channel.send(SessionResumed {
+                    id: self.id,
+                    previous: session.previous,
+                });
+                self.manager
+                    .stats()
+                    .record_packet_received((size_field.width() + size) as u64);
+                continue;
+            }
+
+            let limits = size_limits
+                .as_ref()
+                .and_then(|limits| limits.borrow().clone())
+                .unwrap_or_else(|| self.config.packet_size_limits.clone());
+            // Checked before decryption where the frame allows it, so an
+            // oversized packet is never decrypted.
+            let peeked = reader.peek_payload(&body_buf);
+            if let Some(detail) =
+                peeked.and_then(|(opcode, len)| size_violation(&limits, opcode, len))
+            {
+                error!(target: "TCP", "Reader session {}: {detail}", self.id);
+                break DisconnectReason::Protocol(detail);
+            }
+
             match reader.process_in_place(&mut body_buf) {
                 Ok(ProcessOutcome::Complete) => {
+                    // Compressed and reassembled packets are only sized
+                    // once decoded.
+                    let unpeeked = body_buf.first().filter(|_| peeked.is_none());
+                    if let Some(detail) =
+                        unpeeked.and_then(|&opcode| size_violation(&limits, opcode, body_buf.len()))
+                    {
+                        error!(target: "TCP", "Reader session {}: {detail}", self.id);
+                        break DisconnectReason::Protocol(detail);
+                    }
+
+                    self.manager
+                        .stats()
+                        .record_packet_received((size_field.width() + size) as u64);
+
+                    if self.config.auto_keep_alive && keep_alive::is_keep_alive(&body_buf) {
+                        if let Some(handle) = &handle {
+                            keep_alive::respond(handle);
+                        }
+                        continue;
+                    }
+                    let kind = *client_kind.get_or_insert_with(|| {
+                        key_deadline = self.config.key_timeout.map(|timeout| Instant::now() + timeout);
+                        let kind = ClientKind::from_first_packet_with(
+                            &body_buf,
+                            &self.config.client_kind_opcodes,
+                        );
+                        if let Some(handle) = &handle {
+                            handle.resolve_client_kind(kind);
+                        }
+                        trace!(target: "TCP", "Reader session {} resolved as a {kind} client", self.id);
+                        kind
+                    });
+
+                    if kind == ClientKind::Status
+                        && let Some(motd) = &self.config.status_motd
+                    {
+                        if let Some(handle) = &handle {
+                            let status = ServerStatusPacket {
+                                players_online: self.manager.count() as u32,
+                                max_players: self.config.max_connections,
+                                motd: motd.clone(),
+                            };
+                            status::respond(handle, &status);
+                        }
+                        continue;
+                    }
+
+                    if self.config.require_authentication
+                        && handle.as_ref().is_some_and(awaits_authentication)
+                    {
+                        trace!(
+                            target: "TCP",
+                            "Reader session {} dropping a packet before authentication",
+                            self.id
+                        );
+                        continue;
+                    }
+
+                    let Some(pending) = acquire_pending(&self.pending, self.id, &mut rx).await
+                    else {
+                        break DisconnectReason::Shutdown;
+                    };
+
                     let data = std::mem::take(&mut body_buf);
-                    self.reader_channel.send(RawPacket { id: self.id, data });
+                    self.reader_channel.send(RawPacket {
+                        id: self.id,
+                        data,
+                        kind,
+                        checksum: reader.checksum_status(),
+                        pending: Some(pending),
+                        decode_failures: decode_failures.clone(),
+                    });
                     body_buf = self.buffer_pool.acquire();
                 }
                 Ok(ProcessOutcome::Skip) => {}
                 Err(e) => {
-                    error!(target: "TCP", "Reader session {} processing error: {e}", self.id);
-                    break;
+                    match e.hint() {
+                        Some(hint) => {
+                            error!(target: "TCP", "Reader session {} processing error: {e} ({hint})", self.id)
+                        }
+                        None => {
+                            error!(target: "TCP", "Reader session {} processing error: {e}", self.id)
+                        }
+                    }
+                    break DisconnectReason::from(&e);
                 }
             }
+        };
+
+        if self.config.send_disconnect_packets
+            && let Some(handle) = &handle
+        {
+            disconnect_packet::notify(handle, &reason);
         }
 
         self.buffer_pool.release(body_buf);
-        self.reader_channel.send(ConnectionEnd { id: self.id });
+        self.reader_channel.send(ConnectionEnd {
+            id: self.id,
+            reason,
+        });
         self.manager.unregister(self.id);
         drop(self.permit.take());
     }
 }
 
+/// Reserves a slot for one more packet queued by connection `id`,
+/// waiting while it already has `max_pending_packets` in flight. Returns
+/// `None` if the server shuts down first.
+async fn acquire_pending(
+    pending: &Arc<Semaphore>,
+    id: ConnectionId,
+    rx: &mut watch::Receiver<bool>,
+) -> Option<OwnedSemaphorePermit> {
+    if let Ok(permit) = pending.clone().try_acquire_owned() {
+        return Some(permit);
+    }
+
+    trace!(target: "TCP", "Reader session {id} paused: too many packets pending");
+    loop {
+        tokio::select! {
+            permit = pending.clone().acquire_owned() => return permit.ok(),
+            _ = rx.changed() => {
+                if *rx.borrow() { return None; }
+            }
+        }
+    }
+}
+
+/// Describes the violation if a payload of `len` bytes opening with
+/// `opcode` is larger than the limit configured for that opcode.
+fn size_violation(limits: &BTreeMap<u8, usize>, opcode: u8, len: usize) -> Option<String> {
+    let limit = *limits.get(&opcode)?;
+    (len > limit)
+        .then(|| format!("packet 0x{opcode:02X} of {len} bytes exceeds its {limit} byte limit"))
+}
+
+/// Whether `handle` is past its handshake but not yet authenticated, the
+/// window in which a port that requires authentication drops packets.
+fn awaits_authentication(handle: &ConnectionHandle) -> bool {
+    handle.is_handshake_complete() && handle.auth_state() == AuthState::Unauthenticated
+}
+
+/// Deadline for reading the next packet: the end of the handshake
+/// budget while the handshake runs, or the per-read timeout, whichever
+/// comes first.
+fn packet_deadline(handshake: Option<Instant>, read_timeout: Option<Duration>) -> Option<Instant> {
+    earliest(
+        handshake,
+        read_timeout.map(|timeout| Instant::now() + timeout),
+    )
+}
+
+/// The sooner of two optional deadlines; `None` means no deadline.
+fn earliest(a: Option<Instant>, b: Option<Instant>) -> Option<Instant> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Runs `read` until `deadline`, failing with `TimedOut` past it.
+async fn within<T>(
+    deadline: Option<Instant>,
+    read: impl Future<Output = std::io::Result<T>>,
+) -> std::io::Result<T> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, read)
+            .await
+            .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into())),
+        None => read.await,
+    }
+}
+
+/// Reads the little-endian size prefix, 2 or 4 bytes wide depending on
+/// `size_field`, into the front of `size_buf`.
+///
+/// The prefix may arrive split across TCP segments, so a short read is
+/// completed by the next one instead of being treated as a disconnect.
+/// Only an EOF before the whole prefix arrived ends the session.
+async fn read_size<R>(
+    reader: &mut R,
+    size_field: SizeField,
+    size_buf: &mut [u8; 4],
+) -> std::io::Result<usize>
+where
+    R: AsyncRead + Unpin,
+{
+    let prefix = &mut size_buf[..size_field.width()];
+    reader.read_exact(prefix).await?;
+    Ok(size_field.read(prefix))
+}
+
+/// Most a body buffer grows by ahead of the bytes arriving, unless
+/// it is already larger, in which case it doubles as it fills.
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Reads exactly `size` bytes into `buf`, replacing its contents.
+///
+/// Unlike resizing and calling `read_exact`, the buffer is never
+/// zero-filled: bytes are appended straight from the socket and the
+/// allocation only grows when the declared size exceeds the current
+/// capacity, so a 10-byte keep-alive never touches more than 10 bytes.
+/// The declared size is not trusted up front either: the buffer only
+/// grows once it is full, by up to [`READ_CHUNK`] bytes or its current
+/// size, so a peer announcing a large frame it never sends cannot make
+/// the reader allocate much more than it actually sent.
+async fn read_body<R>(reader: &mut R, buf: &mut Vec<u8>, size: usize) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    buf.clear();
+
+    while buf.len() < size {
+        let remaining = size - buf.len();
+        if buf.capacity() == buf.len() {
+            buf.reserve(remaining.min(READ_CHUNK));
+        }
+        let limit = remaining.min(buf.capacity() - buf.len()) as u64;
+        if (&mut *reader).take(limit).read_buf(buf).await? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::server::throttle::ConnectionLimiter;
+    use crate::{protocol::command::Command, server::throttle::ConnectionLimiter};
     use std::{sync::Arc, time::Duration};
-    use tokio::net::TcpListener;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
 
     fn make_config() -> TcpSettings {
         TcpSettings {
@@ -136,8 +486,7 @@ mod tests {
             channel_capacity: 64,
             max_buffer_size: 256,
             max_connections: 5,
-            connection_timeout_secs: 10,
-            rate_burst: 50,
+            ..TcpSettings::default()
         }
     }
 
@@ -151,6 +500,903 @@ mod tests {
         (manager, permit)
     }
 
+    #[tokio::test]
+    async fn read_body_allocates_exactly_declared_size() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client
+            .write_all(&[0xAB; 10])
+            .await
+            .expect("failed to write keep-alive sized body");
+
+        let mut buf = Vec::new();
+        read_body(&mut server, &mut buf, 10)
+            .await
+            .expect("reading a complete body should succeed");
+
+        assert_eq!(buf, vec![0xAB; 10]);
+        assert_eq!(buf.capacity(), 10);
+    }
+
+    #[tokio::test]
+    async fn read_body_reuses_pooled_capacity_for_small_packets() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client
+            .write_all(&[1, 2, 3])
+            .await
+            .expect("failed to write small body");
+
+        let mut buf = Vec::with_capacity(4096);
+        buf.extend_from_slice(&[0xFF; 32]);
+        read_body(&mut server, &mut buf, 3)
+            .await
+            .expect("reading a small body should succeed");
+
+        assert_eq!(buf, vec![1, 2, 3]);
+        assert_eq!(buf.capacity(), 4096);
+    }
+
+    #[tokio::test]
+    async fn read_body_grows_in_bounded_steps_as_the_body_arrives() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client
+            .write_all(&[7; 10])
+            .await
+            .expect("failed to write the start of a large body");
+        drop(client);
+
+        let mut buf = Vec::new();
+        read_body(
+            &mut server,
+            &mut buf,
+            crate::server::tcp::MAX_WIDE_FRAME_BODY,
+        )
+        .await
+        .expect_err("a body that never arrives should fail");
+
+        assert_eq!(buf, vec![7; 10]);
+        assert!(buf.capacity() <= READ_CHUNK);
+    }
+
+    #[tokio::test]
+    async fn read_body_reads_bodies_past_one_step() {
+        let body: Vec<u8> = (0..3 * READ_CHUNK + 5).map(|i| i as u8).collect();
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+        let writer = tokio::spawn({
+            let body = body.clone();
+            async move { client.write_all(&body).await }
+        });
+
+        let mut buf = Vec::new();
+        read_body(&mut server, &mut buf, body.len())
+            .await
+            .expect("reading a large body should succeed");
+
+        writer
+            .await
+            .expect("writer task panicked")
+            .expect("failed to write large body");
+        assert_eq!(buf, body);
+    }
+
+    #[tokio::test]
+    async fn read_body_reports_unexpected_eof_on_short_body() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client
+            .write_all(&[1, 2])
+            .await
+            .expect("failed to write truncated body");
+        drop(client);
+
+        let mut buf = Vec::new();
+        let err = read_body(&mut server, &mut buf, 5)
+            .await
+            .expect_err("a truncated body should fail");
+
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    /// Waits until `expected` tasks are queued on `channel`, then runs
+    /// them against a Lua VM prepared with `script`.
+    async fn run_queued(
+        channel: &Channel,
+        expected: usize,
+        script: &str,
+    ) -> suon_resource::Resources {
+        for _ in 0..100 {
+            if channel.pending_count() >= expected {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let vm = suon_lua::LuaVm::new();
+        vm.execute(|lua| {
+            lua.load(script)
+                .exec()
+                .expect("failed to define test event handlers");
+        });
+
+        let mut resources = suon_resource::Resources::default();
+        resources.insert(vm);
+        resources.insert(crate::pool::NetworkBufferPool(crate::test_buffer_pool()));
+
+        let mut tasks = Vec::new();
+        channel.wait_and_drain(&mut tasks);
+        for task in &mut tasks {
+            task.run(&mut resources);
+        }
+
+        resources
+    }
+
+    /// Reason passed to `ConnectionEndEvent` by the queued tasks.
+    async fn end_reason(channel: &Channel) -> Option<String> {
+        run_queued(
+            channel,
+            1,
+            "ConnectionEndEvent = { trigger = function(_, _, reason) end_reason = reason; return \
+             true end }",
+        )
+        .await
+        .get::<suon_lua::LuaVm>()
+        .execute(|lua| lua.globals().get("end_reason").ok())
+    }
+
+    async fn spawn_reader(channel: Channel) -> tokio::net::TcpStream {
+        spawn_reader_with(channel, make_config()).await
+    }
+
+    async fn spawn_reader_with(channel: Channel, config: TcpSettings) -> tokio::net::TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind TCP listener for reason test");
+
+        let addr = listener
+            .local_addr()
+            .expect("failed to get listener local address");
+
+        let (manager, permit) = setup();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener
+                .accept()
+                .await
+                .expect("failed to accept incoming connection");
+
+            let (reader_half, ..) = stream.into_split();
+            let (sender, ..) = crossbeam_channel::bounded(64);
+            let id = manager.register(addr, config.protocol, sender);
+
+            ReaderSession::new(
+                id,
+                reader_half,
+                channel,
+                config,
+                Shutdown::new(),
+                manager,
+                permit,
+                crate::test_buffer_pool(),
+            )
+            .spawn();
+        });
+
+        tokio::net::TcpStream::connect(addr)
+            .await
+            .expect("failed to connect test client")
+    }
+
+    /// Sends `first` and then a follow-up packet over an in-memory pipe and
+    /// returns the client kind each `RawPacketEvent` saw, plus the kind
+    /// recorded on the connection handle.
+    async fn resolve_kinds(first: &[u8]) -> (Vec<String>, Option<ClientKind>) {
+        resolve_kinds_with(first, make_config()).await
+    }
+
+    async fn resolve_kinds_with(
+        first: &[u8],
+        config: TcpSettings,
+    ) -> (Vec<String>, Option<ClientKind>) {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let (reader_half, _writer_half) = tokio::io::split(server);
+        let (manager, permit) = setup();
+        let channel = Channel::default();
+
+        let (sender, _receiver) = crossbeam_channel::bounded(64);
+        let peer = "127.0.0.1:7171".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, sender);
+        ReaderSession::new(
+            id,
+            reader_half,
+            channel.clone(),
+            config,
+            Shutdown::new(),
+            manager.clone(),
+            permit,
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        for payload in [first, &[0x64]] {
+            // Zero ("no") checksum.
+            let mut frame = ((4 + payload.len()) as u16).to_le_bytes().to_vec();
+            frame.extend_from_slice(&[0; 4]);
+            frame.extend_from_slice(payload);
+            client
+                .write_all(&frame)
+                .await
+                .expect("failed to write test packet");
+        }
+
+        let resources = run_queued(
+            &channel,
+            2,
+            "kinds = {}; RawPacketEvent = { trigger = function(_, _, _, kind) table.insert(kinds, \
+             kind); return true end }",
+        )
+        .await;
+
+        let kinds: Vec<String> = resources
+            .get::<suon_lua::LuaVm>()
+            .execute(|lua| lua.globals().get("kinds"))
+            .expect("kinds should be recorded");
+        let handle = manager
+            .get(id)
+            .expect("connection should still be registered");
+        (kinds, handle.client_kind())
+    }
+
+    #[tokio::test]
+    async fn reader_session_answers_keep_alive_when_enabled() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let (reader_half, _writer_half) = tokio::io::split(server);
+        let (manager, permit) = setup();
+        let channel = Channel::default();
+        let config = TcpSettings {
+            auto_keep_alive: true,
+            ..make_config()
+        };
+
+        let (sender, receiver) = crossbeam_channel::bounded(64);
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, sender);
+        ReaderSession::new(
+            id,
+            reader_half,
+            channel.clone(),
+            config,
+            Shutdown::new(),
+            manager,
+            permit,
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        // A keep-alive followed by a regular packet, both with a zero checksum.
+        client
+            .write_all(&[0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1E])
+            .await
+            .expect("failed to write keep-alive");
+        client
+            .write_all(&[0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64])
+            .await
+            .expect("failed to write regular packet");
+
+        let resources = run_queued(
+            &channel,
+            1,
+            "packets = {}; RawPacketEvent = { trigger = function(_, _, data) \
+             table.insert(packets, data); return true end }",
+        )
+        .await;
+
+        let packets: Vec<Vec<u8>> = resources
+            .get::<suon_lua::LuaVm>()
+            .execute(|lua| lua.globals().get("packets"))
+            .expect("packets should be recorded");
+        assert_eq!(packets, [[0x64]], "the keep-alive must not reach Lua");
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(Command::Send(data)) if data == [0x1E]
+        ));
+    }
+
+    #[tokio::test]
+    async fn reader_session_stops_reading_at_max_pending_packets() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let (reader_half, _writer_half) = tokio::io::split(server);
+        let (manager, permit) = setup();
+        let channel = Channel::default();
+        let config = TcpSettings {
+            max_pending_packets: 3,
+            ..make_config()
+        };
+
+        let (sender, _receiver) = crossbeam_channel::bounded(64);
+        let peer = "127.0.0.1:7173".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, sender);
+        ReaderSession::new(
+            id,
+            reader_half,
+            channel.clone(),
+            config,
+            Shutdown::new(),
+            manager,
+            permit,
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        // Nobody drains the channel while the client floods it.
+        for opcode in 0..6u8 {
+            client
+                .write_all(&[0x05, 0x00, 0x00, 0x00, 0x00, 0x00, opcode])
+                .await
+                .expect("failed to write test packet");
+        }
+
+        let wait_for = |expected| {
+            let channel = channel.clone();
+            async move {
+                tokio::time::timeout(Duration::from_secs(1), async {
+                    while channel.pending_count() < expected {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                    }
+                })
+                .await
+                .expect("packets should be queued")
+            }
+        };
+
+        wait_for(3).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            channel.pending_count(),
+            3,
+            "the reader must pause at the limit"
+        );
+
+        // Handling (here: dropping) the queued packets lets the rest through.
+        let mut tasks = Vec::new();
+        channel.wait_and_drain(&mut tasks);
+        drop(tasks);
+
+        wait_for(3).await;
+        assert_eq!(channel.pending_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn status_first_packet_routes_as_status_client() {
+        let (kinds, resolved) = resolve_kinds(&[0xFF, 0x01]).await;
+
+        assert_eq!(kinds, ["status", "status"]);
+        assert_eq!(resolved, Some(ClientKind::Status));
+    }
+
+    #[tokio::test]
+    async fn configured_opcode_opens_the_login_stage() {
+        let config = TcpSettings {
+            client_kind_opcodes: BTreeMap::from([(0x14, ClientKind::Login)]),
+            ..make_config()
+        };
+        let (kinds, resolved) = resolve_kinds_with(&[0x14], config).await;
+
+        assert_eq!(kinds, ["login", "login"]);
+        assert_eq!(resolved, Some(ClientKind::Login));
+    }
+
+    #[tokio::test]
+    async fn game_first_packet_routes_as_game_client() {
+        let (kinds, resolved) = resolve_kinds(&[0x0A, 0x02, 0x00]).await;
+
+        assert_eq!(kinds, ["game", "game"]);
+        assert_eq!(resolved, Some(ClientKind::Game));
+    }
+
+    #[tokio::test]
+    async fn reader_session_delivers_packets_coalesced_in_one_write() {
+        let channel = Channel::default();
+        let mut client = spawn_reader(channel.clone()).await;
+
+        // Two checksum-less packets ("ab" and "cde") in a single segment
+        client
+            .write_all(&[
+                0x06, 0x00, 0x00, 0x00, 0x00, 0x00, b'a', b'b', 0x07, 0x00, 0x00, 0x00, 0x00, 0x00,
+                b'c', b'd', b'e',
+            ])
+            .await
+            .expect("failed to write coalesced packets");
+
+        let packets: Vec<Vec<u8>> = run_queued(
+            &channel,
+            2,
+            "packets = {}; RawPacketEvent = { trigger = function(_, _, data) \
+             table.insert(packets, data); return true end }",
+        )
+        .await
+        .get::<suon_lua::LuaVm>()
+        .execute(|lua| {
+            lua.globals()
+                .get("packets")
+                .expect("packets table should exist")
+        });
+
+        assert_eq!(packets, vec![b"ab".to_vec(), b"cde".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn reader_session_passes_the_checksum_status_to_lua() {
+        let channel = Channel::default();
+        let mut client = spawn_reader(channel.clone()).await;
+
+        let checksum = suon_adler32::generate(b"ab");
+        let mut checked = vec![0x06, 0x00];
+        checked.extend_from_slice(&checksum.to_le_bytes());
+        checked.extend_from_slice(b"ab");
+        client
+            .write_all(&checked)
+            .await
+            .expect("failed to write checksummed packet");
+        client
+            .write_all(&[0x05, 0x00, 0x00, 0x00, 0x00, 0x00, b'c'])
+            .await
+            .expect("failed to write packet without checksum");
+
+        let checksums: Vec<mlua::Value> = run_queued(
+            &channel,
+            2,
+            "checksums = {}; RawPacketEvent = { trigger = function(_, _, _, _, checksum) \
+             table.insert(checksums, checksum or false); return true end }",
+        )
+        .await
+        .get::<suon_lua::LuaVm>()
+        .execute(|lua| {
+            lua.globals()
+                .get("checksums")
+                .expect("checksums table should exist")
+        });
+
+        assert_eq!(checksums.len(), 2);
+        assert_eq!(checksums[0].as_u32(), Some(checksum));
+        assert_eq!(checksums[1].as_boolean(), Some(false));
+    }
+
+    #[tokio::test]
+    async fn reader_session_reassembles_split_and_coalesced_packets() {
+        let channel = Channel::default();
+        let mut client = spawn_reader(channel.clone()).await;
+
+        // "ab", "cde" and "f" spread over writes that split both the size
+        // prefix and the body, and that glue packets together.
+        let stream: &[&[u8]] = &[
+            &[0x06],
+            &[0x00, 0x00, 0x00],
+            &[0x00, 0x00, b'a'],
+            &[b'b', 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, b'c'],
+            &[b'd', b'e', 0x05],
+            &[0x00, 0x00, 0x00, 0x00, 0x00, b'f'],
+        ];
+        for chunk in stream {
+            client
+                .write_all(chunk)
+                .await
+                .expect("failed to write stream chunk");
+            client.flush().await.expect("failed to flush test client");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let packets: Vec<Vec<u8>> = run_queued(
+            &channel,
+            3,
+            "packets = {}; RawPacketEvent = { trigger = function(_, _, data) \
+             table.insert(packets, data); return true end }",
+        )
+        .await
+        .get::<suon_lua::LuaVm>()
+        .execute(|lua| {
+            lua.globals()
+                .get("packets")
+                .expect("packets table should exist")
+        });
+
+        assert_eq!(
+            packets,
+            vec![b"ab".to_vec(), b"cde".to_vec(), b"f".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_size_completes_split_prefix() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let reader = tokio::spawn(async move {
+            let mut size_buf = [0u8; 4];
+            read_size(&mut server, SizeField::U16, &mut size_buf).await
+        });
+
+        client
+            .write_all(&[0x34])
+            .await
+            .expect("failed to write first half");
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        client
+            .write_all(&[0x12])
+            .await
+            .expect("failed to write second half");
+
+        let size = reader
+            .await
+            .expect("reader task should not panic")
+            .expect("split prefix should be reassembled");
+        assert_eq!(size, 0x1234);
+    }
+
+    #[tokio::test]
+    async fn read_size_reports_eof_mid_prefix() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client
+            .write_all(&[0x05])
+            .await
+            .expect("failed to write half prefix");
+        drop(client);
+
+        let mut size_buf = [0u8; 4];
+        let err = read_size(&mut server, SizeField::U16, &mut size_buf)
+            .await
+            .expect_err("EOF inside the prefix should fail");
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn reader_session_reports_closed_reason_on_eof() {
+        let channel = Channel::default();
+        let client = spawn_reader(channel.clone()).await;
+        drop(client);
+
+        assert_eq!(end_reason(&channel).await.as_deref(), Some("closed"));
+    }
+
+    #[tokio::test]
+    async fn reader_session_reports_protocol_error_reason() {
+        let channel = Channel::default();
+        let mut client = spawn_reader(channel.clone()).await;
+
+        // size=5, checksum that cannot match the single payload byte
+        client
+            .write_all(&[0x05, 0x00, 0xEF, 0xBE, 0xAD, 0xDE, 0x01])
+            .await
+            .expect("failed to write packet with bad checksum");
+
+        assert_eq!(
+            end_reason(&channel).await.as_deref(),
+            Some("protocol_error")
+        );
+    }
+
+    #[tokio::test]
+    async fn reader_session_reports_decryption_error_reason() {
+        let channel = Channel::default();
+        let mut config = make_config();
+        config.protocol.uses_xtea = true;
+        config.encryption.incoming = true;
+        let mut client = spawn_reader_with(channel.clone(), config).await;
+
+        // size=12, zero checksum, one XTEA block sent before any key is set
+        client
+            .write_all(&[0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, 1, 2, 3, 4, 5, 6, 7, 8])
+            .await
+            .expect("failed to write encrypted packet");
+
+        assert_eq!(
+            end_reason(&channel).await.as_deref(),
+            Some("decryption_error")
+        );
+    }
+
+    #[tokio::test]
+    async fn reader_session_enforces_limits_tightened_mid_session() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let (reader_half, _writer_half) = tokio::io::split(server);
+        let (manager, permit) = setup();
+        let channel = Channel::default();
+        let config = make_config();
+
+        let (sender, _receiver) = crossbeam_channel::bounded(64);
+        let peer = "127.0.0.1:7174".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, sender);
+        let handle = manager.get(id).expect("connection should be registered");
+        ReaderSession::new(
+            id,
+            reader_half,
+            channel.clone(),
+            config,
+            Shutdown::new(),
+            manager,
+            permit,
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        // size=7, zero checksum, a three byte 0x64 packet
+        let packet = [0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00];
+        client
+            .write_all(&packet)
+            .await
+            .expect("failed to write first packet");
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while channel.pending_count() < 1 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("the first packet should be within the port's limits");
+
+        handle.set_packet_size_limits(BTreeMap::from([(0x64, 2)]));
+        client
+            .write_all(&packet)
+            .await
+            .expect("failed to write second packet");
+
+        let resources = run_queued(
+            &channel,
+            2,
+            "packets = 0; RawPacketEvent = { trigger = function() packets = packets + 1; return \
+             true end }; ConnectionEndEvent = { trigger = function(_, _, reason) end_reason = \
+             reason; return true end }",
+        )
+        .await;
+        let (count, reason): (i64, String) = resources
+            .get::<suon_lua::LuaVm>()
+            .execute(|lua| {
+                let globals = lua.globals();
+                Ok::<_, mlua::Error>((globals.get("packets")?, globals.get("end_reason")?))
+            })
+            .expect("both events should be dispatched");
+        assert_eq!(count, 1, "the second packet must be rejected");
+        assert_eq!(reason, "protocol_error");
+    }
+
+    /// Spawns a registered reader session over an in-memory pipe, with
+    /// `config`, and returns the client end and the connection handle.
+    fn spawn_registered(
+        channel: &Channel,
+        config: TcpSettings,
+    ) -> (tokio::io::DuplexStream, crate::connection::ConnectionHandle) {
+        let (client, server) = tokio::io::duplex(1024);
+        let (reader_half, _writer_half) = tokio::io::split(server);
+        let (manager, permit) = setup();
+
+        let (sender, _receiver) = crossbeam_channel::bounded(64);
+        let peer = "127.0.0.1:7175".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, sender);
+        let handle = manager.get(id).expect("connection should be registered");
+        ReaderSession::new(
+            id,
+            reader_half,
+            channel.clone(),
+            config,
+            Shutdown::new(),
+            manager,
+            permit,
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+        (client, handle)
+    }
+
+    /// Writes `count` packets `gap` apart, stopping early once the
+    /// session has hung up.
+    async fn trickle(client: &mut tokio::io::DuplexStream, count: usize, gap: Duration) {
+        for _ in 0..count {
+            if client
+                .write_all(&[0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64])
+                .await
+                .is_err()
+            {
+                return;
+            }
+            tokio::time::sleep(gap).await;
+        }
+    }
+
+    /// Packets dispatched to Lua and the reason the session ended.
+    async fn packets_and_end_reason(channel: &Channel) -> (i64, Option<String>) {
+        run_queued(
+            channel,
+            usize::MAX,
+            "packets = 0; RawPacketEvent = { trigger = function() packets = packets + 1; return \
+             true end }; ConnectionEndEvent = { trigger = function(_, _, reason) end_reason = \
+             reason; return true end }",
+        )
+        .await
+        .get::<suon_lua::LuaVm>()
+        .execute(|lua| {
+            let globals = lua.globals();
+            Ok::<_, mlua::Error>((globals.get("packets")?, globals.get("end_reason")?))
+        })
+        .expect("event globals should be readable")
+    }
+
+    #[tokio::test]
+    async fn reader_session_cuts_off_a_slow_handshake_past_its_whole_budget() {
+        let channel = Channel::default();
+        let config = TcpSettings {
+            handshake_timeout: Some(Duration::from_millis(150)),
+            read_timeout: Some(Duration::from_millis(100)),
+            ..make_config()
+        };
+        let (mut client, _handle) = spawn_registered(&channel, config);
+
+        // Every packet beats the read timeout, but the handshake as a
+        // whole runs past its budget.
+        trickle(&mut client, 10, Duration::from_millis(50)).await;
+
+        let (packets, reason) = packets_and_end_reason(&channel).await;
+        assert!(
+            (1..10).contains(&packets),
+            "the session should end partway through, after {packets} packets"
+        );
+        assert_eq!(reason.as_deref(), Some("timeout"));
+    }
+
+    #[tokio::test]
+    async fn reader_session_lifts_the_handshake_budget_once_completed() {
+        let channel = Channel::default();
+        let config = TcpSettings {
+            handshake_timeout: Some(Duration::from_millis(100)),
+            read_timeout: Some(Duration::from_millis(100)),
+            ..make_config()
+        };
+        let (mut client, handle) = spawn_registered(&channel, config);
+        handle.complete_handshake();
+
+        trickle(&mut client, 5, Duration::from_millis(50)).await;
+        drop(client);
+
+        let (packets, reason) = packets_and_end_reason(&channel).await;
+        assert_eq!(packets, 5);
+        assert_eq!(reason.as_deref(), Some("closed"));
+    }
+
+    #[tokio::test]
+    async fn reader_session_waits_past_the_read_timeout_for_a_slow_key() {
+        let channel = Channel::default();
+        let config = TcpSettings {
+            read_timeout: Some(Duration::from_millis(50)),
+            key_timeout: Some(Duration::from_millis(500)),
+            ..make_config()
+        };
+        let (mut client, handle) = spawn_registered(&channel, config);
+
+        // The login packet is in; the game takes several read timeouts to
+        // look the account up before setting the key, which completes the
+        // handshake.
+        trickle(&mut client, 1, Duration::from_millis(150)).await;
+        handle.complete_handshake();
+        trickle(&mut client, 2, Duration::from_millis(20)).await;
+        drop(client);
+
+        let (packets, reason) = packets_and_end_reason(&channel).await;
+        assert_eq!(packets, 3);
+        assert_eq!(reason.as_deref(), Some("closed"));
+    }
+
+    #[tokio::test]
+    async fn reader_session_gives_up_when_the_key_never_comes() {
+        let channel = Channel::default();
+        let config = TcpSettings {
+            read_timeout: Some(Duration::from_secs(5)),
+            key_timeout: Some(Duration::from_millis(100)),
+            ..make_config()
+        };
+        let (mut client, _handle) = spawn_registered(&channel, config);
+
+        trickle(&mut client, 1, Duration::ZERO).await;
+
+        let (packets, reason) =
+            tokio::time::timeout(Duration::from_secs(1), packets_and_end_reason(&channel))
+                .await
+                .expect("the key wait should end before the read timeout");
+        assert_eq!(packets, 1);
+        assert_eq!(reason.as_deref(), Some("timeout"));
+    }
+
+    #[tokio::test]
+    async fn reader_session_drops_packets_until_authenticated() {
+        let channel = Channel::default();
+        let config = TcpSettings {
+            require_authentication: true,
+            ..make_config()
+        };
+        let (mut client, handle) = spawn_registered(&channel, config);
+        let settle = Duration::from_millis(50);
+
+        // The login packet is part of the handshake and goes through.
+        trickle(&mut client, 1, settle).await;
+        handle.complete_handshake();
+        // Game packets sent before the login is confirmed are dropped.
+        trickle(&mut client, 2, settle).await;
+        handle.authenticate();
+        trickle(&mut client, 1, settle).await;
+        drop(client);
+
+        let (packets, reason) = packets_and_end_reason(&channel).await;
+        assert_eq!(packets, 2);
+        assert_eq!(reason.as_deref(), Some("closed"));
+    }
+
+    #[tokio::test]
+    async fn reader_session_times_out_an_idle_read() {
+        let channel = Channel::default();
+        let config = TcpSettings {
+            read_timeout: Some(Duration::from_millis(50)),
+            ..make_config()
+        };
+        let (_client, _handle) = spawn_registered(&channel, config);
+
+        assert_eq!(end_reason(&channel).await.as_deref(), Some("timeout"));
+    }
+
+    #[test]
+    fn size_limit_applies_only_to_listed_opcodes() {
+        let limits = BTreeMap::from([(0x1E, 1)]);
+
+        assert_eq!(size_violation(&limits, 0x1E, 1), None);
+        assert_eq!(
+            size_violation(&limits, 0x1E, 2).as_deref(),
+            Some("packet 0x1E of 2 bytes exceeds its 1 byte limit")
+        );
+        assert_eq!(size_violation(&limits, 0x64, 512), None);
+    }
+
+    #[tokio::test]
+    async fn reader_session_rejects_packet_over_its_opcode_limit() {
+        let channel = Channel::default();
+        let config = TcpSettings {
+            packet_size_limits: Arc::new(BTreeMap::from([(0x1E, 1)])),
+            ..make_config()
+        };
+        let mut client = spawn_reader_with(channel.clone(), config).await;
+
+        // A keep-alive (0x1E) that should be one byte carries a second one;
+        // a zero checksum skips validation.
+        client
+            .write_all(&[0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1E, 0x00])
+            .await
+            .expect("failed to write oversized keep-alive");
+
+        assert_eq!(
+            end_reason(&channel).await.as_deref(),
+            Some("protocol_error")
+        );
+    }
+
+    #[tokio::test]
+    async fn reader_session_accepts_packet_within_its_opcode_limit() {
+        let channel = Channel::default();
+        let config = TcpSettings {
+            packet_size_limits: Arc::new(BTreeMap::from([(0x1E, 1)])),
+            ..make_config()
+        };
+        let mut client = spawn_reader_with(channel.clone(), config).await;
+
+        client
+            .write_all(&[0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1E])
+            .await
+            .expect("failed to write keep-alive");
+
+        let resources = run_queued(
+            &channel,
+            1,
+            "RawPacketEvent = { trigger = function(_, _, data) packet = data; return true end }",
+        )
+        .await;
+
+        let packet: Vec<u8> = resources
+            .get::<suon_lua::LuaVm>()
+            .execute(|lua| lua.globals().get("packet"))
+            .expect("keep-alive within its limit should be dispatched");
+        assert_eq!(packet, [0x1E]);
+    }
+
     #[tokio::test]
     async fn reader_session_spawn_and_cleanup_on_disconnect() {
         let listener = TcpListener::bind("127.0.0.1:0")
@@ -290,7 +1536,6 @@ mod tests {
 
         tokio::time::sleep(tokio::time::Duration::from_millis(15)).await;
 
-        use tokio::io::AsyncWriteExt;
         client
             .write_all(b"\x00\x05")
             .await
@@ -0,0 +1,134 @@
+use std::{sync::Arc, time::Duration};
+
+use suon_channel::Channel;
+use tokio::time::MissedTickBehavior;
+
+use crate::connection::{disconnect::DisconnectReason, manager::ConnectionManager};
+
+use super::connection_end::ConnectionEnd;
+
+/// How often the registry is swept for connections whose reader session
+/// is gone.
+pub(crate) const REAP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Sweeps `manager` every `interval` for connections whose reader session
+/// exited without unregistering them, and ends each one in Lua as
+/// [`DisconnectReason::SessionLost`], since its `ConnectionEnd` was
+/// never sent. Runs until its runtime shuts down.
+pub(crate) async fn run(manager: Arc<ConnectionManager>, channel: Channel, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+        for id in manager.reap_exited() {
+            channel.send(ConnectionEnd {
+                id,
+                reason: DisconnectReason::SessionLost,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{
+        shutdown::Shutdown,
+        tcp::{
+            ProtocolSettings, TcpSettings,
+            connection::{Connection, mock_transport},
+        },
+        throttle::ConnectionLimiter,
+    };
+
+    #[tokio::test]
+    async fn reaper_removes_connections_whose_reader_exited() {
+        let manager = Arc::new(ConnectionManager::new(0));
+        let channel = Channel::default();
+        let peer = "127.0.0.1:7171".parse().expect("valid test address");
+
+        let (lost_sender, lost_receiver) = crossbeam_channel::bounded(4);
+        let lost = manager.register(peer, ProtocolSettings::default(), lost_sender);
+        let (live_sender, _live_receiver) = crossbeam_channel::bounded(4);
+        let live = manager.register(peer, ProtocolSettings::default(), live_sender);
+        let _live_reader = manager
+            .get(live)
+            .expect("connection should be registered")
+            .reader_liveness();
+
+        // A reader that ends without reaching its cleanup.
+        drop(
+            manager
+                .get(lost)
+                .expect("connection should be registered")
+                .reader_liveness(),
+        );
+
+        tokio::spawn(run(
+            manager.clone(),
+            channel.clone(),
+            Duration::from_millis(10),
+        ));
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while channel.pending_count() == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("the reaper should end the lost connection");
+
+        assert!(manager.get(lost).is_none());
+        assert!(manager.get(live).is_some());
+        assert_eq!(channel.pending_count(), 1);
+        assert!(
+            matches!(
+                lost_receiver.try_recv(),
+                Err(crossbeam_channel::TryRecvError::Disconnected)
+            ),
+            "the writer's command channel should be disconnected"
+        );
+    }
+
+    #[test]
+    fn a_reader_session_dropped_mid_read_is_reaped() {
+        let manager = Arc::new(ConnectionManager::new(0));
+        let peer = "127.0.0.1:7171".parse().expect("valid test address");
+        let (sender, receiver) = crossbeam_channel::bounded(4);
+        let id = manager.register(peer, ProtocolSettings::default(), sender);
+        let permit = ConnectionLimiter::new(1)
+            .try_acquire()
+            .expect("a fresh limiter should have a permit");
+        let (client, reader_half, writer_half) = mock_transport();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build test runtime");
+        runtime.block_on(async {
+            Connection::spawn_io(
+                reader_half,
+                writer_half,
+                receiver,
+                Channel::default(),
+                manager.clone(),
+                TcpSettings::default(),
+                Shutdown::new(),
+                id,
+                permit,
+                crate::test_buffer_pool(),
+            );
+            // Let the reader reach its first read, where it waits for the
+            // client.
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        });
+
+        // Shutting the runtime down drops the reader at that await, so it
+        // never unregisters the connection.
+        drop(runtime);
+        assert!(manager.get(id).is_some());
+        assert_eq!(manager.reap_exited(), [id]);
+        assert!(manager.get(id).is_none());
+        drop(client);
+    }
+}
@@ -0,0 +1,94 @@
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+use tracing::{debug, trace};
+
+use crate::{
+    protocol::writer::PacketWriter,
+    server::tcp::{
+        protocol::{ProtocolSettings, SizeField, write_str},
+        settings::TcpSettings,
+    },
+};
+
+/// Opcode of the login error packet, which clients show as a message box
+/// instead of a generic connection error.
+pub const REJECT_OPCODE: u8 = 0x0B;
+
+pub(crate) const SERVER_FULL: &str = "The server is full. Please try again later.";
+pub(crate) const SERVER_SHUTTING_DOWN: &str = "The server is shutting down.";
+pub(crate) const TOO_MANY_ATTEMPTS: &str =
+    "Too many connection attempts. Please wait a moment and try again.";
+
+/// Frames a rejection packet carrying `reason` for `protocol`, behind a
+/// `size_field` prefix.
+///
+/// No XTEA key has been exchanged yet, so XTEA protocols fall back to
+/// checksum framing just like any packet sent before the handshake.
+pub(crate) fn rejection_packet(
+    protocol: ProtocolSettings,
+    size_field: SizeField,
+    reason: &str,
+) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(1 + 2 + reason.len());
+    payload.push(REJECT_OPCODE);
+    write_str(&mut payload, reason);
+
+    let mut writer = PacketWriter::new(protocol, payload.len() + 16).with_size_field(size_field);
+    writer.send(&payload);
+    writer.take_buffer()
+}
+
+/// Writes a rejection packet to `stream` and closes it, without holding
+/// up the accept loop.
+pub(crate) fn on_reject(mut stream: TcpStream, config: &TcpSettings, reason: &'static str) {
+    let packet = rejection_packet(config.protocol, config.size_field, reason);
+    let write_timeout = config.write_timeout;
+
+    tokio::spawn(async move {
+        let result = tokio::time::timeout(write_timeout, async {
+            stream.write_all(&packet).await?;
+            stream.shutdown().await
+        })
+        .await;
+
+        match result {
+            Ok(Ok(())) => trace!(target: "TCP", "Rejected connection: {reason}"),
+            Ok(Err(e)) => debug!(target: "TCP", "Failed to send rejection ({reason}): {e}"),
+            Err(_) => debug!(target: "TCP", "Timed out sending rejection ({reason})"),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejection_packet_carries_opcode_and_reason() {
+        let packet = rejection_packet(ProtocolSettings::default(), SizeField::U16, "full");
+
+        let size = u16::from_le_bytes([packet[0], packet[1]]) as usize;
+        assert_eq!(size, packet.len() - 2);
+
+        let payload = &packet[2 + 4..];
+        let checksum = u32::from_le_bytes([packet[2], packet[3], packet[4], packet[5]]);
+        assert_eq!(checksum, suon_adler32::generate(payload));
+        assert_eq!(payload, [REJECT_OPCODE, 4, 0, b'f', b'u', b'l', b'l']);
+    }
+
+    #[test]
+    fn rejection_packet_uses_checksum_framing_for_xtea_protocols() {
+        let protocol = ProtocolSettings {
+            header_size: 6,
+            has_checksum: true,
+            uses_xtea: true,
+            uses_rsa: true,
+        };
+
+        let packet = rejection_packet(protocol, SizeField::U16, SERVER_FULL);
+        assert_eq!(
+            &packet[2 + 4..2 + 4 + 3],
+            &[REJECT_OPCODE, SERVER_FULL.len() as u8, 0]
+        );
+        assert_eq!(&packet[2 + 4 + 3..], SERVER_FULL.as_bytes());
+    }
+}
@@ -0,0 +1,182 @@
+use suon_channel::TaskHandler;
+use suon_lua::LuaVm;
+use suon_macros::Task;
+use suon_resource::Resources;
+
+use crate::{
+    connection::{
+        client_kind::ClientKind,
+        handle::ConnectionHandle,
+        id::ConnectionId,
+        resumption::{ResumableSession, ResumptionToken},
+    },
+    server::tcp::protocol::{ProtocolSettings, SEQUENCE_FIELD_LEN, checksummed_region},
+};
+
+/// Opcode of the packet a reconnecting client opens with to resume its
+/// previous session.
+pub const RESUME_SESSION_OPCODE: u8 = 0x0C;
+
+/// The first packet of a reconnecting client, presenting the resumption
+/// token it was issued on login.
+///
+/// Encoded as the resume opcode followed by the 16 token bytes. The
+/// client has no key yet, so the packet is sent in the clear on every
+/// port. On ports whose frames carry a checksum field, either for the
+/// checksum or for the XTEA sequence, the payload follows that field,
+/// holding its adler32 checksum or zero. The reader recognises the packet
+/// before decrypting anything. Its 17 or 21 byte body is never a whole
+/// number of XTEA blocks after the field and is shorter than an RSA
+/// block, so it cannot be mistaken for an encrypted packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResumeSessionPacket {
+    pub token: ResumptionToken,
+}
+
+impl ResumeSessionPacket {
+    /// Encodes the packet as a payload.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(17);
+        payload.push(RESUME_SESSION_OPCODE);
+        payload.extend_from_slice(&self.token.0);
+        payload
+    }
+
+    /// Decodes a payload produced by [`ResumeSessionPacket::encode`], or
+    /// `None` if it is truncated, has trailing bytes or does not start
+    /// with the resume opcode.
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        let (&opcode, rest) = payload.split_first()?;
+        if opcode != RESUME_SESSION_OPCODE {
+            return None;
+        }
+
+        Some(Self {
+            token: ResumptionToken(rest.try_into().ok()?),
+        })
+    }
+
+    /// Recognises the packet in a frame body that has not been through
+    /// the packet reader, or `None` if the body is something else.
+    pub fn from_frame(
+        body: &[u8],
+        protocol: ProtocolSettings,
+        checksum_enabled: bool,
+    ) -> Option<Self> {
+        if !checksum_enabled && !protocol.uses_xtea {
+            return Self::decode(body);
+        }
+
+        let (checksum, payload) = body.split_first_chunk::<SEQUENCE_FIELD_LEN>()?;
+        let checksum = u32::from_le_bytes(*checksum);
+        if checksum_enabled
+            && checksum != 0
+            && checksum != suon_adler32::generate(checksummed_region(body))
+        {
+            return None;
+        }
+        Self::decode(payload)
+    }
+}
+
+/// Hands `session` over to the connection behind `handle`: it takes the
+/// client kind, protocol version and XTEA key of the issuing connection,
+/// its handshake is complete and it is authenticated.
+pub(crate) fn restore(handle: &ConnectionHandle, session: &ResumableSession) {
+    handle.resolve_client_kind(session.client_kind.unwrap_or(ClientKind::Unknown));
+    if let Some(version) = session.protocol_version
+        && let Err(e) = handle.set_protocol_version(version)
+    {
+        tracing::debug!(target: "TCP", "Connection {} resumed protocol version not queued: {e}", handle.id());
+    }
+    match session.xtea_key {
+        Some(key) => {
+            if let Err(e) = handle.set_xtea_key(key) {
+                tracing::debug!(target: "TCP", "Connection {} resumed XTEA key not queued: {e}", handle.id());
+            }
+        }
+        None => handle.complete_handshake(),
+    }
+    handle.authenticate();
+}
+
+/// Task sent from the reader session once a connection has resumed the
+/// session of `previous`, so game logic can rebind the player to it.
+#[derive(Task)]
+pub(crate) struct SessionResumed {
+    pub id: ConnectionId,
+    pub previous: ConnectionId,
+}
+
+impl TaskHandler for SessionResumed {
+    fn run(&mut self, resources: &mut Resources) {
+        let vm = resources.get::<LuaVm>();
+        if let Err(err) = vm.trigger_event(
+            "SessionResumedEvent",
+            (self.id.as_u64(), self.previous.as_u64()),
+        ) {
+            tracing::error!(target: "TCP", "SessionResumed error: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_session_packet_roundtrips_through_encode_and_decode() {
+        let packet = ResumeSessionPacket {
+            token: ResumptionToken([7; 16]),
+        };
+        let payload = packet.encode();
+
+        assert_eq!(payload.len(), 17);
+        assert_eq!(payload[0], RESUME_SESSION_OPCODE);
+        assert_eq!(ResumeSessionPacket::decode(&payload), Some(packet));
+        assert_eq!(ResumeSessionPacket::decode(&payload[..16]), None);
+        assert_eq!(ResumeSessionPacket::decode(&[0x0A; 17]), None);
+    }
+
+    #[test]
+    fn from_frame_follows_the_checksum_field_of_the_port() {
+        let packet = ResumeSessionPacket {
+            token: ResumptionToken([7; 16]),
+        };
+        let payload = packet.encode();
+        let plain = ProtocolSettings {
+            header_size: 2,
+            has_checksum: false,
+            uses_xtea: false,
+            uses_rsa: false,
+        };
+        let xtea = ProtocolSettings {
+            header_size: 6,
+            has_checksum: true,
+            uses_xtea: true,
+            uses_rsa: true,
+        };
+        let checksummed = [
+            &suon_adler32::generate(&payload).to_le_bytes()[..],
+            &payload,
+        ]
+        .concat();
+        let unchecked = [&[0; 4][..], &payload].concat();
+        let corrupt = [&[1, 0, 0, 0][..], &payload].concat();
+
+        assert_eq!(
+            ResumeSessionPacket::from_frame(&payload, plain, false),
+            Some(packet)
+        );
+        assert_eq!(
+            ResumeSessionPacket::from_frame(&checksummed, xtea, true),
+            Some(packet)
+        );
+        assert_eq!(
+            ResumeSessionPacket::from_frame(&unchecked, xtea, false),
+            Some(packet)
+        );
+        assert_eq!(ResumeSessionPacket::from_frame(&corrupt, xtea, true), None);
+        assert_eq!(ResumeSessionPacket::from_frame(&payload, xtea, true), None);
+    }
+}
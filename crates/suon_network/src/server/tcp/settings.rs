@@ -1,15 +1,18 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use serde::Serialize;
 
-use crate::server::{
-    kind::ServerKind,
-    settings::ServerSettings,
-    tcp::{EncryptionSettings, ProtocolSettings},
+use crate::{
+    connection::client_kind::ClientKind,
+    server::{
+        kind::ServerKind,
+        settings::ServerSettings,
+        tcp::{DecodeFailurePolicy, EncryptionSettings, FlushPolicy, ProtocolSettings, SizeField},
+    },
 };
 
 /// Configuration for a TCP listener port.
-#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, Hash)]
 pub struct TcpSettings {
     pub protocol: ProtocolSettings,
     #[serde(rename = "flush_interval_ms", with = "suon_serde::duration_ms")]
@@ -17,9 +20,96 @@ pub struct TcpSettings {
     pub encryption: EncryptionSettings,
     pub channel_capacity: usize,
     pub max_buffer_size: usize,
+    /// Buffered byte count that forces an immediate socket flush instead
+    /// of waiting for the next flush tick. `0` disables the threshold.
+    pub flush_threshold: usize,
+    /// Connections the port serves at once. 0 rejects every connection.
     pub max_connections: u32,
     pub connection_timeout_secs: u64,
     pub rate_burst: u32,
+    /// Accepted sockets allowed to wait for the `onConnect` decision at
+    /// once before the listener stops accepting.
+    pub accept_queue_capacity: usize,
+    /// How long a single socket write may stall before it counts as a
+    /// failed attempt.
+    #[serde(rename = "write_timeout_ms", with = "suon_serde::duration_ms")]
+    pub write_timeout: Duration,
+    /// Extra attempts a stalled write gets before the writer gives up.
+    pub write_retries: u32,
+    /// Split packets too large for one frame into consecutive frames
+    /// instead of dropping them, each opening with
+    /// [`FRAGMENT_OPCODE`](crate::server::tcp::FRAGMENT_OPCODE) and a
+    /// continuation flag. Fragments the client sends the same way are
+    /// joined before they reach game code.
+    pub allow_fragmentation: bool,
+    /// Largest packet the client may send as fragments, counted once
+    /// they are joined. A client going past it is disconnected.
+    pub max_fragmented_size: usize,
+    /// Largest decoded payload accepted per opcode (the payload's first
+    /// byte). Opcodes without an entry are only bound by the frame size.
+    /// Shared with every connection on the port rather than copied.
+    pub packet_size_limits: Arc<BTreeMap<u8, usize>>,
+    /// Answer client keep-alive packets from the reader session instead
+    /// of passing them to Lua.
+    pub auto_keep_alive: bool,
+    /// When buffered packets are flushed to the socket.
+    pub flush_policy: FlushPolicy,
+    /// Decoded packets a connection may have queued for Lua at once.
+    /// Once reached, the reader stops reading from the socket until Lua
+    /// catches up, so a flooding client is throttled by TCP itself.
+    pub max_pending_packets: usize,
+    /// Bytes per second a connection may be sent, with up to a second's
+    /// worth going out in one burst. Writes past it are delayed, so
+    /// further commands queue up. 0 disables the limit.
+    pub max_send_rate: u64,
+    /// Message of the day sent with the reader session's answer to status
+    /// queries. When unset, status queries are passed to Lua like any
+    /// other packet.
+    pub status_motd: Option<String>,
+    /// First-packet opcodes resolved to a client kind on top of the
+    /// built-in status, login and game opcodes, for protocols that open
+    /// a stage with another packet. Entries override the built-ins.
+    pub client_kind_opcodes: BTreeMap<u8, ClientKind>,
+    /// Time a client has to finish its whole handshake, counted from
+    /// the start of the reader session until the handshake is completed
+    /// on its [`ConnectionHandle`](crate::connection::handle::ConnectionHandle).
+    /// `None` leaves the handshake unbounded.
+    #[serde(
+        rename = "handshake_timeout_ms",
+        with = "suon_serde::duration_ms::option"
+    )]
+    pub handshake_timeout: Option<Duration>,
+    /// Time allowed for reading each packet, from waiting for its size
+    /// to receiving its last byte. `None` waits indefinitely.
+    #[serde(rename = "read_timeout_ms", with = "suon_serde::duration_ms::option")]
+    pub read_timeout: Option<Duration>,
+    /// Time the game has to complete the handshake once the client's
+    /// first packet is in, such as setting the XTEA key after a slow
+    /// account lookup. The client has nothing more to send until it gets
+    /// an answer, so this wait replaces the per-read timeout, while
+    /// `handshake_timeout` still caps the handshake as a whole. `None`
+    /// keeps the per-read timeout.
+    #[serde(rename = "key_timeout_ms", with = "suon_serde::duration_ms::option")]
+    pub key_timeout: Option<Duration>,
+    /// Drop packets that arrive after the handshake until game logic has
+    /// called [`authenticate`](crate::connection::ConnectionHandle::authenticate)
+    /// on the connection, so nothing reaches the game before the login
+    /// is confirmed.
+    pub require_authentication: bool,
+    /// Disconnect clients that keep sending packets game code fails to
+    /// handle. `None` leaves such packets to game code alone.
+    pub decode_failure_policy: Option<DecodeFailurePolicy>,
+    /// Width of the size prefix framing packets in both directions.
+    pub size_field: SizeField,
+    /// Send the client a [`DisconnectPacket`](crate::server::tcp::DisconnectPacket)
+    /// with the reason before closing its connection over a protocol
+    /// violation, instead of just dropping the socket.
+    pub send_disconnect_packets: bool,
+    /// Let a new connection whose first packet is a
+    /// [`ResumeSessionPacket`](crate::server::tcp::ResumeSessionPacket)
+    /// take over a session that was issued a resumption token, skipping
+    /// the handshake and login.
+    pub allow_resumption: bool,
 }
 
 impl Default for TcpSettings {
@@ -30,9 +120,30 @@ impl Default for TcpSettings {
             encryption: EncryptionSettings::default(),
             channel_capacity: 1024,
             max_buffer_size: 4096,
+            flush_threshold: 0,
             max_connections: 100,
             connection_timeout_secs: 10,
             rate_burst: 50,
+            accept_queue_capacity: 64,
+            write_timeout: Duration::from_secs(5),
+            write_retries: 3,
+            allow_fragmentation: false,
+            max_fragmented_size: 1 << 20,
+            packet_size_limits: Default::default(),
+            auto_keep_alive: false,
+            flush_policy: Default::default(),
+            max_pending_packets: 256,
+            max_send_rate: 0,
+            status_motd: None,
+            client_kind_opcodes: Default::default(),
+            handshake_timeout: None,
+            read_timeout: None,
+            key_timeout: None,
+            require_authentication: false,
+            decode_failure_policy: None,
+            size_field: Default::default(),
+            send_disconnect_packets: false,
+            allow_resumption: false,
         }
     }
 }
@@ -46,24 +157,132 @@ impl TcpSettings {
                 encryption,
                 channel_capacity,
                 max_buffer_size,
+                flush_threshold,
                 max_connections,
                 rate_burst,
-                ..
+                accept_queue_capacity,
+                write_timeout,
+                write_retries,
+                allow_fragmentation,
+                max_fragmented_size,
+                packet_size_limits,
+                auto_keep_alive,
+                flush_policy,
+                max_pending_packets,
+                max_send_rate,
+                status_motd,
+                client_kind_opcodes,
+                handshake_timeout,
+                read_timeout,
+                key_timeout,
+                require_authentication,
+                decode_failure_policy,
+                size_field,
+                send_disconnect_packets,
+                allow_resumption,
             } => TcpSettings {
                 protocol: *protocol,
                 flush_interval: *flush_interval,
                 encryption: *encryption,
                 channel_capacity: *channel_capacity,
                 max_buffer_size: *max_buffer_size,
+                flush_threshold: *flush_threshold,
                 max_connections: *max_connections,
                 connection_timeout_secs: 10,
                 rate_burst: *rate_burst,
+                accept_queue_capacity: *accept_queue_capacity,
+                write_timeout: *write_timeout,
+                write_retries: *write_retries,
+                allow_fragmentation: *allow_fragmentation,
+                max_fragmented_size: *max_fragmented_size,
+                packet_size_limits: Arc::new(packet_size_limits.clone()),
+                auto_keep_alive: *auto_keep_alive,
+                flush_policy: *flush_policy,
+                max_pending_packets: *max_pending_packets,
+                max_send_rate: *max_send_rate,
+                status_motd: status_motd.clone(),
+                client_kind_opcodes: client_kind_opcodes.clone(),
+                handshake_timeout: *handshake_timeout,
+                read_timeout: *read_timeout,
+                key_timeout: *key_timeout,
+                require_authentication: *require_authentication,
+                decode_failure_policy: *decode_failure_policy,
+                size_field: *size_field,
+                send_disconnect_packets: *send_disconnect_packets,
+                allow_resumption: *allow_resumption,
             },
             _ => unreachable!(),
         }
     }
 }
 
+impl From<TcpSettings> for ServerKind {
+    fn from(settings: TcpSettings) -> Self {
+        let TcpSettings {
+            protocol,
+            flush_interval,
+            encryption,
+            channel_capacity,
+            max_buffer_size,
+            flush_threshold,
+            max_connections,
+            connection_timeout_secs: _,
+            rate_burst,
+            accept_queue_capacity,
+            write_timeout,
+            write_retries,
+            allow_fragmentation,
+            max_fragmented_size,
+            packet_size_limits,
+            auto_keep_alive,
+            flush_policy,
+            max_pending_packets,
+            max_send_rate,
+            status_motd,
+            client_kind_opcodes,
+            handshake_timeout,
+            read_timeout,
+            key_timeout,
+            require_authentication,
+            decode_failure_policy,
+            size_field,
+            send_disconnect_packets,
+            allow_resumption,
+        } = settings;
+
+        ServerKind::Tcp {
+            protocol,
+            flush_interval,
+            encryption,
+            channel_capacity,
+            max_buffer_size,
+            flush_threshold,
+            max_connections,
+            rate_burst,
+            accept_queue_capacity,
+            write_timeout,
+            write_retries,
+            allow_fragmentation,
+            max_fragmented_size,
+            packet_size_limits: Arc::unwrap_or_clone(packet_size_limits),
+            auto_keep_alive,
+            flush_policy,
+            max_pending_packets,
+            max_send_rate,
+            status_motd,
+            client_kind_opcodes,
+            handshake_timeout,
+            read_timeout,
+            key_timeout,
+            require_authentication,
+            decode_failure_policy,
+            size_field,
+            send_disconnect_packets,
+            allow_resumption,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,14 +293,13 @@ mod tests {
         ServerSettings {
             port: 7171,
             address: "0.0.0.0".into(),
-            kind: ServerKind::Tcp {
+            kind: TcpSettings {
                 protocol: ProtocolSettings {
                     header_size: 6,
                     has_checksum: true,
                     uses_xtea: true,
                     uses_rsa: true,
                 },
-                flush_interval: Duration::from_millis(10),
                 encryption: EncryptionSettings {
                     incoming: true,
                     outgoing: false,
@@ -89,9 +307,11 @@ mod tests {
                 channel_capacity: 512,
                 max_buffer_size: 8192,
                 max_connections: 50,
-                rate_burst: 50,
-            },
+                ..TcpSettings::default()
+            }
+            .into(),
             retry_delay: Duration::from_millis(5000),
+            backlog: 1024,
         }
     }
 
@@ -123,6 +343,7 @@ mod tests {
                 max_headers: 32,
             },
             retry_delay: Duration::from_millis(15000),
+            backlog: 1024,
         };
         TcpSettings::from_settings(&settings);
     }
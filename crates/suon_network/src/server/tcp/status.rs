@@ -0,0 +1,109 @@
+use super::protocol::{read_str, write_str};
+use crate::connection::{client_kind::STATUS_OPCODE, handle::ConnectionHandle};
+
+/// The server's answer to a status query.
+///
+/// Encoded as the status opcode, the online and maximum player counts as
+/// little-endian `u32`s, and the message of the day as a `u16`
+/// length-prefixed string, the same string layout the Lua message
+/// modules use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerStatusPacket {
+    pub players_online: u32,
+    pub max_players: u32,
+    pub motd: String,
+}
+
+impl ServerStatusPacket {
+    /// Encodes the status as a packet payload. A message of the day
+    /// longer than `u16::MAX` bytes is cut at the last whole character
+    /// that fits.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(11 + self.motd.len());
+        payload.push(STATUS_OPCODE);
+        payload.extend_from_slice(&self.players_online.to_le_bytes());
+        payload.extend_from_slice(&self.max_players.to_le_bytes());
+        write_str(&mut payload, &self.motd);
+        payload
+    }
+
+    /// Decodes a payload produced by [`ServerStatusPacket::encode`], or `None`
+    /// if it is truncated, has trailing bytes or does not start with the
+    /// status opcode.
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        let (&opcode, rest) = payload.split_first()?;
+        if opcode != STATUS_OPCODE {
+            return None;
+        }
+
+        let (players_online, rest) = rest.split_first_chunk::<4>()?;
+        let (max_players, rest) = rest.split_first_chunk::<4>()?;
+        let (motd, rest) = read_str(rest)?;
+        if !rest.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            players_online: u32::from_le_bytes(*players_online),
+            max_players: u32::from_le_bytes(*max_players),
+            motd: motd.to_owned(),
+        })
+    }
+}
+
+/// Queues `status` on `handle`, then closes the connection once it has
+/// been written.
+pub(crate) fn respond(handle: &ConnectionHandle, status: &ServerStatusPacket) {
+    if let Err(e) = handle.send_and_close(status.encode()) {
+        tracing::debug!(target: "TCP", "Connection {} status answer not queued: {e}", handle.id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(motd: &str) -> ServerStatusPacket {
+        ServerStatusPacket {
+            players_online: 12,
+            max_players: 500,
+            motd: motd.to_string(),
+        }
+    }
+
+    #[test]
+    fn status_roundtrips_through_encode_and_decode() {
+        let status = status("Welcome to Suon!");
+        let payload = status.encode();
+
+        assert_eq!(payload[0], STATUS_OPCODE);
+        assert_eq!(payload.len(), 11 + status.motd.len());
+        assert_eq!(ServerStatusPacket::decode(&payload), Some(status));
+    }
+
+    #[test]
+    fn decode_rejects_malformed_payloads() {
+        let payload = status("motd").encode();
+
+        assert_eq!(
+            ServerStatusPacket::decode(&payload[..payload.len() - 1]),
+            None
+        );
+        assert_eq!(
+            ServerStatusPacket::decode(&[payload.as_slice(), &[0]].concat()),
+            None
+        );
+        assert_eq!(ServerStatusPacket::decode(&[0x01, 0, 0, 0, 0]), None);
+        assert_eq!(ServerStatusPacket::decode(&[]), None);
+    }
+
+    #[test]
+    fn encode_cuts_an_oversized_motd_on_a_char_boundary() {
+        let status = status(&"é".repeat(u16::MAX as usize));
+        let decoded = ServerStatusPacket::decode(&status.encode())
+            .expect("oversized motd should still decode");
+
+        assert_eq!(decoded.motd.len(), u16::MAX as usize - 1);
+        assert!(status.motd.starts_with(&decoded.motd));
+    }
+}
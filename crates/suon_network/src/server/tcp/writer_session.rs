@@ -1,49 +1,131 @@
-use std::sync::Arc;
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
+use crossbeam_channel::TryRecvError;
 use suon_channel::BufferPool;
-use tokio::io::{AsyncWriteExt, BufWriter};
-use tracing::{error, trace};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt, BufWriter},
+    sync::Notify,
+};
+use tracing::{error, trace, warn};
 
 use crate::{
-    protocol::{command::Command, writer::PacketWriter},
-    server::tcp::settings::TcpSettings,
+    connection::{
+        budget::{BudgetCharge, BufferedCharge},
+        handle::WriteError,
+        id::ConnectionId,
+        stats::ConnectionStats,
+        tap::{PacketDirection, PacketTap},
+    },
+    protocol::{
+        command::Command,
+        writer::{PacketWriter, WriteError as FrameError},
+    },
+    server::tcp::{flush_policy::FlushPolicy, settings::TcpSettings},
 };
 
-use crate::server::shutdown::Shutdown;
+use crate::server::{shutdown::Shutdown, throttle::SendRateLimiter};
 
-pub(crate) struct WriterSession {
+/// Frames queued commands and writes them to the write half of a
+/// connection.
+///
+/// Generic over the transport for the same reason as
+/// [`ReaderSession`](super::reader_session::ReaderSession).
+pub(crate) struct WriterSession<W = tokio::net::tcp::OwnedWriteHalf> {
     command_receiver: crossbeam_channel::Receiver<Command>,
-    writer_half: tokio::net::tcp::OwnedWriteHalf,
+    writer_half: W,
     buffer_pool: Arc<BufferPool>,
     config: TcpSettings,
     shutdown: Shutdown,
+    stats: Arc<ConnectionStats>,
+    checksum_enabled: Arc<AtomicBool>,
+    send_limiter: SendRateLimiter,
+    packet_tap: Option<(ConnectionId, PacketTap)>,
+    budget: BufferedCharge,
+    wake: Arc<Notify>,
 }
 
-impl WriterSession {
+impl<W> WriterSession<W>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
     pub fn new(
         command_receiver: crossbeam_channel::Receiver<Command>,
-        writer_half: tokio::net::tcp::OwnedWriteHalf,
+        writer_half: W,
         config: TcpSettings,
         shutdown: Shutdown,
         buffer_pool: Arc<BufferPool>,
     ) -> Self {
+        let checksum_enabled = Arc::new(AtomicBool::new(config.protocol.has_checksum));
+        let send_limiter = SendRateLimiter::new(config.max_send_rate);
         WriterSession {
             command_receiver,
             writer_half,
             buffer_pool,
             config,
             shutdown,
+            stats: Arc::default(),
+            checksum_enabled,
+            send_limiter,
+            packet_tap: None,
+            budget: BufferedCharge::default(),
+            wake: Arc::default(),
         }
     }
 
+    /// Records sent packets and bytes into `stats` instead of a private
+    /// counter set.
+    pub fn with_stats(mut self, stats: Arc<ConnectionStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Publishes checksum mode changes to `checksum_enabled`, so a reader
+    /// session sharing the flag verifies incoming packets the same way.
+    pub fn with_checksum_flag(mut self, checksum_enabled: Arc<AtomicBool>) -> Self {
+        self.checksum_enabled = checksum_enabled;
+        self
+    }
+
+    /// Records the frames written for connection `id` on `tap`, if it is
+    /// enabled.
+    pub fn with_packet_tap(mut self, id: ConnectionId, tap: PacketTap) -> Self {
+        self.packet_tap = tap.is_enabled().then_some((id, tap));
+        self
+    }
+
+    /// Gives the packets queued on the connection's handles back to
+    /// `budget` as they are written, and everything still queued once
+    /// the session ends.
+    pub fn with_budget_charge(mut self, budget: BudgetCharge) -> Self {
+        self.budget = BufferedCharge::new(budget);
+        self
+    }
+
+    /// Drains the command queue as soon as `wake` is signalled rather than
+    /// on the next flush tick, so thresholds, explicit flushes and the
+    /// `Immediate` policy act when a command is queued.
+    pub fn with_wake(mut self, wake: Arc<Notify>) -> Self {
+        self.wake = wake;
+        self
+    }
+
     pub fn spawn(self) {
         tokio::spawn(self.run());
     }
 
-    async fn run(self) {
+    async fn run(mut self) {
         let mut packet_writer =
             PacketWriter::new(self.config.protocol, self.config.max_buffer_size);
         packet_writer.set_xtea_enabled(self.config.encryption.outgoing);
+        packet_writer.set_fragmentation(self.config.allow_fragmentation);
+        packet_writer.set_size_field(self.config.size_field);
+        packet_writer.set_checksum_enabled(self.checksum_enabled.load(Ordering::Acquire));
 
         let mut buf_writer = BufWriter::new(self.writer_half);
         let flush_interval = self.config.flush_interval;
@@ -56,17 +138,23 @@ impl WriterSession {
             tokio::select! {
                 biased;
                 _ = flush_timer.tick() => {
-                    if !packet_writer.is_empty() {
-                        let buf = packet_writer.take_buffer();
-                        if let Err(e) = buf_writer.write_all(&buf).await {
-                            error!(target: "TCP", "Failed to flush buffered TCP data to socket: {e}");
-                            break;
-                        }
-
-                        self.buffer_pool.release(buf);
-                    }
-                    if let Err(e) = buf_writer.flush().await {
-                        error!(target: "TCP", "Failed to flush buffered TCP data to socket: {e}");
+                    if self.config.flush_policy == FlushPolicy::Interval
+                        && let Err(e) = write_out(
+                            &mut buf_writer,
+                            &mut packet_writer,
+                            &self.config,
+                            &self.stats,
+                            &self.buffer_pool,
+                            &self.send_limiter,
+                            &mut self.budget,
+                        )
+                        .await
+                    {
+                        error!(
+                            target: "TCP",
+                            "Failed to flush buffered TCP data to socket: {e}; dropping {} queued commands",
+                            self.command_receiver.len(),
+                        );
                         break;
                     }
                 }
@@ -74,8 +162,9 @@ impl WriterSession {
                     if *rx.borrow() {
                         if !packet_writer.is_empty() {
                             let buf = packet_writer.take_buffer();
-                            if let Err(e) = buf_writer.write_all(&buf).await {
-                                error!(target: "TCP", "Failed to flush remaining data during TCP connection shutdown: {e}");
+                            match buf_writer.write_all(&buf).await {
+                                Ok(()) => self.stats.record_bytes_sent(buf.len() as u64),
+                                Err(e) => error!(target: "TCP", "Failed to flush remaining data during TCP connection shutdown: {e}"),
                             }
                             self.buffer_pool.release(buf);
                         }
@@ -86,65 +175,438 @@ impl WriterSession {
                         break;
                     }
                 }
+                _ = self.wake.notified() => {}
             }
 
-            while let Ok(command) = self.command_receiver.try_recv() {
+            // Packets that cross the flush threshold mark the flush as due
+            // rather than flushing one by one, so a burst drained here
+            // goes out in a single write and flush.
+            let mut flush_due = false;
+            let disconnected = loop {
+                let command = match self.command_receiver.try_recv() {
+                    Ok(command) => command,
+                    Err(TryRecvError::Empty) => break false,
+                    Err(TryRecvError::Disconnected) => break true,
+                };
                 match command {
                     Command::Send(plaintext) => {
-                        packet_writer.send(&plaintext);
-                        if packet_writer.should_flush_by_size() {
-                            let buf = packet_writer.take_buffer();
-                            if let Err(e) = buf_writer.write_all(&buf).await {
-                                error!(target: "TCP", "Failed to write framed packet to TCP socket: {e}");
-                                return;
-                            }
-
-                            self.buffer_pool.release(buf);
-                        }
+                        buffer_group(
+                            &mut packet_writer,
+                            std::slice::from_ref(&plaintext),
+                            Framing::Packets,
+                            &self.stats,
+                            &mut self.budget,
+                            self.packet_tap.as_ref(),
+                        )
+                        .ok();
+                    }
+                    Command::SendBatch(packets) => {
+                        buffer_group(
+                            &mut packet_writer,
+                            &packets,
+                            Framing::Packets,
+                            &self.stats,
+                            &mut self.budget,
+                            self.packet_tap.as_ref(),
+                        )
+                        .ok();
                     }
                     Command::SendRaw(data) => {
-                        packet_writer.send_raw(&data);
-                        if packet_writer.should_flush_by_size() {
-                            let buf = packet_writer.take_buffer();
-                            if let Err(e) = buf_writer.write_all(&buf).await {
-                                error!(target: "TCP", "Failed to write raw data to TCP socket: {e}");
-                                return;
-                            }
+                        buffer_group(
+                            &mut packet_writer,
+                            std::slice::from_ref(&data),
+                            Framing::Raw,
+                            &self.stats,
+                            &mut self.budget,
+                            self.packet_tap.as_ref(),
+                        )
+                        .ok();
+                    }
+                    Command::Flush => {
+                        if let Err(e) = write_out(
+                            &mut buf_writer,
+                            &mut packet_writer,
+                            &self.config,
+                            &self.stats,
+                            &self.buffer_pool,
+                            &self.send_limiter,
+                            &mut self.budget,
+                        )
+                        .await
+                        {
+                            error!(
+                                target: "TCP",
+                                "Failed to flush TCP socket on request: {e}; dropping {} queued commands",
+                                self.command_receiver.len(),
+                            );
+                            return;
+                        }
+                        continue;
+                    }
+                    Command::SendFlushed(plaintext, written) => {
+                        if buffer_group(
+                            &mut packet_writer,
+                            std::slice::from_ref(&plaintext),
+                            Framing::Packets,
+                            &self.stats,
+                            &mut self.budget,
+                            self.packet_tap.as_ref(),
+                        )
+                        .is_err()
+                        {
+                            written.send(Err(WriteError::Rejected)).ok();
+                            continue;
+                        }
 
-                            self.buffer_pool.release(buf);
+                        if let Err(e) = write_out(
+                            &mut buf_writer,
+                            &mut packet_writer,
+                            &self.config,
+                            &self.stats,
+                            &self.buffer_pool,
+                            &self.send_limiter,
+                            &mut self.budget,
+                        )
+                        .await
+                        {
+                            error!(
+                                target: "TCP",
+                                "Failed to flush acknowledged packet to TCP socket: {e}; dropping {} queued commands",
+                                self.command_receiver.len(),
+                            );
+                            return;
                         }
+                        written.send(Ok(())).ok();
+                        continue;
                     }
                     Command::SetXteaKey(key) => {
                         packet_writer.set_xtea_key(key);
+                        continue;
                     }
                     Command::SetEncryptionEnabled(enabled) => {
                         packet_writer.set_xtea_enabled(enabled);
+                        continue;
+                    }
+                    Command::SetChecksumEnabled(enabled) => {
+                        packet_writer.set_checksum_enabled(enabled);
+                        self.checksum_enabled.store(enabled, Ordering::Release);
+                        continue;
                     }
                     Command::SetCompressionThreshold(_) => {
                         // reserved for future use
+                        continue;
+                    }
+                    Command::SendAndClose(plaintext) => {
+                        buffer_group(
+                            &mut packet_writer,
+                            std::slice::from_ref(&plaintext),
+                            Framing::Packets,
+                            &self.stats,
+                            &mut self.budget,
+                            self.packet_tap.as_ref(),
+                        )
+                        .ok();
+                        close_out(
+                            &mut buf_writer,
+                            &mut packet_writer,
+                            &self.stats,
+                            &self.buffer_pool,
+                        )
+                        .await;
+                        return;
                     }
                     Command::Close | Command::CloseWithReason(_) => {
-                        if !packet_writer.is_empty() {
-                            let buf = packet_writer.take_buffer();
-                            if let Err(e) = buf_writer.write_all(&buf).await {
-                                error!(target: "TCP", "Failed to write remaining data during TCP socket close: {e}");
-                            }
-                            self.buffer_pool.release(buf);
-                        }
-
-                        if let Err(e) = buf_writer.flush().await {
-                            error!(target: "TCP", "Failed to flush TCP socket during close: {e}");
-                        }
-
-                        if let Err(e) = buf_writer.shutdown().await {
-                            error!(target: "TCP", "Failed to shutdown TCP socket gracefully: {e}");
-                        }
+                        close_out(
+                            &mut buf_writer,
+                            &mut packet_writer,
+                            &self.stats,
+                            &self.buffer_pool,
+                        )
+                        .await;
                         return;
                     }
                 }
+
+                // Only Send, SendBatch and SendRaw get here, with whatever
+                // they buffered.
+                let pressure = self.budget.budget().is_under_pressure();
+                flush_due |= pressure || reached_flush_threshold(&self.config, &packet_writer);
+
+                if (pressure || packet_writer.should_flush_by_size())
+                    && let Err(e) = write_buffered(
+                        &mut buf_writer,
+                        &mut packet_writer,
+                        &self.config,
+                        &self.stats,
+                        &self.buffer_pool,
+                        &self.send_limiter,
+                        &mut self.budget,
+                    )
+                    .await
+                {
+                    error!(
+                        target: "TCP",
+                        "Failed to write buffered packets to TCP socket: {e}; dropping {} queued commands",
+                        self.command_receiver.len(),
+                    );
+                    return;
+                }
+            };
+
+            // Every handle is gone, so nothing can be queued anymore. What
+            // is already buffered still goes out before the socket closes.
+            if disconnected {
+                trace!(target: "TCP", "Writer session lost its connection handles; closing");
+                close_out(
+                    &mut buf_writer,
+                    &mut packet_writer,
+                    &self.stats,
+                    &self.buffer_pool,
+                )
+                .await;
+                return;
+            }
+
+            let immediate =
+                self.config.flush_policy == FlushPolicy::Immediate && !packet_writer.is_empty();
+            if (flush_due || immediate)
+                && let Err(e) = write_out(
+                    &mut buf_writer,
+                    &mut packet_writer,
+                    &self.config,
+                    &self.stats,
+                    &self.buffer_pool,
+                    &self.send_limiter,
+                    &mut self.budget,
+                )
+                .await
+            {
+                error!(
+                    target: "TCP",
+                    "Failed to flush drained commands to TCP socket: {e}; dropping {} queued commands",
+                    self.command_receiver.len(),
+                );
+                break;
+            }
+        }
+    }
+}
+
+/// Whether a failed socket operation is worth retrying.
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+    )
+}
+
+/// Writes whatever `packet_writer` still has buffered, then flushes and
+/// shuts down the socket. Bytes that cannot be written are logged as
+/// lost rather than dropped silently.
+async fn close_out<W>(
+    writer: &mut W,
+    packet_writer: &mut PacketWriter,
+    stats: &ConnectionStats,
+    buffer_pool: &BufferPool,
+) where
+    W: AsyncWrite + Unpin,
+{
+    if !packet_writer.is_empty() {
+        let buf = packet_writer.take_buffer();
+        match writer.write_all(&buf).await {
+            Ok(()) => stats.record_bytes_sent(buf.len() as u64),
+            Err(e) => error!(
+                target: "TCP",
+                "Failed to write remaining data during TCP socket close: {e}; {} buffered bytes lost",
+                buf.len(),
+            ),
+        }
+        buffer_pool.release(buf);
+    }
+
+    if let Err(e) = writer.flush().await {
+        error!(target: "TCP", "Failed to flush TCP socket during close: {e}");
+    }
+
+    if let Err(e) = writer.shutdown().await {
+        error!(target: "TCP", "Failed to shutdown TCP socket gracefully: {e}");
+    }
+}
+
+/// How [`buffer_group`] puts payloads onto the buffer.
+#[derive(Clone, Copy)]
+enum Framing {
+    /// Framed and encrypted with the connection's current settings.
+    Packets,
+    /// Appended as-is.
+    Raw,
+}
+
+/// Buffers `payloads` as one group, all or none: if one of them does
+/// not fit a frame, the whole group is dropped and the buffer is left as
+/// it was. Counts the packets sent, hands their frames to the packet tap
+/// and moves their charge from queued to buffered, or gives it back when
+/// the group is dropped.
+fn buffer_group(
+    packet_writer: &mut PacketWriter,
+    payloads: &[Vec<u8>],
+    framing: Framing,
+    stats: &ConnectionStats,
+    budget: &mut BufferedCharge,
+    packet_tap: Option<&(ConnectionId, PacketTap)>,
+) -> Result<(), FrameError> {
+    let bytes = payloads.iter().map(Vec::len).sum();
+    let from = packet_writer.buffer_len();
+    match framing {
+        Framing::Packets => {
+            if let Err(e) = packet_writer.try_send_all(payloads) {
+                warn!(target: "TCP", "Dropping {} outgoing packet(s): {e}", payloads.len());
+                budget.dropped(bytes);
+                return Err(e);
+            }
+            for _ in payloads {
+                stats.record_packet_sent();
+            }
+        }
+        Framing::Raw => {
+            for data in payloads {
+                packet_writer.send_raw(data);
             }
         }
     }
+    budget.framed(bytes);
+    tap_outgoing(packet_tap, packet_writer, from);
+    Ok(())
+}
+
+/// Hands the frames appended to `packet_writer` since `from` to the
+/// packet tap, if the session has one.
+fn tap_outgoing(
+    packet_tap: Option<&(ConnectionId, PacketTap)>,
+    packet_writer: &PacketWriter,
+    from: usize,
+) {
+    if let Some((id, tap)) = packet_tap
+        && let Some(frames) = packet_writer.buffered().get(from..)
+    {
+        tap.record(*id, PacketDirection::Outgoing, frames.to_vec());
+    }
+}
+
+/// Writes whatever `packet_writer` has buffered and flushes the socket.
+async fn write_out<W>(
+    writer: &mut W,
+    packet_writer: &mut PacketWriter,
+    config: &TcpSettings,
+    stats: &ConnectionStats,
+    buffer_pool: &BufferPool,
+    send_limiter: &SendRateLimiter,
+    budget: &mut BufferedCharge,
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    write_buffered(
+        writer,
+        packet_writer,
+        config,
+        stats,
+        buffer_pool,
+        send_limiter,
+        budget,
+    )
+    .await?;
+    flush_with_retries(writer, config).await
+}
+
+/// Writes whatever `packet_writer` has buffered, within the send rate,
+/// without flushing the socket. The packets stay charged to `budget`
+/// until they are written.
+async fn write_buffered<W>(
+    writer: &mut W,
+    packet_writer: &mut PacketWriter,
+    config: &TcpSettings,
+    stats: &ConnectionStats,
+    buffer_pool: &BufferPool,
+    send_limiter: &SendRateLimiter,
+    budget: &mut BufferedCharge,
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    if packet_writer.is_empty() {
+        return Ok(());
+    }
+
+    let buf = packet_writer.take_buffer();
+    send_limiter.throttle(buf.len()).await;
+    write_with_retries(writer, &buf, config).await?;
+    stats.record_bytes_sent(buf.len() as u64);
+    buffer_pool.release(buf);
+    budget.written();
+    Ok(())
+}
+
+/// Writes all of `buf`, giving a stalled or transiently failing socket
+/// up to `write_retries` further attempts of `write_timeout` each.
+///
+/// Uses `write` rather than `write_all` so a retry resumes after the
+/// bytes that already went out instead of sending them twice.
+async fn write_with_retries<W>(writer: &mut W, buf: &[u8], config: &TcpSettings) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut written = 0;
+    let mut failures = 0;
+    while written < buf.len() {
+        let error =
+            match tokio::time::timeout(config.write_timeout, writer.write(&buf[written..])).await {
+                Ok(Ok(0)) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(Ok(n)) => {
+                    written += n;
+                    continue;
+                }
+                Ok(Err(e)) if !is_transient(&e) => return Err(e),
+                Ok(Err(e)) => e,
+                Err(_) => io::ErrorKind::TimedOut.into(),
+            };
+
+        failures += 1;
+        if failures > config.write_retries {
+            return Err(error);
+        }
+        warn!(target: "TCP", "Socket write failed ({error}), retry {failures}/{}", config.write_retries);
+    }
+
+    Ok(())
+}
+
+/// Flushes `writer` with the same retry policy as [`write_with_retries`].
+async fn flush_with_retries<W>(writer: &mut W, config: &TcpSettings) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut failures = 0;
+    loop {
+        let error = match tokio::time::timeout(config.write_timeout, writer.flush()).await {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(e)) if !is_transient(&e) => return Err(e),
+            Ok(Err(e)) => e,
+            Err(_) => io::ErrorKind::TimedOut.into(),
+        };
+
+        failures += 1;
+        if failures > config.write_retries {
+            return Err(error);
+        }
+        warn!(target: "TCP", "Socket flush failed ({error}), retry {failures}/{}", config.write_retries);
+    }
+}
+
+/// Whether the buffered bytes crossed the configured flush threshold
+/// and must reach the socket without waiting for the next tick.
+fn reached_flush_threshold(config: &TcpSettings, packet_writer: &PacketWriter) -> bool {
+    config.flush_threshold > 0 && packet_writer.buffer_len() >= config.flush_threshold
 }
 
 #[cfg(test)]
@@ -170,9 +632,946 @@ mod tests {
             channel_capacity: 64,
             max_buffer_size: 256,
             max_connections: 5,
-            connection_timeout_secs: 10,
-            rate_burst: 50,
+            ..TcpSettings::default()
+        }
+    }
+
+    /// Writer that stalls (stays pending) until `ready_at`, then accepts
+    /// everything.
+    struct StallingWriter {
+        ready_at: tokio::time::Instant,
+        written: Vec<u8>,
+    }
+
+    impl StallingWriter {
+        fn stalled_for(stall: Duration) -> Self {
+            StallingWriter {
+                ready_at: tokio::time::Instant::now() + stall,
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncWrite for StallingWriter {
+        fn poll_write(
+            mut self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<io::Result<usize>> {
+            if tokio::time::Instant::now() < self.ready_at {
+                return std::task::Poll::Pending;
+            }
+            self.written.extend_from_slice(buf);
+            std::task::Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Writer that records the bytes written between consecutive flushes.
+    #[derive(Default)]
+    struct FlushRecorder {
+        pending: Vec<u8>,
+        flushes: Arc<std::sync::Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl AsyncWrite for FlushRecorder {
+        fn poll_write(
+            mut self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<io::Result<usize>> {
+            self.pending.extend_from_slice(buf);
+            std::task::Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(
+            mut self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            if !self.pending.is_empty() {
+                let flushed = std::mem::take(&mut self.pending);
+                self.flushes
+                    .lock()
+                    .expect("flush log lock poisoned")
+                    .push(flushed);
+            }
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    fn retry_config(write_retries: u32) -> TcpSettings {
+        TcpSettings {
+            write_timeout: Duration::from_millis(10),
+            write_retries,
+            ..make_config()
+        }
+    }
+
+    #[tokio::test]
+    async fn write_with_retries_recovers_after_a_timeout() {
+        // Outlives the first 10ms attempt but not the second
+        let mut writer = StallingWriter::stalled_for(Duration::from_millis(15));
+
+        write_with_retries(&mut writer, b"hello", &retry_config(1))
+            .await
+            .expect("write should succeed on the retry");
+
+        assert_eq!(writer.written, b"hello");
+    }
+
+    #[tokio::test]
+    async fn write_with_retries_gives_up_after_exhausting_retries() {
+        let mut writer = StallingWriter::stalled_for(Duration::from_secs(10));
+
+        let err = write_with_retries(&mut writer, b"hello", &retry_config(2))
+            .await
+            .expect_err("a long stall should exhaust two retries");
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(writer.written.is_empty());
+    }
+
+    /// Spawns a writer session woken by the returned handle, writing to
+    /// the returned client end of an in-memory pipe, and waits out its
+    /// first, immediate tick, so what is queued afterwards is drained by
+    /// the wake-up alone.
+    async fn spawn_woken(
+        config: TcpSettings,
+    ) -> (tokio::io::DuplexStream, crate::connection::ConnectionHandle) {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let handle = crate::connection::ConnectionHandle::new(
+            ConnectionId::new(0, 1),
+            "127.0.0.1:7172".parse().expect("valid test address"),
+            tx,
+        );
+        WriterSession::new(
+            rx,
+            server,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .with_wake(handle.writer_wake().clone())
+        .spawn();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        (client, handle)
+    }
+
+    #[tokio::test]
+    async fn immediate_policy_flushes_as_commands_arrive() {
+        use tokio::io::AsyncReadExt;
+
+        let (mut client, handle) = spawn_woken(TcpSettings {
+            flush_interval: Duration::from_secs(60),
+            flush_policy: FlushPolicy::Immediate,
+            ..make_config()
+        })
+        .await;
+
+        handle
+            .send(b"hello".to_vec())
+            .expect("failed to queue packet");
+
+        let mut buf = [0u8; 2 + 4 + 5];
+        tokio::time::timeout(Duration::from_secs(1), client.read_exact(&mut buf))
+            .await
+            .expect("the packet should be flushed without waiting for a tick")
+            .expect("failed to read flushed packet");
+        assert_eq!(&buf[6..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn explicit_flush_writes_as_soon_as_it_is_queued() {
+        use tokio::io::AsyncReadExt;
+
+        let (mut client, handle) = spawn_woken(TcpSettings {
+            flush_interval: Duration::from_secs(60),
+            flush_policy: FlushPolicy::Manual,
+            ..make_config()
+        })
+        .await;
+
+        handle
+            .send(b"hello".to_vec())
+            .expect("failed to queue packet");
+        let mut buf = [0u8; 2 + 4 + 5];
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), client.read_exact(&mut buf))
+                .await
+                .is_err(),
+            "nothing should be written before the flush"
+        );
+
+        handle.flush().expect("failed to queue flush");
+        tokio::time::timeout(Duration::from_secs(1), client.read_exact(&mut buf))
+            .await
+            .expect("the flush should not wait for a tick")
+            .expect("failed to read flushed packet");
+        assert_eq!(&buf[6..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn writer_session_flushes_immediately_past_threshold() {
+        use tokio::io::AsyncReadExt;
+
+        // The next tick is a minute away, so only the threshold can push
+        // the data out.
+        let (mut client, handle) = spawn_woken(TcpSettings {
+            flush_interval: Duration::from_secs(60),
+            flush_threshold: 8,
+            ..make_config()
+        })
+        .await;
+
+        handle
+            .send(b"hello".to_vec())
+            .expect("failed to queue packet");
+
+        let mut buf = [0u8; 2 + 4 + 5];
+        tokio::time::timeout(Duration::from_secs(1), client.read_exact(&mut buf))
+            .await
+            .expect("packet past the threshold should be flushed without waiting for a tick")
+            .expect("failed to read flushed packet");
+
+        assert_eq!(&buf[6..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn writer_session_flushes_a_batch_together() {
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let config = TcpSettings {
+            flush_interval: Duration::from_secs(60),
+            // Each 10-byte frame alone would cross this threshold.
+            flush_threshold: 8,
+            ..make_config()
+        };
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let packets = [b"aaaa", b"bbbb", b"cccc"].map(|packet| packet.to_vec());
+        tx.send(Command::SendBatch(packets.to_vec()))
+            .expect("failed to queue packet batch");
+        WriterSession::new(
+            rx,
+            recorder,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while flushes.lock().expect("flush log lock poisoned").is_empty() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("batch past the threshold should be flushed");
+
+        let expected: Vec<u8> = packets
+            .iter()
+            .flat_map(|payload| {
+                let mut frame = 8u16.to_le_bytes().to_vec();
+                frame.extend_from_slice(&suon_adler32::generate(payload).to_le_bytes());
+                frame.extend_from_slice(payload);
+                frame
+            })
+            .collect();
+        assert_eq!(
+            *flushes.lock().expect("flush log lock poisoned"),
+            vec![expected]
+        );
+    }
+
+    #[tokio::test]
+    async fn writer_session_drops_a_batch_with_an_oversized_packet_whole() {
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let stats = Arc::new(ConnectionStats::default());
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        tx.send(Command::SendBatch(vec![
+            b"before".to_vec(),
+            vec![0; u16::MAX as usize],
+            b"after".to_vec(),
+        ]))
+        .expect("failed to queue packet batch");
+        tx.send(Command::Send(b"next".to_vec()))
+            .expect("failed to queue packet");
+        tx.send(Command::Flush).expect("failed to queue flush");
+        WriterSession::new(
+            rx,
+            recorder,
+            make_config(),
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .with_stats(stats.clone())
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while flushes.lock().expect("flush log lock poisoned").is_empty() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the packet after the batch should be flushed");
+
+        let mut expected = 8u16.to_le_bytes().to_vec();
+        expected.extend_from_slice(&suon_adler32::generate(b"next").to_le_bytes());
+        expected.extend_from_slice(b"next");
+        assert_eq!(
+            *flushes.lock().expect("flush log lock poisoned"),
+            vec![expected],
+            "no member of the rejected batch may go out"
+        );
+        assert_eq!(stats.packets_sent.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn immediate_policy_flushes_without_waiting_for_a_tick() {
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let config = TcpSettings {
+            flush_interval: Duration::from_secs(60),
+            flush_policy: FlushPolicy::Immediate,
+            ..make_config()
+        };
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        tx.send(Command::Send(b"hello".to_vec()))
+            .expect("failed to queue packet");
+        WriterSession::new(
+            rx,
+            recorder,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while flushes.lock().expect("flush log lock poisoned").is_empty() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("immediate policy should flush once the command is framed");
+
+        let flushed = flushes.lock().expect("flush log lock poisoned");
+        assert_eq!(flushed.len(), 1);
+        assert!(flushed[0].ends_with(b"hello"));
+    }
+
+    #[tokio::test]
+    async fn manual_policy_waits_for_an_explicit_flush() {
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let config = TcpSettings {
+            flush_interval: Duration::from_millis(5),
+            flush_policy: FlushPolicy::Manual,
+            ..make_config()
+        };
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        tx.send(Command::Send(b"hello".to_vec()))
+            .expect("failed to queue packet");
+        WriterSession::new(
+            rx,
+            recorder,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        // Several ticks pass without pushing anything out
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(flushes.lock().expect("flush log lock poisoned").is_empty());
+
+        tx.send(Command::Flush).expect("failed to queue flush");
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while flushes.lock().expect("flush log lock poisoned").is_empty() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("explicit flush should push the buffered packet out");
+
+        let flushed = flushes.lock().expect("flush log lock poisoned");
+        assert_eq!(flushed.len(), 1);
+        assert!(flushed[0].ends_with(b"hello"));
+    }
+
+    #[tokio::test]
+    async fn writer_session_sends_buffered_bytes_after_the_connection_is_unregistered() {
+        use crate::connection::manager::ConnectionManager;
+
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let config = TcpSettings {
+            flush_interval: Duration::from_millis(5),
+            flush_policy: FlushPolicy::Manual,
+            ..make_config()
+        };
+
+        let manager = ConnectionManager::new(0);
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let peer = "127.0.0.1:7172".parse().expect("valid test address");
+        let id = manager.register(peer, config.protocol, tx);
+        manager
+            .get(id)
+            .expect("connection should be registered")
+            .send(b"goodbye".to_vec())
+            .expect("failed to queue packet");
+
+        // The manager held the last handle, so the writer loses every sender.
+        manager.unregister(id);
+        WriterSession::new(
+            rx,
+            recorder,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while flushes.lock().expect("flush log lock poisoned").is_empty() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("the buffered packet should be sent, not lost");
+
+        let flushed = flushes.lock().expect("flush log lock poisoned");
+        assert_eq!(flushed.len(), 1);
+        assert!(flushed[0].ends_with(b"goodbye"));
+    }
+
+    #[tokio::test]
+    async fn send_flushed_resolves_once_the_packet_is_written() {
+        use crate::connection::{ConnectionHandle, ConnectionId};
+
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let config = TcpSettings {
+            flush_interval: Duration::from_secs(60),
+            ..make_config()
+        };
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let handle = ConnectionHandle::new(
+            ConnectionId::new(0, 1),
+            "127.0.0.1:7172".parse().expect("valid test address"),
+            tx,
+        );
+        let written = handle.send_flushed(b"ack me".to_vec());
+        assert!(flushes.lock().expect("flush log lock poisoned").is_empty());
+
+        WriterSession::new(
+            rx,
+            recorder,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(1), written)
+            .await
+            .expect("the write should be acknowledged without waiting for a tick")
+            .expect("the packet should be written");
+
+        let flushed = flushes.lock().expect("flush log lock poisoned");
+        assert_eq!(flushed.len(), 1);
+        assert!(flushed[0].ends_with(b"ack me"));
+    }
+
+    #[tokio::test]
+    async fn send_flushed_reports_a_closed_connection() {
+        use crate::connection::{ConnectionHandle, ConnectionId, WriteError};
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let handle = ConnectionHandle::new(
+            ConnectionId::new(0, 1),
+            "127.0.0.1:7172".parse().expect("valid test address"),
+            tx,
+        );
+
+        // The writer goes away with a command still queued.
+        let lost = handle.send_flushed(b"lost".to_vec());
+        drop(rx);
+
+        assert_eq!(
+            handle.send_flushed(b"late".to_vec()).await,
+            Err(WriteError::Closed)
+        );
+
+        // The queued command is only freed once the last sender goes too.
+        drop(handle);
+        assert_eq!(lost.await, Err(WriteError::Closed));
+    }
+
+    #[tokio::test]
+    async fn writer_session_holds_writes_to_the_send_rate() {
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let config = TcpSettings {
+            flush_interval: Duration::from_secs(60),
+            flush_threshold: 1,
+            max_send_rate: 400,
+            ..make_config()
+        };
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        for _ in 0..3 {
+            tx.send(Command::Send(vec![0xAB; 200]))
+                .expect("failed to queue packet");
+        }
+        let started = tokio::time::Instant::now();
+        WriterSession::new(
+            rx,
+            recorder,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                let flushed: usize = flushes
+                    .lock()
+                    .expect("flush log lock poisoned")
+                    .iter()
+                    .map(Vec::len)
+                    .sum();
+                if flushed >= 618 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("throttled frames should still all go out");
+
+        // 618 bytes at 400 B/s with a 400-byte burst: ~545ms of waiting.
+        assert!(started.elapsed() >= Duration::from_millis(450));
+    }
+
+    #[tokio::test]
+    async fn writer_session_coalesces_queued_packets_into_one_flush() {
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let config = TcpSettings {
+            flush_interval: Duration::from_secs(60),
+            // Each packet crosses the threshold on its own.
+            flush_threshold: 8,
+            ..make_config()
+        };
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        for byte in 0..4u8 {
+            tx.send(Command::Send(vec![byte; 16]))
+                .expect("failed to queue packet");
+        }
+        WriterSession::new(
+            rx,
+            recorder,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while flushes.lock().expect("flush log lock poisoned").is_empty() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("queued packets should be flushed");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let flushes = flushes.lock().expect("flush log lock poisoned");
+        assert_eq!(flushes.len(), 1, "burst should go out in one flush");
+        assert_eq!(flushes[0].len(), 4 * (2 + 4 + 16));
+    }
+
+    #[tokio::test]
+    async fn writer_session_releases_the_buffer_budget_once_written() {
+        use crate::connection::{
+            BufferBudget, ConnectionHandle, ConnectionId, WriteError, budget::BudgetCharge,
+        };
+
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let budget = BufferBudget::new(100);
+        // Other connections already hold most of the budget.
+        let others = BudgetCharge::new(budget.clone());
+        assert!(others.try_charge(95));
+
+        let charge = BudgetCharge::new(budget.clone());
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let handle = ConnectionHandle::new(
+            ConnectionId::new(0, 1),
+            "127.0.0.1:7172".parse().expect("valid test address"),
+            tx,
+        )
+        .with_budget_charge(charge.clone());
+
+        // Refused when queued, before the writer ever sees it.
+        let refused = handle.send_flushed(b"too much".to_vec()).await;
+        assert_eq!(refused, Err(WriteError::Rejected));
+        assert!(handle.send(b"too much".to_vec()).is_err());
+        assert_eq!(budget.used(), 95);
+
+        others.release(95);
+        handle
+            .send(b"queued".to_vec())
+            .expect("the packet should fit once the budget is released");
+        assert_eq!(budget.used(), 6, "charged as soon as it is queued");
+
+        WriterSession::new(
+            rx,
+            recorder,
+            make_config(),
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .with_budget_charge(charge)
+        .spawn();
+
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            handle.send_flushed(b"fits".to_vec()),
+        )
+        .await
+        .expect("the writer should answer straight away")
+        .expect("the packet should be written");
+        assert_eq!(flushes.lock().expect("flush log lock poisoned").len(), 1);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[tokio::test]
+    async fn writer_session_gives_back_packets_still_queued_when_it_ends() {
+        use crate::connection::{
+            BufferBudget, ConnectionHandle, ConnectionId, budget::BudgetCharge,
+        };
+
+        let budget = BufferBudget::new(100);
+        let charge = BudgetCharge::new(budget.clone());
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let handle = ConnectionHandle::new(
+            ConnectionId::new(0, 1),
+            "127.0.0.1:7172".parse().expect("valid test address"),
+            tx,
+        )
+        .with_budget_charge(charge.clone());
+        handle.close().expect("failed to queue close");
+        handle
+            .send(vec![0xAB; 40])
+            .expect("failed to queue packet after close");
+        assert_eq!(budget.used(), 40);
+
+        WriterSession::new(
+            rx,
+            FlushRecorder::default(),
+            make_config(),
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .with_budget_charge(charge)
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while budget.used() > 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("the packet queued behind the close should be given back");
+    }
+
+    #[tokio::test]
+    async fn writer_session_writes_out_early_under_budget_pressure() {
+        use crate::connection::{
+            BufferBudget, ConnectionHandle, ConnectionId, budget::BudgetCharge,
+        };
+
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let budget = BufferBudget::new(200);
+        let charge = BudgetCharge::new(budget.clone());
+        let config = TcpSettings {
+            // Neither the interval nor a threshold would flush the packet.
+            flush_interval: Duration::from_secs(60),
+            ..make_config()
+        };
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        let handle = ConnectionHandle::new(
+            ConnectionId::new(0, 1),
+            "127.0.0.1:7172".parse().expect("valid test address"),
+            tx,
+        )
+        .with_budget_charge(charge.clone());
+        handle
+            .send(vec![0xCD; 150])
+            .expect("failed to queue packet");
+        WriterSession::new(
+            rx,
+            recorder,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .with_budget_charge(charge)
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while flushes.lock().expect("flush log lock poisoned").is_empty() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("a packet holding most of the budget should go out at once");
+
+        assert_eq!(budget.used(), 0);
+        drop(handle);
+    }
+
+    #[tokio::test]
+    async fn writer_session_encrypts_only_packets_queued_after_the_key() {
+        let recorder = FlushRecorder::default();
+        let flushes = recorder.flushes.clone();
+        let config = TcpSettings {
+            protocol: ProtocolSettings {
+                header_size: 6,
+                has_checksum: true,
+                uses_xtea: true,
+                uses_rsa: true,
+            },
+            encryption: EncryptionSettings {
+                incoming: true,
+                outgoing: true,
+            },
+            flush_interval: Duration::from_secs(60),
+            ..make_config()
+        };
+        let key = [0x0123_4567, 0x89AB_CDEF, 0xFEDC_BA98, 0x7654_3210];
+
+        let (tx, rx) = crossbeam_channel::bounded(16);
+        for command in [
+            Command::Send(b"login".to_vec()),
+            Command::SetXteaKey(key),
+            Command::Send(b"game".to_vec()),
+            Command::Flush,
+        ] {
+            tx.send(command).expect("failed to queue command");
+        }
+        WriterSession::new(
+            rx,
+            recorder,
+            config,
+            Shutdown::new(),
+            crate::test_buffer_pool(),
+        )
+        .spawn();
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while flushes.lock().expect("flush log lock poisoned").is_empty() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("both packets should be flushed together");
+
+        let flushed = flushes.lock().expect("flush log lock poisoned")[0].clone();
+        // The login response was framed before the key: checksum framing,
+        // payload in the clear.
+        let (plain, encrypted) = flushed.split_at(2 + 4 + 5);
+        assert_eq!(&plain[..2], &[9, 0]);
+        assert_eq!(&plain[6..], b"login");
+
+        let mut body = encrypted[6..].to_vec();
+        suon_xtea::decrypt(&mut body, &suon_xtea::expand(&key))
+            .expect("the second frame should be whole XTEA blocks");
+        assert_eq!(crate::server::tcp::xtea_unpad(&body), b"game");
+    }
+
+    #[tokio::test]
+    async fn writer_session_distinguishes_empty_send_from_empty_raw() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind TCP listener for empty payload test");
+
+        let addr = listener
+            .local_addr()
+            .expect("failed to get listener local address");
+
+        let shutdown = Shutdown::new();
+        let config = make_config();
+        let session_shutdown = shutdown.clone();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener
+                .accept()
+                .await
+                .expect("failed to accept incoming connection");
+
+            let (.., writer_half) = stream.into_split();
+            let (tx, rx) = crossbeam_channel::bounded(16);
+
+            tx.send(Command::SendRaw(Vec::new())).ok();
+            tx.send(Command::Send(Vec::new())).ok();
+            tx.send(Command::SendRaw(Vec::new())).ok();
+            WriterSession::new(
+                rx,
+                writer_half,
+                config,
+                session_shutdown,
+                crate::test_buffer_pool(),
+            )
+            .spawn();
+            tx
+        });
+
+        let mut client = tokio::net::TcpStream::connect(addr)
+            .await
+            .expect("failed to connect test client");
+
+        let tx = server.await.expect("server task should not panic");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(tx);
+        shutdown.trigger();
+
+        use tokio::io::AsyncReadExt;
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), client.read_to_end(&mut received))
+            .await
+            .expect("writer should close after shutdown")
+            .expect("failed to read from writer session");
+
+        // Only the framed empty payload reaches the wire: size 4, then the
+        // checksum of no bytes.
+        let mut expected = 4u16.to_le_bytes().to_vec();
+        expected.extend_from_slice(&suon_adler32::generate(b"").to_le_bytes());
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn writer_session_preserves_per_sender_order_across_flushes() {
+        use crate::connection::{ConnectionHandle, ConnectionId};
+
+        const THREADS: u8 = 4;
+        const PACKETS: u16 = 200;
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind TCP listener for ordering test");
+
+        let addr = listener
+            .local_addr()
+            .expect("failed to get listener local address");
+
+        // Small buffers so size and threshold flushes happen mid-stream.
+        let config = TcpSettings {
+            max_buffer_size: 64,
+            flush_threshold: 32,
+            ..make_config()
+        };
+
+        let server = tokio::spawn(async move {
+            let (stream, peer) = listener
+                .accept()
+                .await
+                .expect("failed to accept incoming connection");
+
+            let (.., writer_half) = stream.into_split();
+            let (tx, rx) = crossbeam_channel::bounded(usize::from(THREADS) * 256);
+            WriterSession::new(
+                rx,
+                writer_half,
+                config,
+                Shutdown::new(),
+                crate::test_buffer_pool(),
+            )
+            .spawn();
+            ConnectionHandle::new(ConnectionId::new(0, 1), peer, tx)
+        });
+
+        let mut client = tokio::net::TcpStream::connect(addr)
+            .await
+            .expect("failed to connect test client");
+
+        let handle = server.await.expect("server task should not panic");
+        let senders: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    for seq in 0..PACKETS {
+                        let [lo, hi] = seq.to_le_bytes();
+                        let result = if seq % 2 == 0 {
+                            handle.send(vec![thread, lo, hi])
+                        } else {
+                            // Pre-framed with a zero ("no") checksum.
+                            handle.send_raw(vec![7, 0, 0, 0, 0, 0, thread, lo, hi])
+                        };
+                        result.expect("command channel should have room");
+                    }
+                })
+            })
+            .collect();
+
+        for sender in senders {
+            sender.join().expect("sender thread should not panic");
         }
+        handle.close().expect("close should be queued");
+
+        use tokio::io::AsyncReadExt;
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut received))
+            .await
+            .expect("writer should close after the queued Close")
+            .expect("failed to read from writer session");
+
+        let mut next = [0u16; THREADS as usize];
+        let mut rest = received.as_slice();
+        while !rest.is_empty() {
+            let size = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+            let payload = &rest[2 + 4..2 + size];
+            let thread = usize::from(payload[0]);
+            let seq = u16::from_le_bytes([payload[1], payload[2]]);
+
+            assert_eq!(seq, next[thread], "thread {thread} packets out of order");
+            next[thread] += 1;
+            rest = &rest[2 + size..];
+        }
+
+        assert_eq!(next, [PACKETS; THREADS as usize]);
     }
 
     #[tokio::test]
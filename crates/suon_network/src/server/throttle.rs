@@ -5,12 +5,14 @@ use std::{
         Arc, Mutex,
         atomic::{AtomicUsize, Ordering},
     },
-    time::Instant,
+    time::{Duration, Instant},
 };
 use tracing::{debug, trace};
 
 use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
 
+use crate::connection::manager::ConnectionManager;
+
 #[derive(Debug, Clone)]
 pub(crate) struct ConnectionLimiter {
     semaphore: Arc<Semaphore>,
@@ -52,6 +54,102 @@ impl Drop for ConnectionPermit {
     }
 }
 
+/// Bounds how many accepted sockets may wait for Lua's `onConnect`
+/// decision at once.
+///
+/// While the queue is full the accept loop stops calling `accept()`, so
+/// a connection flood backs up in the kernel listen backlog instead of
+/// piling up tasks on the main thread.
+#[derive(Debug, Clone)]
+pub(crate) struct AcceptQueue {
+    semaphore: Arc<Semaphore>,
+}
+
+impl AcceptQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(capacity)),
+        }
+    }
+
+    /// Waits for a free slot, recording it in the manager's
+    /// [`pending_accepts`](crate::connection::ConnectionStats::pending_accepts)
+    /// gauge until the returned [`AcceptSlot`] is dropped.
+    pub async fn reserve(&self, manager: &Arc<ConnectionManager>) -> AcceptSlot {
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("accept queue semaphore is never closed");
+        manager.stats().record_accept_queued();
+        AcceptSlot {
+            _permit: permit,
+            manager: manager.clone(),
+        }
+    }
+}
+
+pub(crate) struct AcceptSlot {
+    _permit: OwnedSemaphorePermit,
+    manager: Arc<ConnectionManager>,
+}
+
+impl Drop for AcceptSlot {
+    fn drop(&mut self) {
+        self.manager.stats().record_accept_dequeued();
+    }
+}
+
+/// Token bucket capping the bytes per second a writer session sends.
+///
+/// The bucket holds one second's worth of bytes, so bursts up to the
+/// rate go out at once; anything past that is delayed until enough
+/// budget has built up again.
+#[derive(Debug)]
+pub(crate) struct SendRateLimiter {
+    bytes_per_sec: u64,
+    /// When the bucket will be completely refilled, given everything
+    /// reserved so far.
+    refilled_at: Mutex<Option<tokio::time::Instant>>,
+}
+
+impl SendRateLimiter {
+    const BURST: Duration = Duration::from_secs(1);
+
+    /// A limit of 0 bytes per second never delays anything.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            refilled_at: Mutex::new(None),
+        }
+    }
+
+    /// Charges `bytes` to the bucket and returns how long to wait before
+    /// sending them.
+    pub fn reserve(&self, bytes: usize) -> Duration {
+        if self.bytes_per_sec == 0 {
+            return Duration::ZERO;
+        }
+
+        let now = tokio::time::Instant::now();
+        let cost = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        let mut refilled_at = self.refilled_at.lock().unwrap_or_else(|e| e.into_inner());
+        let next = refilled_at.map_or(now, |at| at.max(now)) + cost;
+        *refilled_at = Some(next);
+        next.saturating_duration_since(now + Self::BURST)
+    }
+
+    /// Waits until `bytes` may be sent.
+    pub async fn throttle(&self, bytes: usize) {
+        let delay = self.reserve(bytes);
+        if !delay.is_zero() {
+            trace!(target: "Throttle", "Delaying {bytes} outgoing bytes by {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 struct PacketCounter {
     timestamps: Vec<Instant>,
@@ -64,6 +162,9 @@ pub(crate) struct PacketRateLimiter {
 }
 
 impl PacketRateLimiter {
+    /// How long an attempt counts against the burst.
+    const WINDOW: Duration = Duration::from_secs(1);
+
     pub fn new(max_burst: u32) -> Self {
         Self {
             inner: Arc::new(Mutex::new(HashMap::new())),
@@ -78,7 +179,7 @@ impl PacketRateLimiter {
 
         state
             .timestamps
-            .retain(|t| now.duration_since(*t).as_secs() < 1);
+            .retain(|t| now.duration_since(*t) < Self::WINDOW);
 
         if state.timestamps.len() >= self.max_burst as usize {
             debug!(target: "Throttle", "Rate limiting {addr}: burst exceeded");
@@ -89,6 +190,37 @@ impl PacketRateLimiter {
         true
     }
 
+    /// When `addr` may connect again if [`allow`](Self::allow) would
+    /// refuse it now, or `None` if it would be let through. Unlike
+    /// `allow`, this records no attempt and leaves the state untouched.
+    ///
+    /// With a burst of 0 every address is refused; the reported time is
+    /// then one window from now.
+    #[allow(dead_code)]
+    pub fn is_blocked(&self, addr: &SocketAddr) -> Option<Instant> {
+        let now = Instant::now();
+        let max_burst = self.max_burst as usize;
+        if max_burst == 0 {
+            return Some(now + Self::WINDOW);
+        }
+
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let recent: Vec<Instant> = inner
+            .get(addr)?
+            .timestamps
+            .iter()
+            .copied()
+            .filter(|t| now.duration_since(*t) < Self::WINDOW)
+            .collect();
+        if recent.len() < max_burst {
+            return None;
+        }
+
+        // Attempts are recorded oldest first; enough of them must age out
+        // to drop below the burst.
+        Some(recent[recent.len() - max_burst] + Self::WINDOW)
+    }
+
     #[allow(dead_code)]
     pub fn remove(&self, addr: &SocketAddr) {
         drop(
@@ -106,6 +238,25 @@ mod tests {
     use super::*;
     use std::net::{Ipv4Addr, SocketAddrV4};
 
+    #[test]
+    fn send_rate_limiter_allows_a_burst_then_delays() {
+        let limiter = SendRateLimiter::new(1000);
+        assert_eq!(limiter.reserve(600), Duration::ZERO);
+        assert_eq!(limiter.reserve(400), Duration::ZERO);
+
+        let delay = limiter.reserve(500);
+        assert!(
+            delay > Duration::from_millis(450) && delay <= Duration::from_millis(500),
+            "half a second's worth past the burst should wait ~500ms, got {delay:?}"
+        );
+    }
+
+    #[test]
+    fn send_rate_limiter_zero_is_unlimited() {
+        let limiter = SendRateLimiter::new(0);
+        assert_eq!(limiter.reserve(usize::MAX), Duration::ZERO);
+    }
+
     fn test_addr(n: u16) -> SocketAddr {
         SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, n))
     }
@@ -146,6 +297,47 @@ mod tests {
         assert_eq!(limiter.active_count(), 0);
     }
 
+    #[tokio::test]
+    async fn accept_queue_tracks_pending_accepts() {
+        let queue = AcceptQueue::new(2);
+        let manager = Arc::new(ConnectionManager::new(0));
+
+        let first = queue.reserve(&manager).await;
+        let second = queue.reserve(&manager).await;
+        assert_eq!(manager.stats().pending_accepts.load(Ordering::Relaxed), 2);
+
+        drop(first);
+        assert_eq!(manager.stats().pending_accepts.load(Ordering::Relaxed), 1);
+
+        drop(second);
+        assert_eq!(manager.stats().pending_accepts.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn accept_queue_waits_when_full() {
+        let queue = AcceptQueue::new(1);
+        let manager = Arc::new(ConnectionManager::new(0));
+
+        let slot = queue.reserve(&manager).await;
+        let blocked = tokio::time::timeout(
+            std::time::Duration::from_millis(20),
+            queue.reserve(&manager),
+        )
+        .await;
+        assert!(
+            blocked.is_err(),
+            "reserve should wait while the queue is full"
+        );
+
+        drop(slot);
+        let _slot = tokio::time::timeout(
+            std::time::Duration::from_millis(20),
+            queue.reserve(&manager),
+        )
+        .await
+        .expect("reserve should succeed once a slot is released");
+    }
+
     #[test]
     fn rate_limiter_allows_up_to_burst() {
         let rl = PacketRateLimiter::new(3);
@@ -177,6 +369,26 @@ mod tests {
         assert!(rl.allow(addr));
     }
 
+    #[test]
+    fn rate_limiter_is_blocked_peeks_without_recording() {
+        let rl = PacketRateLimiter::new(2);
+        let addr = test_addr(4);
+
+        assert_eq!(rl.is_blocked(&addr), None);
+        assert!(rl.allow(addr));
+        assert_eq!(rl.is_blocked(&addr), None);
+        assert!(rl.allow(addr));
+
+        let until = rl
+            .is_blocked(&addr)
+            .expect("an address past its burst should be blocked");
+        let now = Instant::now();
+        assert!(until > now && until <= now + Duration::from_secs(1));
+        // Peeking did not use up attempts of its own.
+        assert_eq!(rl.is_blocked(&addr), Some(until));
+        assert!(rl.is_blocked(&test_addr(5)).is_none());
+    }
+
     #[test]
     fn rate_limiter_per_ip_independent() {
         let rl = PacketRateLimiter::new(2);
@@ -1,12 +1,73 @@
-use std::{path::Path, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use tracing::{error, info};
 
 use crate::{
-    server::{kind::ServerKind, settings::ServerSettings, tcp::ProtocolSettings},
+    server::{
+        kind::ServerKind,
+        settings::ServerSettings,
+        tcp::{ProtocolSettings, TcpSettings},
+    },
     settings_error::SettingsError,
 };
 
-const FILE: &str = "NetworkSettings.toml";
+/// Settings file name without extension; `load` looks for it with each
+/// [`SettingsFormat`] extension in turn.
+const FILE_STEM: &str = "NetworkSettings";
+
+/// Serialization format of a settings file, chosen by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingsFormat {
+    Toml,
+    Json,
+    Ron,
+}
+
+impl SettingsFormat {
+    /// Lookup order for `load`; the first entry is used when creating the
+    /// default file.
+    const ALL: [SettingsFormat; 3] = [
+        SettingsFormat::Toml,
+        SettingsFormat::Json,
+        SettingsFormat::Ron,
+    ];
+
+    fn extension(self) -> &'static str {
+        match self {
+            SettingsFormat::Toml => "toml",
+            SettingsFormat::Json => "json",
+            SettingsFormat::Ron => "ron",
+        }
+    }
+
+    fn from_path(path: &Path) -> Result<Self, SettingsError> {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        Self::ALL
+            .into_iter()
+            .find(|format| Some(format.extension()) == extension)
+            .ok_or_else(|| SettingsError::UnsupportedFormat(path.display().to_string()))
+    }
+
+    fn parse(self, content: &str) -> Result<NetworkSettings, SettingsError> {
+        Ok(match self {
+            SettingsFormat::Toml => toml::from_str(content)?,
+            SettingsFormat::Json => serde_json::from_str(content)?,
+            SettingsFormat::Ron => ron::from_str(content)?,
+        })
+    }
+
+    fn render(self, settings: &NetworkSettings) -> Result<String, SettingsError> {
+        Ok(match self {
+            SettingsFormat::Toml => toml::to_string(settings)?,
+            SettingsFormat::Json => serde_json::to_string_pretty(settings)?,
+            SettingsFormat::Ron => {
+                ron::ser::to_string_pretty(settings, ron::ser::PrettyConfig::default())?
+            }
+        })
+    }
+}
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct BufferPoolSettings {
@@ -25,16 +86,32 @@ impl Default for BufferPoolSettings {
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct NetworkSettings {
+    /// Threads driving connection IO on the network runtime.
     pub worker_threads: usize,
+    /// Upper bound on the runtime's pool for blocking work.
+    #[serde(default = "default_max_blocking_threads")]
+    pub max_blocking_threads: usize,
     pub server: Vec<ServerSettings>,
     pub buffer_pool: BufferPoolSettings,
+    /// Raw frames kept by the [`PacketTap`](crate::connection::PacketTap)
+    /// for debugging; `0` turns the capture off.
+    #[serde(default)]
+    pub packet_tap_capacity: usize,
+    /// Cap on the bytes buffered for sending across all connections, see
+    /// [`BufferBudget`](crate::connection::BufferBudget); `0` leaves it
+    /// unbounded.
+    #[serde(default)]
+    pub max_total_buffer_bytes: usize,
 }
 
 impl Default for NetworkSettings {
     fn default() -> Self {
         NetworkSettings {
             worker_threads: 2,
+            max_blocking_threads: default_max_blocking_threads(),
             buffer_pool: BufferPoolSettings::default(),
+            packet_tap_capacity: 0,
+            max_total_buffer_bytes: 0,
             server: vec![
                 ServerSettings {
                     port: 7171,
@@ -50,10 +127,32 @@ impl Default for NetworkSettings {
                         encryption: Default::default(),
                         channel_capacity: 1024,
                         max_buffer_size: 4096,
+                        flush_threshold: 0,
                         max_connections: 100,
                         rate_burst: 50,
+                        accept_queue_capacity: 64,
+                        write_timeout: Duration::from_secs(5),
+                        write_retries: 3,
+                        allow_fragmentation: false,
+                        max_fragmented_size: 1 << 20,
+                        packet_size_limits: Default::default(),
+                        auto_keep_alive: false,
+                        flush_policy: Default::default(),
+                        max_pending_packets: 256,
+                        max_send_rate: 0,
+                        status_motd: None,
+                        client_kind_opcodes: Default::default(),
+                        handshake_timeout: None,
+                        read_timeout: None,
+                        key_timeout: None,
+                        require_authentication: false,
+                        decode_failure_policy: None,
+                        size_field: Default::default(),
+                        send_disconnect_packets: false,
+                        allow_resumption: false,
                     },
                     retry_delay: Duration::from_millis(15000),
+                    backlog: 1024,
                 },
                 ServerSettings {
                     port: 7172,
@@ -69,10 +168,32 @@ impl Default for NetworkSettings {
                         encryption: Default::default(),
                         channel_capacity: 1024,
                         max_buffer_size: 4096,
+                        flush_threshold: 0,
                         max_connections: 100,
                         rate_burst: 50,
+                        accept_queue_capacity: 64,
+                        write_timeout: Duration::from_secs(5),
+                        write_retries: 3,
+                        allow_fragmentation: false,
+                        max_fragmented_size: 1 << 20,
+                        packet_size_limits: Default::default(),
+                        auto_keep_alive: false,
+                        flush_policy: Default::default(),
+                        max_pending_packets: 256,
+                        max_send_rate: 0,
+                        status_motd: None,
+                        client_kind_opcodes: Default::default(),
+                        handshake_timeout: None,
+                        read_timeout: None,
+                        key_timeout: None,
+                        require_authentication: false,
+                        decode_failure_policy: None,
+                        size_field: Default::default(),
+                        send_disconnect_packets: false,
+                        allow_resumption: false,
                     },
                     retry_delay: Duration::from_millis(15000),
+                    backlog: 1024,
                 },
                 ServerSettings {
                     port: 8080,
@@ -83,27 +204,158 @@ impl Default for NetworkSettings {
                         max_headers: 32,
                     },
                     retry_delay: Duration::from_millis(15000),
+                    backlog: 1024,
                 },
             ],
         }
     }
 }
 
+/// The runtime [`NetworkPlugin`](crate::NetworkPlugin) would build from
+/// the default settings, for tests that spawn servers or connections
+/// without an `App`.
+#[cfg(test)]
+pub(crate) fn test_runtime() -> std::sync::Arc<tokio::runtime::Runtime> {
+    std::sync::Arc::new(
+        NetworkSettings::default()
+            .build_runtime()
+            .expect("failed to build test runtime"),
+    )
+}
+
+fn default_max_blocking_threads() -> usize {
+    512
+}
+
+/// Fluent construction of [`NetworkSettings`] in code, for embedding the
+/// server or for tests that need specific ports and timeouts without a
+/// settings file.
+///
+/// Starts from the default runtime and buffer pool sizes with no servers;
+/// add them with [`server`](Self::server) or
+/// [`tcp_server`](Self::tcp_server).
+#[derive(Debug, Clone)]
+pub struct NetworkSettingsBuilder {
+    settings: NetworkSettings,
+}
+
+impl NetworkSettingsBuilder {
+    pub fn worker_threads(mut self, threads: usize) -> Self {
+        self.settings.worker_threads = threads;
+        self
+    }
+
+    pub fn max_blocking_threads(mut self, threads: usize) -> Self {
+        self.settings.max_blocking_threads = threads;
+        self
+    }
+
+    pub fn buffer_pool(mut self, buffer_pool: BufferPoolSettings) -> Self {
+        self.settings.buffer_pool = buffer_pool;
+        self
+    }
+
+    pub fn packet_tap_capacity(mut self, capacity: usize) -> Self {
+        self.settings.packet_tap_capacity = capacity;
+        self
+    }
+
+    pub fn max_total_buffer_bytes(mut self, bytes: usize) -> Self {
+        self.settings.max_total_buffer_bytes = bytes;
+        self
+    }
+
+    /// Adds a server of any kind.
+    pub fn server(mut self, server: ServerSettings) -> Self {
+        self.settings.server.push(server);
+        self
+    }
+
+    /// Adds a TCP server on `port`, listening on every interface.
+    pub fn tcp_server(self, port: u16, settings: TcpSettings) -> Self {
+        self.server(ServerSettings {
+            port,
+            address: "0.0.0.0".into(),
+            kind: settings.into(),
+            retry_delay: Duration::from_millis(15000),
+            backlog: 1024,
+        })
+    }
+
+    /// The settings, checked with [`NetworkSettings::validate`].
+    pub fn build(self) -> Result<NetworkSettings, SettingsError> {
+        self.settings.validate()?;
+        Ok(self.settings)
+    }
+}
+
 impl NetworkSettings {
+    /// A [`NetworkSettingsBuilder`] with no servers.
+    pub fn builder() -> NetworkSettingsBuilder {
+        NetworkSettingsBuilder {
+            settings: NetworkSettings {
+                server: Vec::new(),
+                ..NetworkSettings::default()
+            },
+        }
+    }
+
+    /// Builds the network runtime with the configured pool sizes.
+    pub fn build_runtime(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(self.worker_threads)
+            .max_blocking_threads(self.max_blocking_threads)
+            .enable_io()
+            .enable_time()
+            .build()
+    }
+
     fn read(path: &Path) -> Result<Self, SettingsError> {
+        let format = SettingsFormat::from_path(path)?;
         let content = std::fs::read_to_string(path)?;
-        let settings: NetworkSettings = toml::from_str(&content)?;
+        let settings = format.parse(&content)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Checks invariants that parse fine but would break the servers at
+    /// runtime (zero intervals, capacities or limits, clashing ports).
+    /// A `max_connections` of 0 is allowed and makes the server reject
+    /// every connection, and a port of 0 binds an ephemeral port that
+    /// [`NetworkManager::listen_address`](crate::manager::NetworkManager::listen_address)
+    /// reports once the server is up.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.worker_threads == 0 {
+            return Err(SettingsError::Validation(
+                "worker_threads must not be 0".into(),
+            ));
+        }
 
-        for server_settings in &settings.server {
-            if server_settings.port == 0 {
-                return Err(SettingsError::Validation(
-                    "server port must not be 0".into(),
-                ));
+        if self.max_blocking_threads == 0 {
+            return Err(SettingsError::Validation(
+                "max_blocking_threads must not be 0".into(),
+            ));
+        }
+
+        if self.buffer_pool.buffer_size == 0 {
+            return Err(SettingsError::Validation(
+                "buffer_pool.buffer_size must not be 0".into(),
+            ));
+        }
+
+        for server_settings in &self.server {
+            if let Some(field) = zero_field(&server_settings.kind) {
+                return Err(SettingsError::Validation(format!(
+                    "{} server on port {}: {field} must not be 0",
+                    server_settings.kind.as_str(),
+                    server_settings.port
+                )));
             }
         }
 
+        // Port 0 asks the OS for an ephemeral port, so those never clash.
         let mut ports = std::collections::HashSet::new();
-        for server_settings in &settings.server {
+        for server_settings in self.server.iter().filter(|server| server.port != 0) {
             if !ports.insert((server_settings.port, server_settings.kind.clone())) {
                 return Err(SettingsError::Validation(format!(
                     "duplicate {} server on port {}",
@@ -113,11 +365,11 @@ impl NetworkSettings {
             }
         }
 
-        Ok(settings)
+        Ok(())
     }
 
     fn write(&self, path: &Path) -> Result<(), SettingsError> {
-        let content = toml::to_string(self)?;
+        let content = SettingsFormat::from_path(path)?.render(self)?;
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
@@ -126,10 +378,18 @@ impl NetworkSettings {
     }
 
     pub fn load() -> Self {
-        let path = Path::new(FILE);
-        info!(target: "Settings", "Loading network settings from {FILE}");
+        let existing = SettingsFormat::ALL
+            .into_iter()
+            .map(|format| PathBuf::from(format!("{FILE_STEM}.{}", format.extension())))
+            .find(|path| path.exists());
+
+        let path = existing.unwrap_or_else(|| {
+            PathBuf::from(format!("{FILE_STEM}.{}", SettingsFormat::Toml.extension()))
+        });
+        let file = path.display();
+        info!(target: "Settings", "Loading network settings from {file}");
 
-        match Self::read(path) {
+        match Self::read(&path) {
             Ok(settings) => settings,
             Err(err) => {
                 let not_found = matches!(
@@ -140,20 +400,56 @@ impl NetworkSettings {
 
                 if not_found {
                     let settings = NetworkSettings::default();
-                    settings.write(path).unwrap_or_else(|write_err| {
+                    settings.write(&path).unwrap_or_else(|write_err| {
                         error!(target: "Settings", "Failed to write default settings: {write_err}");
                         panic!("Failed to write default settings: {write_err}")
                     });
                     settings
                 } else {
-                    error!(target: "Settings", "Failed to load settings from {FILE}: {err}");
-                    panic!("Failed to load settings from {FILE}: {err}");
+                    error!(target: "Settings", "Failed to load settings from {file}: {err}");
+                    panic!("Failed to load settings from {file}: {err}");
                 }
             }
         }
     }
 }
 
+/// Name of the first setting of `kind` that must be non-zero but is zero.
+fn zero_field(kind: &ServerKind) -> Option<&'static str> {
+    match kind {
+        ServerKind::Tcp {
+            flush_interval,
+            channel_capacity,
+            max_buffer_size,
+            rate_burst,
+            accept_queue_capacity,
+            write_timeout,
+            max_pending_packets,
+            ..
+        } => [
+            ("flush_interval_ms", flush_interval.is_zero()),
+            ("channel_capacity", *channel_capacity == 0),
+            ("max_buffer_size", *max_buffer_size == 0),
+            ("rate_burst", *rate_burst == 0),
+            ("accept_queue_capacity", *accept_queue_capacity == 0),
+            ("write_timeout_ms", write_timeout.is_zero()),
+            ("max_pending_packets", *max_pending_packets == 0),
+        ]
+        .into_iter()
+        .find_map(|(field, zero)| zero.then_some(field)),
+        ServerKind::Http {
+            rate_burst,
+            max_headers,
+            ..
+        } => [
+            ("rate_burst", *rate_burst == 0),
+            ("max_headers", *max_headers == 0),
+        ]
+        .into_iter()
+        .find_map(|(field, zero)| zero.then_some(field)),
+    }
+}
+
 impl std::fmt::Display for NetworkSettings {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let servers: Vec<String> = self
@@ -182,6 +478,53 @@ impl std::fmt::Display for NetworkSettings {
 mod tests {
     use super::*;
 
+    #[test]
+    fn builder_carries_custom_settings() {
+        let settings = NetworkSettings::builder()
+            .worker_threads(1)
+            .max_total_buffer_bytes(1 << 20)
+            .tcp_server(
+                17172,
+                TcpSettings {
+                    read_timeout: Some(Duration::from_millis(200)),
+                    max_connections: 4,
+                    ..TcpSettings::default()
+                },
+            )
+            .build()
+            .expect("custom settings should be valid");
+
+        assert_eq!(settings.worker_threads, 1);
+        assert_eq!(settings.max_total_buffer_bytes, 1 << 20);
+        assert_eq!(settings.server.len(), 1);
+
+        let tcp = TcpSettings::from_settings(&settings.server[0]);
+        assert_eq!(settings.server[0].port, 17172);
+        assert_eq!(tcp.read_timeout, Some(Duration::from_millis(200)));
+        assert_eq!(tcp.max_connections, 4);
+    }
+
+    #[test]
+    fn builder_validates_what_it_builds() {
+        let result = NetworkSettings::builder()
+            .tcp_server(17173, TcpSettings::default())
+            .tcp_server(17173, TcpSettings::default())
+            .build();
+
+        assert!(matches!(result, Err(SettingsError::Validation(_))));
+    }
+
+    #[test]
+    fn test_runtime_runs_spawned_tasks_without_an_app() {
+        let runtime = test_runtime();
+        let task = runtime.spawn(async {
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            7
+        });
+
+        assert_eq!(runtime.block_on(task).expect("task should not panic"), 7);
+    }
+
     #[test]
     fn network_settings_default() {
         let settings = NetworkSettings::default();
@@ -218,6 +561,22 @@ mod tests {
         assert!(matches!(result, Err(SettingsError::Io(_))));
     }
 
+    #[test]
+    fn settings_error_keeps_the_underlying_error_as_its_source() {
+        use std::error::Error;
+
+        let path = std::env::temp_dir().join("suon_test_settings_missing_source.toml");
+        let err = NetworkSettings::read(&path).expect_err("a missing file should fail");
+
+        let source = err
+            .source()
+            .and_then(|source| source.downcast_ref::<std::io::Error>())
+            .expect("an IO failure should expose the io::Error");
+        assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(err.to_string(), source.to_string());
+        assert!(SettingsError::Validation("bad".into()).source().is_none());
+    }
+
     #[test]
     fn network_settings_read_invalid_toml() {
         let dir = std::env::temp_dir().join("suon_test_settings_invalid");
@@ -234,6 +593,289 @@ mod tests {
         std::fs::remove_file(&path).expect("failed to remove settings file after test");
     }
 
+    #[test]
+    fn network_settings_toml_roundtrips_packet_size_limits() {
+        let mut settings = NetworkSettings::default();
+        if let ServerKind::Tcp {
+            packet_size_limits, ..
+        } = tcp_kind(&mut settings)
+        {
+            packet_size_limits.insert(0x1E, 1);
+        }
+
+        let rendered = toml::to_string(&settings).expect("failed to render settings");
+        let parsed: NetworkSettings = toml::from_str(&rendered).expect("failed to parse settings");
+
+        assert_eq!(parsed.server[0].kind, settings.server[0].kind);
+    }
+
+    fn tcp_kind(settings: &mut NetworkSettings) -> &mut ServerKind {
+        &mut settings.server[0].kind
+    }
+
+    #[test]
+    fn network_settings_default_is_valid() {
+        NetworkSettings::default()
+            .validate()
+            .expect("default settings should pass validation");
+    }
+
+    #[test]
+    fn network_settings_validate_rejects_zero_workers() {
+        let settings = NetworkSettings {
+            worker_threads: 0,
+            ..NetworkSettings::default()
+        };
+
+        let err = settings
+            .validate()
+            .expect_err("zero worker threads should be rejected");
+        assert!(err.to_string().contains("worker_threads"));
+    }
+
+    #[test]
+    fn network_settings_validate_rejects_zero_blocking_threads() {
+        let settings = NetworkSettings {
+            max_blocking_threads: 0,
+            ..NetworkSettings::default()
+        };
+
+        let err = settings
+            .validate()
+            .expect_err("zero blocking threads should be rejected");
+        assert!(err.to_string().contains("max_blocking_threads"));
+    }
+
+    #[test]
+    fn network_settings_pool_sizes_reach_the_runtime() {
+        let settings: NetworkSettings = toml::from_str(
+            &toml::to_string(&NetworkSettings {
+                worker_threads: 3,
+                max_blocking_threads: 4,
+                ..NetworkSettings::default()
+            })
+            .expect("failed to render settings"),
+        )
+        .expect("failed to parse settings");
+        assert_eq!(settings.max_blocking_threads, 4);
+
+        let runtime = settings
+            .build_runtime()
+            .expect("failed to build runtime from settings");
+        assert_eq!(runtime.metrics().num_workers(), 3);
+    }
+
+    #[test]
+    fn network_settings_validate_rejects_zero_flush_interval() {
+        let mut settings = NetworkSettings::default();
+        if let ServerKind::Tcp { flush_interval, .. } = tcp_kind(&mut settings) {
+            *flush_interval = Duration::ZERO;
+        }
+
+        let err = settings
+            .validate()
+            .expect_err("zero flush interval should be rejected");
+        assert_eq!(
+            err.to_string(),
+            "tcp server on port 7171: flush_interval_ms must not be 0"
+        );
+    }
+
+    #[test]
+    fn network_settings_validate_rejects_zero_accept_queue() {
+        let mut settings = NetworkSettings::default();
+        if let ServerKind::Tcp {
+            accept_queue_capacity,
+            ..
+        } = tcp_kind(&mut settings)
+        {
+            *accept_queue_capacity = 0;
+        }
+
+        let err = settings
+            .validate()
+            .expect_err("zero accept queue capacity should be rejected");
+        assert!(err.to_string().contains("accept_queue_capacity"));
+    }
+
+    #[test]
+    fn network_settings_validate_accepts_zero_max_connections() {
+        let mut settings = NetworkSettings::default();
+        if let ServerKind::Tcp {
+            max_connections, ..
+        } = tcp_kind(&mut settings)
+        {
+            *max_connections = 0;
+        }
+
+        settings
+            .validate()
+            .expect("a server that rejects every connection is valid");
+    }
+
+    #[test]
+    fn network_settings_validate_rejects_zero_http_headers() {
+        let mut settings = NetworkSettings::default();
+        settings.server[2].kind = ServerKind::Http {
+            max_connections: 100,
+            rate_burst: 50,
+            max_headers: 0,
+        };
+
+        let err = settings
+            .validate()
+            .expect_err("zero max_headers should be rejected");
+        assert_eq!(
+            err.to_string(),
+            "http server on port 8080: max_headers must not be 0"
+        );
+    }
+
+    #[test]
+    fn network_settings_validate_rejects_duplicate_ports() {
+        let mut settings = NetworkSettings::default();
+        settings.server[1].port = settings.server[0].port;
+        settings.server[1].kind = settings.server[0].kind.clone();
+
+        assert!(matches!(
+            settings.validate(),
+            Err(SettingsError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn network_settings_validate_accepts_port_zero() {
+        let mut settings = NetworkSettings::default();
+        settings.server[0].port = 0;
+
+        settings.validate().expect("port 0 should be accepted");
+    }
+
+    #[test]
+    fn network_settings_read_rejects_invalid_values() {
+        let dir = std::env::temp_dir().join("suon_test_settings_zero_workers");
+        let path = dir.join("NetworkSettings.toml");
+
+        NetworkSettings {
+            worker_threads: 0,
+            ..NetworkSettings::default()
+        }
+        .write(&path)
+        .expect("failed to write settings to temp file");
+
+        let result = NetworkSettings::read(&path);
+        assert!(matches!(result, Err(SettingsError::Validation(_))));
+
+        std::fs::remove_file(&path).expect("failed to remove settings file after test");
+    }
+
+    fn roundtrip_in_format(extension: &str) {
+        let settings = NetworkSettings::default();
+        let dir = std::env::temp_dir().join(format!("suon_test_settings_{extension}"));
+        let path = dir.join(format!("NetworkSettings.{extension}"));
+
+        settings
+            .write(&path)
+            .expect("failed to write default settings to temp file");
+
+        let loaded = NetworkSettings::read(&path).expect("failed to read settings from temp file");
+        assert_eq!(loaded.to_string(), settings.to_string());
+        assert_eq!(
+            format!("{:?}", loaded.server[0].kind),
+            format!("{:?}", settings.server[0].kind)
+        );
+
+        std::fs::remove_file(&path).expect("failed to remove settings file after test");
+    }
+
+    #[test]
+    fn network_settings_json_roundtrip() {
+        roundtrip_in_format("json");
+    }
+
+    #[test]
+    fn network_settings_ron_roundtrip() {
+        roundtrip_in_format("ron");
+    }
+
+    #[test]
+    fn network_settings_formats_load_the_same_settings() {
+        let dir = std::env::temp_dir().join("suon_test_settings_formats");
+        std::fs::create_dir_all(&dir).expect("failed to create temp directory for test");
+
+        let toml = r#"
+            worker_threads = 4
+
+            [buffer_pool]
+            buffer_size = 2048
+            prealloc = 8
+
+            [[server]]
+            port = 7171
+            address = "127.0.0.1"
+            type = "http"
+            max_connections = 10
+            rate_burst = 5
+            max_headers = 16
+            retry_delay_ms = 1000
+        "#;
+        let json = r#"{
+            "worker_threads": 4,
+            "buffer_pool": { "buffer_size": 2048, "prealloc": 8 },
+            "server": [{
+                "port": 7171,
+                "address": "127.0.0.1",
+                "type": "http",
+                "max_connections": 10,
+                "rate_burst": 5,
+                "max_headers": 16,
+                "retry_delay_ms": 1000
+            }]
+        }"#;
+        let ron = r#"(
+            worker_threads: 4,
+            buffer_pool: (buffer_size: 2048, prealloc: 8),
+            server: [{
+                "port": 7171,
+                "address": "127.0.0.1",
+                "type": "http",
+                "max_connections": 10,
+                "rate_burst": 5,
+                "max_headers": 16,
+                "retry_delay_ms": 1000,
+            }],
+        )"#;
+
+        for (extension, content) in [("toml", toml), ("json", json), ("ron", ron)] {
+            let path = dir.join(format!("NetworkSettings.{extension}"));
+            std::fs::write(&path, content).expect("failed to write settings to temp file");
+
+            let settings = NetworkSettings::read(&path)
+                .unwrap_or_else(|err| panic!("failed to read {extension} settings: {err}"));
+            assert_eq!(settings.worker_threads, 4, "{extension}");
+            assert_eq!(settings.buffer_pool.buffer_size, 2048, "{extension}");
+            assert_eq!(
+                settings.max_blocking_threads,
+                default_max_blocking_threads(),
+                "{extension}"
+            );
+            assert_eq!(
+                settings.to_string(),
+                "workers=4 server=[127.0.0.1:7171/http]",
+                "{extension}"
+            );
+
+            std::fs::remove_file(&path).expect("failed to remove settings file after test");
+        }
+    }
+
+    #[test]
+    fn network_settings_read_unsupported_extension() {
+        let path = std::env::temp_dir().join("NetworkSettings.yaml");
+        let result = NetworkSettings::read(&path);
+        assert!(matches!(result, Err(SettingsError::UnsupportedFormat(_))));
+    }
+
     #[test]
     fn network_settings_display_contains_servers() {
         let settings = NetworkSettings::default();
@@ -0,0 +1,558 @@
+//! Roundtrip checks between the Lua `OutgoingMessage` encoder and the
+//! `IncomingMessage` decoder shipped in `modules/network`.
+//!
+//! Both sides are written independently, so every primitive is encoded
+//! with random values and decoded back to catch width, sign and
+//! endianness mismatches. A roundtrip cannot tell if both sides flip
+//! together, so the wire layout of each primitive is also pinned to
+//! exact little-endian bytes.
+
+use mlua::{Function, Lua, Value};
+
+const OUTGOING_MSG: &str = include_str!("../../../modules/network/outgoing_msg.lua");
+const INCOMING_MSG: &str = include_str!("../../../modules/network/incoming_msg.lua");
+
+const ITERATIONS: usize = 512;
+
+/// Minimal xorshift generator so runs are reproducible without extra
+/// dev-dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn string(&mut self) -> String {
+        const CHARS: &[char] = &['a', 'Z', '0', ' ', '\0', 'é', 'ß', 'Ж', '中', '🗡'];
+
+        let len = (self.next() % 64) as usize;
+        (0..len)
+            .map(|_| CHARS[(self.next() % CHARS.len() as u64) as usize])
+            .collect()
+    }
+}
+
+/// Loads `outgoing_msg.lua` into `lua` and returns the `OutgoingMessage`
+/// class.
+fn outgoing(lua: &Lua) -> Value {
+    lua.load(OUTGOING_MSG)
+        .set_name("network.outgoing_msg")
+        .eval()
+        .expect("outgoing_msg.lua should load")
+}
+
+/// Loads `incoming_msg.lua` into `lua` and returns the `IncomingMessage`
+/// class.
+fn incoming(lua: &Lua) -> Value {
+    lua.load(INCOMING_MSG)
+        .set_name("network.incoming_msg")
+        .eval()
+        .expect("incoming_msg.lua should load")
+}
+
+/// Loads both message modules and returns a Lua function that encodes a
+/// value with `put`, decodes it with `get` and returns the decoded value,
+/// whether the decoder consumed the whole buffer, and the encoded bytes.
+fn roundtrip(lua: &Lua) -> Function {
+    lua.load(
+        r#"
+        local Outgoing, Incoming = ...
+        return function(put, get, value)
+            local out = Outgoing()
+            out[put](out, value)
+
+            local msg = Incoming(out:getBuffer())
+            local decoded = msg[get](msg)
+            return decoded, msg:eof(), out:getBuffer()
+        end
+        "#,
+    )
+    .call((outgoing(lua), incoming(lua)))
+    .expect("roundtrip helper should load")
+}
+
+fn check_integers(put: &str, get: &str, values: impl Iterator<Item = i64>) {
+    let lua = Lua::new();
+    let roundtrip = roundtrip(&lua);
+
+    for value in values {
+        let (decoded, eof): (i64, bool) = roundtrip
+            .call((put, get, value))
+            .expect("integer roundtrip should not raise");
+
+        assert_eq!(decoded, value, "{put}/{get} mismatch");
+        assert!(eof, "{get} left unread bytes for {value}");
+    }
+}
+
+fn random_values(seed: u64, min: i64, max: i64) -> impl Iterator<Item = i64> {
+    let mut rng = Rng(seed);
+    let span = (max - min) as u64 + 1;
+
+    [min, max, 0.clamp(min, max)]
+        .into_iter()
+        .chain((0..ITERATIONS).map(move |_| min + (rng.next() % span) as i64))
+}
+
+#[test]
+fn u8_roundtrip() {
+    check_integers("addU8", "getU8", random_values(1, 0, u8::MAX.into()));
+}
+
+#[test]
+fn i8_roundtrip() {
+    check_integers(
+        "addI8",
+        "getI8",
+        random_values(2, i8::MIN.into(), i8::MAX.into()),
+    );
+}
+
+#[test]
+fn u16_roundtrip() {
+    check_integers("addU16", "getU16", random_values(3, 0, u16::MAX.into()));
+}
+
+#[test]
+fn i16_roundtrip() {
+    check_integers(
+        "addI16",
+        "getI16",
+        random_values(4, i16::MIN.into(), i16::MAX.into()),
+    );
+}
+
+#[test]
+fn u32_roundtrip() {
+    check_integers("addU32", "getU32", random_values(5, 0, u32::MAX.into()));
+}
+
+#[test]
+fn i32_roundtrip() {
+    check_integers(
+        "addI32",
+        "getI32",
+        random_values(6, i32::MIN.into(), i32::MAX.into()),
+    );
+}
+
+/// Asserts that `value` is written as exactly `bytes` and reads back as
+/// `value`.
+fn check_layout(put: &str, get: &str, value: i64, bytes: &[u8]) {
+    let lua = Lua::new();
+    let (decoded, eof, encoded): (i64, bool, mlua::String) = roundtrip(&lua)
+        .call((put, get, value))
+        .expect("layout roundtrip should not raise");
+
+    assert_eq!(
+        encoded.as_bytes().as_ref(),
+        bytes,
+        "{put}({value:#x}) wire layout"
+    );
+    assert_eq!(decoded, value, "{get} of {bytes:02x?}");
+    assert!(eof, "{get} left unread bytes");
+}
+
+#[test]
+fn integers_are_little_endian_on_the_wire() {
+    check_layout("addU8", "getU8", 0xAB, &[0xAB]);
+    check_layout("addU16", "getU16", 0x0102, &[0x02, 0x01]);
+    check_layout("addU32", "getU32", 0x0102_0304, &[0x04, 0x03, 0x02, 0x01]);
+    check_layout(
+        "addU64",
+        "getU64",
+        0x0102_0304_0506_0708,
+        &[0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01],
+    );
+}
+
+#[test]
+fn signed_integers_are_little_endian_twos_complement() {
+    check_layout("addI8", "getI8", -2, &[0xFE]);
+    check_layout("addI16", "getI16", -0x0102, &[0xFE, 0xFE]);
+    check_layout("addI16", "getI16", 0x0102, &[0x02, 0x01]);
+    check_layout("addI32", "getI32", -0x0102_0304, &[0xFC, 0xFC, 0xFD, 0xFE]);
+    check_layout("addI32", "getI32", 0x0102_0304, &[0x04, 0x03, 0x02, 0x01]);
+    check_layout(
+        "addI64",
+        "getI64",
+        -0x0102_0304_0506_0708,
+        &[0xF8, 0xF8, 0xF9, 0xFA, 0xFB, 0xFC, 0xFD, 0xFE],
+    );
+}
+
+#[test]
+fn ports_are_little_endian_u16() {
+    check_layout("addPort", "getPort", 7172, &[0x04, 0x1C]);
+    check_layout("addPort", "getPort", 0xFFFF, &[0xFF, 0xFF]);
+}
+
+#[test]
+fn ipv4_octets_are_written_in_address_order() {
+    let lua = Lua::new();
+    let roundtrip = roundtrip(&lua);
+
+    for (address, bytes) in [
+        ("127.0.0.1", [0x7F, 0x00, 0x00, 0x01]),
+        ("192.168.1.20", [0xC0, 0xA8, 0x01, 0x14]),
+        ("255.255.255.255", [0xFF; 4]),
+    ] {
+        let (decoded, eof, encoded): (String, bool, mlua::String) = roundtrip
+            .call(("addIpv4", "getIpv4", address))
+            .expect("ipv4 roundtrip should not raise");
+
+        assert_eq!(encoded.as_bytes().as_ref(), bytes, "{address} wire layout");
+        assert_eq!(decoded, address);
+        assert!(eof);
+    }
+}
+
+#[test]
+fn add_ipv4_refuses_malformed_addresses() {
+    let lua = Lua::new();
+    let write: Function = lua
+        .load(
+            r#"
+            local Outgoing = ...
+            return function(address)
+                local out = Outgoing()
+                return out:addIpv4(address), out:getLength()
+            end
+            "#,
+        )
+        .call(outgoing(&lua))
+        .expect("ipv4 helper should load");
+
+    for address in ["256.0.0.1", "1.2.3", "1.2.3.4.5", "a.b.c.d", ""] {
+        let (written, length): (bool, i64) = write.call(address).expect("addIpv4 should not raise");
+
+        assert!(!written, "{address:?} should be refused");
+        assert_eq!(length, 0, "nothing may be written for {address:?}");
+    }
+}
+
+#[test]
+fn string_length_prefix_is_little_endian_u16() {
+    let lua = Lua::new();
+    let value = "x".repeat(0x0102);
+
+    let (decoded, eof, encoded): (String, bool, mlua::String) = roundtrip(&lua)
+        .call(("addString", "getString", value.as_str()))
+        .expect("string roundtrip should not raise");
+    let encoded = encoded.as_bytes();
+    assert_eq!(&encoded[..2], &[0x02, 0x01]);
+    assert_eq!(&encoded[2..], value.as_bytes());
+    assert_eq!(decoded, value);
+    assert!(eof);
+}
+
+#[test]
+fn bool_roundtrip() {
+    let lua = Lua::new();
+    let roundtrip = roundtrip(&lua);
+
+    for value in [true, false] {
+        let (decoded, eof): (bool, bool) = roundtrip
+            .call(("addBoolean", "getBoolean", value))
+            .expect("boolean roundtrip should not raise");
+
+        assert_eq!(decoded, value);
+        assert!(eof);
+    }
+}
+
+#[test]
+fn string_roundtrip_arbitrary_utf8() {
+    let lua = Lua::new();
+    let roundtrip = roundtrip(&lua);
+    let mut rng = Rng(7);
+
+    for _ in 0..ITERATIONS {
+        let value = rng.string();
+        let (decoded, eof): (mlua::String, bool) = roundtrip
+            .call(("addString", "getString", value.as_str()))
+            .expect("string roundtrip should not raise");
+
+        assert_eq!(decoded.as_bytes().as_ref(), value.as_bytes());
+        assert!(eof, "getString left unread bytes for {value:?}");
+    }
+}
+
+#[test]
+fn zero_length_string_reads_only_its_prefix() {
+    let lua = Lua::new();
+
+    let (plain, sanitized, reason, next, eof): (String, String, Option<String>, i64, bool) = lua
+        .load(
+            r#"
+            local Incoming = ...
+            local msg = Incoming("\0\0\0\0\42")
+            local plain = msg:getString()
+            local sanitized, reason = msg:getStringSanitized(30)
+            return plain, sanitized, reason, msg:getU8(), msg:eof()
+            "#,
+        )
+        .call(incoming(&lua))
+        .expect("zero-length string script should not raise");
+
+    assert_eq!(plain, "");
+    assert_eq!(sanitized, "");
+    assert_eq!(reason, None);
+    assert_eq!(next, 42, "the byte after the empty strings must be intact");
+    assert!(eof);
+}
+
+#[test]
+fn array_roundtrip_16_bytes() {
+    let lua = Lua::new();
+
+    let token: Vec<u8> = (0..16).map(|byte| byte * 17).collect();
+    let (written, rejected, decoded, eof, short): (bool, bool, mlua::String, bool, Value) = lua
+        .load(
+            r#"
+            local Outgoing, Incoming, token = ...
+            local out = Outgoing()
+            local written = out:addArray(token, 16)
+            local rejected = not out:addArray(token, 15)
+
+            local msg = Incoming(out:getBuffer())
+            local decoded = msg:getArray(16)
+            return written, rejected, decoded, msg:eof(), Incoming(token):getArray(17)
+            "#,
+        )
+        .call((
+            outgoing(&lua),
+            incoming(&lua),
+            lua.create_string(&token).expect("token string"),
+        ))
+        .expect("array roundtrip should not raise");
+
+    assert!(written);
+    assert!(rejected);
+    assert_eq!(decoded.as_bytes().as_ref(), token.as_slice());
+    assert!(eof);
+    assert!(short.is_nil());
+}
+
+/// Encodes `value` with `addString` and decodes it with
+/// `getStringSanitized(max)`, returning the string or the failure reason
+/// plus the read position afterwards.
+fn sanitized(value: &[u8], max: usize) -> (Result<Vec<u8>, String>, usize) {
+    let lua = Lua::new();
+
+    let (decoded, reason, position): (Option<mlua::String>, Option<String>, usize) = lua
+        .load(
+            r#"
+            local Outgoing, Incoming, value, max = ...
+            local out = Outgoing()
+            out:addString(value)
+
+            local msg = Incoming(out:getBuffer())
+            local decoded, reason = msg:getStringSanitized(max)
+            return decoded, reason, msg:getPosition()
+            "#,
+        )
+        .call((
+            outgoing(&lua),
+            incoming(&lua),
+            lua.create_string(value).expect("value string"),
+            max,
+        ))
+        .expect("sanitized string script should not raise");
+
+    let result = match (decoded, reason) {
+        (Some(decoded), None) => Ok(decoded.as_bytes().to_vec()),
+        (None, Some(reason)) => Err(reason),
+        other => panic!("unexpected result {other:?}"),
+    };
+    (result, position)
+}
+
+#[test]
+fn sanitized_string_accepts_plain_names() {
+    assert_eq!(
+        sanitized(b"Knight Ann", 30),
+        (Ok(b"Knight Ann".to_vec()), 13)
+    );
+    assert_eq!(
+        sanitized("Žofia".as_bytes(), 30).0,
+        Ok("Žofia".as_bytes().to_vec())
+    );
+}
+
+#[test]
+fn sanitized_string_rejects_interior_nul() {
+    assert_eq!(
+        sanitized(b"adm\0in", 30),
+        (Err("invalid_string".to_owned()), 1)
+    );
+    assert_eq!(
+        sanitized(b"new\nline", 30).0,
+        Err("invalid_string".to_owned())
+    );
+}
+
+#[test]
+fn sanitized_string_rejects_over_length() {
+    assert_eq!(sanitized(&[b'a'; 31], 30), (Err("too_long".to_owned()), 1));
+    assert_eq!(sanitized(&[b'a'; 30], 30).0, Ok(vec![b'a'; 30]));
+}
+
+#[test]
+fn reset_reuses_one_message_across_packets() {
+    let lua = Lua::new();
+
+    let (values, same_object, eof): (Vec<i64>, bool, bool) = lua
+        .load(
+            r#"
+            local Outgoing, Incoming = ...
+            local msg = Incoming("")
+            local first = msg
+            local values = {}
+
+            for x = 1, 3 do
+                local out = Outgoing()
+                out:addU16(x * 100)
+                out:addU16(x * 200)
+
+                msg:reset(out:getBuffer())
+                table.insert(values, msg:getU16())
+                table.insert(values, msg:getU16())
+            end
+
+            return values, rawequal(msg, first), msg:eof()
+            "#,
+        )
+        .call((outgoing(&lua), incoming(&lua)))
+        .expect("reset script should not raise");
+
+    assert_eq!(values, [100, 200, 200, 400, 300, 600]);
+    assert!(same_object);
+    assert!(eof);
+}
+
+#[test]
+fn patch_backfills_a_length_placeholder() {
+    let lua = Lua::new();
+
+    let (buffer, patched, out_of_bounds): (mlua::String, bool, bool) = lua
+        .load(
+            r#"
+            local Outgoing = ...
+            local out = Outgoing()
+            out:addU8(0x64)
+            local placeholder = out:getLength() + 1
+            out:addU16(0)
+            out:addRaw("body")
+
+            local length = out:getLength() - placeholder - 1
+            local patched = out:patch(placeholder, string.char(length & 0xFF, length >> 8))
+            local out_of_bounds = out:patch(out:getLength(), "xy")
+            return out:getBuffer(), patched, out_of_bounds
+            "#,
+        )
+        .call(outgoing(&lua))
+        .expect("patch script should not raise");
+
+    assert!(patched);
+    assert!(!out_of_bounds);
+    assert_eq!(buffer.as_bytes().as_ref(), b"\x64\x04\x00body");
+}
+
+#[test]
+fn optional_and_list_roundtrip() {
+    let lua = Lua::new();
+
+    type Decoded = (Option<u32>, Option<u32>, Vec<u16>, Vec<u16>, bool, Vec<u16>);
+    let (present, absent, list, empty, eof, truncated): Decoded = lua
+        .load(
+            r#"
+            local Outgoing, Incoming = ...
+            local out = Outgoing()
+            out:addOptional(0xDEADBEEF, out.addU32)
+            out:addOptional(nil, out.addU32)
+            out:addList({ 1, 500, 65535 }, out.addU16)
+            out:addList({}, out.addU16)
+
+            local msg = Incoming(out:getBuffer())
+            local present = msg:getOptional(msg.getU32)
+            local absent = msg:getOptional(msg.getU32)
+            local list = msg:getList(msg.getU16)
+            local empty = msg:getList(msg.getU16)
+
+            -- Claims 1000 items but carries a single one.
+            local truncated = Incoming("\xE8\x03\x07\x00"):getList(msg.getU16)
+            return present, absent, list, empty, msg:eof(), truncated
+            "#,
+        )
+        .call((outgoing(&lua), incoming(&lua)))
+        .expect("optional/list script should not raise");
+
+    assert_eq!(present, Some(0xDEAD_BEEF));
+    assert_eq!(absent, None);
+    assert_eq!(list, [1, 500, 65535]);
+    assert!(empty.is_empty());
+    assert!(eof);
+    assert_eq!(truncated, [7]);
+}
+
+#[test]
+fn decode_opcode_routes_raw_packets_by_their_leading_byte() {
+    let lua = Lua::new();
+
+    let (routed, empty): (Vec<String>, bool) = lua
+        .load(
+            r#"
+            local Outgoing, Incoming = ...
+            local handlers = {
+                [0x14] = function(msg) return "logout" end,
+                [0x96] = function(msg) return "say " .. msg:getString() end,
+            }
+
+            local say = Outgoing()
+            say:addU8(0x96)
+            say:addString("hello")
+
+            local routed = {}
+            for _, raw in ipairs({ say:getBuffer(), string.char(0x14) }) do
+                local opcode, msg = Incoming.decodeOpcode(raw)
+                table.insert(routed, handlers[opcode](msg))
+            end
+
+            return routed, Incoming.decodeOpcode("") == nil
+            "#,
+        )
+        .call((outgoing(&lua), incoming(&lua)))
+        .expect("dispatch script should not raise");
+
+    assert_eq!(routed, ["say hello", "logout"]);
+    assert!(empty);
+}
+
+#[test]
+fn add_string_refuses_strings_longer_than_its_prefix() {
+    let lua = Lua::new();
+
+    let (longest, too_long, length): (bool, bool, i64) = lua
+        .load(
+            r#"
+            local Outgoing = ...
+            local fits = Outgoing()
+            local longest = fits:addString(string.rep("a", 0xFFFF))
+
+            local out = Outgoing()
+            local too_long = out:addString(string.rep("a", 0x10000))
+            return longest, too_long, out:getLength()
+            "#,
+        )
+        .call(outgoing(&lua))
+        .expect("string script should not raise");
+
+    assert!(longest);
+    assert!(!too_long);
+    assert_eq!(length, 0, "nothing may be written for an oversized string");
+}
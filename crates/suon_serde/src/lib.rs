@@ -2,6 +2,7 @@
 //!
 //! Usage: `#[serde(with = "suon_serde::duration_ms")]`
 //! or `#[serde(with = "suon_serde::duration_ms::option")]`
+//! or `#[serde(with = "suon_serde::string_keys")]`
 
 /// Serialize/deserialize [`Duration`] as a `u64` count of milliseconds.
 pub mod duration_ms {
@@ -49,3 +50,41 @@ pub mod duration_ms {
         }
     }
 }
+
+/// Serialize/deserialize a [`BTreeMap`] with its keys written as strings.
+///
+/// Formats such as TOML only allow string keys, and internally tagged enums
+/// buffer keys as strings before handing them to the field deserializer.
+///
+/// [`BTreeMap`]: std::collections::BTreeMap
+pub mod string_keys {
+    use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+    pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Display,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_map(map.iter().map(|(key, value)| (key.to_string(), value)))
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        K: FromStr + Ord,
+        K::Err: Display,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        BTreeMap::<String, V>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| {
+                key.parse()
+                    .map(|key| (key, value))
+                    .map_err(|error| D::Error::custom(format!("invalid key {key:?}: {error}")))
+            })
+            .collect()
+    }
+}
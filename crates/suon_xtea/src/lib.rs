@@ -22,13 +22,35 @@
 //! The round keys can be precomputed once via [`expand()`] and reused across
 //! multiple [`encrypt()`] / [`decrypt()`] calls for the same key.
 //!
+//! # Single blocks
+//!
+//! [`encrypt_block()`] / [`decrypt_block()`] apply the cipher to one block
+//! given as two `u32` halves, with no byte order involved.
+//!
+//! # Chaining
+//!
+//! [`encrypt()`] / [`decrypt()`] treat blocks independently (ECB).
+//! [`encrypt_cbc()`] / [`decrypt_cbc()`] chain them from an 8-byte IV so
+//! identical plaintext blocks encrypt differently.
+//!
 //! # Errors
 //!
 //! Returns [`XteaError::InvalidDataLength`] if `data.len()` is not a multiple
 //! of 8 bytes.
+//!
+//! # Streams
+//!
+//! With the `tokio` feature, `XteaReader` and `XteaWriter` apply the
+//! cipher to an `AsyncRead` / `AsyncWrite` as bytes pass through.
 
 use std::fmt;
 
+#[cfg(feature = "tokio")]
+mod stream;
+
+#[cfg(feature = "tokio")]
+pub use stream::{XteaReader, XteaWriter};
+
 use tracing::trace;
 
 /// Golden ratio constant used to derive per-round key material.
@@ -48,11 +70,16 @@ const HALF_BLOCK: usize = BLOCK_SIZE / 2;
 /// A 128-bit XTEA key stored as four 32-bit little-endian words.
 pub type Key = [u32; 4];
 
+/// Number of round keys in an [`ExpandedKey`]: one per Feistel half-round.
+pub const EXPANDED_KEY_LEN: usize = ROUNDS * 2;
+
 /// Precomputed round keys for XTEA.
 ///
 /// Interleaved format: `[left_key_0, right_key_0, left_key_1, right_key_1, ...]`
 /// Produced by [`expand()`] and consumed by [`encrypt()`] / [`decrypt()`].
-pub type ExpandedKey = [u32; ROUNDS * 2];
+/// Being a plain array, a schedule can be cached, indexed and compared
+/// freely; it always holds [`EXPANDED_KEY_LEN`] words.
+pub type ExpandedKey = [u32; EXPANDED_KEY_LEN];
 
 /// Errors returned by XTEA operations.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -99,7 +126,7 @@ fn mix(v: u32) -> u32 {
 /// when the 128-bit key is known upfront.
 #[inline(always)]
 pub const fn expand(key: &Key) -> ExpandedKey {
-    let mut round_keys = [0u32; ROUNDS * 2];
+    let mut round_keys = [0u32; EXPANDED_KEY_LEN];
 
     // Running sum that advances by DELTA each round.
     // Used both to scramble the key selection (via `sum & 3` and `(sum >> 11) & 3`)
@@ -107,7 +134,7 @@ pub const fn expand(key: &Key) -> ExpandedKey {
     let mut sum = 0u32;
     let mut key_index = 0;
 
-    while key_index < ROUNDS * 2 {
+    while key_index < EXPANDED_KEY_LEN {
         // Left half key: select key word based on low 2 bits of sum.
         round_keys[key_index] = key[(sum & 3) as usize].wrapping_add(sum);
 
@@ -123,11 +150,60 @@ pub const fn expand(key: &Key) -> ExpandedKey {
     round_keys
 }
 
+/// Reads an 8-byte block as its two little-endian halves.
+#[inline(always)]
+fn read_block(block: &[u8]) -> [u32; 2] {
+    let left = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+    let right = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    [left, right]
+}
+
+/// Writes two halves back to an 8-byte block in little-endian order.
+#[inline(always)]
+fn write_block(block: &mut [u8], [left, right]: [u32; 2]) {
+    block[..HALF_BLOCK].copy_from_slice(&left.to_le_bytes());
+    block[HALF_BLOCK..BLOCK_SIZE].copy_from_slice(&right.to_le_bytes());
+}
+
+/// Encrypts a single 64-bit block, given as its two 32-bit halves.
+///
+/// This is the bare cipher with no byte-order convention attached, so
+/// it can be checked against published test vectors and used to build
+/// chaining modes. [`encrypt()`] reads each 8-byte block as two
+/// little-endian words before applying the same transform.
+#[inline]
+pub fn encrypt_block(block: [u32; 2], expanded: &ExpandedKey) -> [u32; 2] {
+    let [mut left, mut right] = block;
+
+    let mut key_index = 0;
+    while key_index < EXPANDED_KEY_LEN {
+        left = left.wrapping_add(mix(right) ^ expanded[key_index]);
+        right = right.wrapping_add(mix(left) ^ expanded[key_index + 1]);
+        key_index += 2;
+    }
+
+    [left, right]
+}
+
+/// Decrypts a single 64-bit block, the inverse of [`encrypt_block()`].
+#[inline]
+pub fn decrypt_block(block: [u32; 2], expanded: &ExpandedKey) -> [u32; 2] {
+    let [mut left, mut right] = block;
+
+    let mut key_index = EXPANDED_KEY_LEN;
+    while key_index > 0 {
+        key_index -= 2;
+        right = right.wrapping_sub(mix(left) ^ expanded[key_index + 1]);
+        left = left.wrapping_sub(mix(right) ^ expanded[key_index]);
+    }
+
+    [left, right]
+}
+
 /// Encrypts `data` in-place with XTEA using precomputed round keys.
 ///
-/// For each round, all data blocks are processed sequentially before moving
-/// to the next round. This keeps the round keys hot in cache and maximizes
-/// instruction-level parallelism across independent blocks.
+/// Each 8-byte block is read as two little-endian words and passed
+/// through [`encrypt_block()`] independently of its neighbours.
 ///
 /// # Errors
 ///
@@ -151,30 +227,9 @@ pub fn encrypt(data: &mut [u8], expanded: &ExpandedKey) -> Result<(), XteaError>
         data_len / BLOCK_SIZE
     );
 
-    // Iterate over the 32 round pairs (64 entries, step 2).
-    let mut key_index = 0;
-    while key_index < ROUNDS * 2 {
-        // Load the left and right round keys for this Feistel round.
-        let left_key = expanded[key_index];
-        let right_key = expanded[key_index + 1];
-
-        // Apply this round's transformation to every block in the buffer.
-        for block in data.chunks_exact_mut(BLOCK_SIZE) {
-            // Read the two 32-bit halves from the block (little-endian).
-            let left = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
-            let right = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
-
-            // Feistel round: scramble the opposite half, XOR with key material,
-            // then add (wrapping) to the current half.
-            let new_left = left.wrapping_add(mix(right) ^ left_key);
-            let new_right = right.wrapping_add(mix(new_left) ^ right_key);
-
-            // Write the updated halves back to the block (little-endian).
-            block[..HALF_BLOCK].copy_from_slice(&new_left.to_le_bytes());
-            block[HALF_BLOCK..BLOCK_SIZE].copy_from_slice(&new_right.to_le_bytes());
-        }
-
-        key_index += 2;
+    for block in data.chunks_exact_mut(BLOCK_SIZE) {
+        let halves = encrypt_block(read_block(block), expanded);
+        write_block(block, halves);
     }
 
     Ok(())
@@ -182,8 +237,8 @@ pub fn encrypt(data: &mut [u8], expanded: &ExpandedKey) -> Result<(), XteaError>
 
 /// Decrypts `data` in-place with XTEA using precomputed round keys.
 ///
-/// Processes rounds in reverse order, streaming through all blocks per round
-/// for optimal cache behavior and instruction-level parallelism.
+/// The inverse of [`encrypt()`]: every block goes through
+/// [`decrypt_block()`].
 ///
 /// # Errors
 ///
@@ -206,45 +261,68 @@ pub fn decrypt(data: &mut [u8], expanded: &ExpandedKey) -> Result<(), XteaError>
         data_len / BLOCK_SIZE
     );
 
-    // Empty data trivially decrypts to empty data.
-    if data_len == 0 {
-        return Ok(());
-    }
-
-    // Start from the last round pair and work backwards.
-    let mut key_index = ROUNDS * 2 - 1;
-    loop {
-        // Load the right and left round keys for this round.
-        // Note: right_key is loaded first because decrypt reverses the
-        // order of operations within each Feistel round.
-        let right_key = expanded[key_index];
-        let left_key = expanded[key_index - 1];
-
-        // Apply this round's inverse transformation to every block.
-        for block in data.chunks_exact_mut(BLOCK_SIZE) {
-            // Read the two 32-bit halves from the block (little-endian).
-            let left = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
-            let right = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
-
-            // Inverse Feistel round: subtract (wrapping) instead of adding.
-            // The right key is applied first (mirroring the encrypt order).
-            let new_right = right.wrapping_sub(mix(left) ^ right_key);
-            let new_left = left.wrapping_sub(mix(new_right) ^ left_key);
-
-            // Write the decrypted halves back to the block (little-endian).
-            block[..HALF_BLOCK].copy_from_slice(&new_left.to_le_bytes());
-            block[HALF_BLOCK..BLOCK_SIZE].copy_from_slice(&new_right.to_le_bytes());
-        }
+    for block in data.chunks_exact_mut(BLOCK_SIZE) {
+        let halves = decrypt_block(read_block(block), expanded);
+        write_block(block, halves);
+    }
 
-        // Stop after processing the first round pair (indices 0 and 1).
-        if key_index == 1 {
-            break;
-        }
+    trace!(target: "Xtea", "XTEA decrypt done");
+    Ok(())
+}
 
-        key_index -= 2;
+/// Encrypts `data` in-place in CBC mode, starting the chain from `iv`.
+///
+/// Each plaintext block is XORed with the previous ciphertext block (the
+/// IV for the first one) before [`encrypt_block()`], so repeated
+/// plaintext blocks no longer show up as repeated ciphertext. Blocks are
+/// read as little-endian words like [`encrypt()`]. As there, padding the
+/// final block is up to the caller.
+///
+/// # Errors
+///
+/// Returns [`XteaError::InvalidDataLength`] if `data.len()` is not a multiple of 8.
+pub fn encrypt_cbc(
+    data: &mut [u8],
+    expanded: &ExpandedKey,
+    iv: [u8; BLOCK_SIZE],
+) -> Result<(), XteaError> {
+    if !data.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(XteaError::InvalidDataLength(data.len()));
+    }
+
+    let mut previous = read_block(&iv);
+    for block in data.chunks_exact_mut(BLOCK_SIZE) {
+        let [left, right] = read_block(block);
+        previous = encrypt_block([left ^ previous[0], right ^ previous[1]], expanded);
+        write_block(block, previous);
+    }
+
+    Ok(())
+}
+
+/// Decrypts `data` in-place in CBC mode, the inverse of [`encrypt_cbc()`]
+/// with the same `iv`.
+///
+/// # Errors
+///
+/// Returns [`XteaError::InvalidDataLength`] if `data.len()` is not a multiple of 8.
+pub fn decrypt_cbc(
+    data: &mut [u8],
+    expanded: &ExpandedKey,
+    iv: [u8; BLOCK_SIZE],
+) -> Result<(), XteaError> {
+    if !data.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(XteaError::InvalidDataLength(data.len()));
+    }
+
+    let mut previous = read_block(&iv);
+    for block in data.chunks_exact_mut(BLOCK_SIZE) {
+        let ciphertext = read_block(block);
+        let [left, right] = decrypt_block(ciphertext, expanded);
+        write_block(block, [left ^ previous[0], right ^ previous[1]]);
+        previous = ciphertext;
     }
 
-    trace!(target: "Xtea", "XTEA decrypt done");
     Ok(())
 }
 
@@ -398,6 +476,144 @@ mod tests {
         );
     }
 
+    /// Published XTEA vectors (32 cycles), with keys and blocks written as
+    /// big-endian words: `(key, plaintext, ciphertext)`.
+    const BLOCK_VECTORS: [(Key, [u32; 2], [u32; 2]); 6] = [
+        (
+            [0x0001_0203, 0x0405_0607, 0x0809_0A0B, 0x0C0D_0E0F],
+            [0x4142_4344, 0x4546_4748],
+            [0x497D_F3D0, 0x7261_2CB5],
+        ),
+        (
+            [0x0001_0203, 0x0405_0607, 0x0809_0A0B, 0x0C0D_0E0F],
+            [0x4141_4141, 0x4141_4141],
+            [0xE78F_2D13, 0x7443_41D8],
+        ),
+        (
+            [0x0001_0203, 0x0405_0607, 0x0809_0A0B, 0x0C0D_0E0F],
+            [0x5A5B_6E27, 0x8948_D77F],
+            [0x4141_4141, 0x4141_4141],
+        ),
+        (
+            [0; 4],
+            [0x4142_4344, 0x4546_4748],
+            [0xA039_0589, 0xF8B8_EFA5],
+        ),
+        (
+            [0; 4],
+            [0x4141_4141, 0x4141_4141],
+            [0xED23_375A, 0x821A_8C2D],
+        ),
+        (
+            [0; 4],
+            [0x70E1_225D, 0x6E4E_7655],
+            [0x4141_4141, 0x4141_4141],
+        ),
+    ];
+
+    #[test]
+    fn block_functions_match_published_vectors() {
+        for (key, plaintext, ciphertext) in BLOCK_VECTORS {
+            let expanded_keys = expand(&key);
+            assert_eq!(
+                encrypt_block(plaintext, &expanded_keys),
+                ciphertext,
+                "encrypt mismatch for key {key:08x?}"
+            );
+            assert_eq!(
+                decrypt_block(ciphertext, &expanded_keys),
+                plaintext,
+                "decrypt mismatch for key {key:08x?}"
+            );
+        }
+    }
+
+    #[test]
+    fn decrypt_block_inverts_encrypt_block() {
+        let expanded_keys = expand(&[0x0123_4567, 0x89AB_CDEF, 0xFEDC_BA98, 0x7654_3210]);
+        for block in [
+            [0, 0],
+            [1, 0],
+            [0, 1],
+            [u32::MAX, u32::MAX],
+            [0xDEAD_BEEF, 0x0BAD_F00D],
+        ] {
+            let ciphertext = encrypt_block(block, &expanded_keys);
+            assert_ne!(ciphertext, block);
+            assert_eq!(decrypt_block(ciphertext, &expanded_keys), block);
+        }
+    }
+
+    #[test]
+    fn buffer_encrypt_matches_block_on_little_endian_words() {
+        for (key, plaintext, ciphertext) in BLOCK_VECTORS {
+            let expanded_keys = expand(&key);
+            let mut buffer = [plaintext[0].to_le_bytes(), plaintext[1].to_le_bytes()].concat();
+            encrypt(&mut buffer, &expanded_keys).expect("encrypt should succeed for one block");
+
+            let expected = [ciphertext[0].to_le_bytes(), ciphertext[1].to_le_bytes()].concat();
+            assert_eq!(buffer, expected, "buffer mismatch for key {key:08x?}");
+        }
+    }
+
+    const CBC_IV: [u8; 8] = [0x10, 0x32, 0x54, 0x76, 0x98, 0xBA, 0xDC, 0xFE];
+
+    #[test]
+    fn cbc_roundtrip() {
+        let expanded_keys = expand(&[0x0123_4567, 0x89AB_CDEF, 0xFEDC_BA98, 0x7654_3210]);
+        let plaintext: Vec<u8> = (0..40).collect();
+        let mut buffer = plaintext.clone();
+
+        encrypt_cbc(&mut buffer, &expanded_keys, CBC_IV).expect("CBC encrypt should succeed");
+        assert_ne!(buffer, plaintext);
+
+        decrypt_cbc(&mut buffer, &expanded_keys, CBC_IV).expect("CBC decrypt should succeed");
+        assert_eq!(buffer, plaintext);
+    }
+
+    #[test]
+    fn cbc_hides_repeated_plaintext_blocks() {
+        let expanded_keys = expand(&[1, 2, 3, 4]);
+
+        let mut ecb = vec![0xABu8; 24];
+        encrypt(&mut ecb, &expanded_keys).expect("encrypt should succeed");
+        assert_eq!(ecb[..8], ecb[8..16], "ECB repeats identical blocks");
+
+        let mut cbc = vec![0xABu8; 24];
+        encrypt_cbc(&mut cbc, &expanded_keys, CBC_IV).expect("CBC encrypt should succeed");
+        assert_ne!(cbc[..8], cbc[8..16]);
+        assert_ne!(cbc[8..16], cbc[16..24]);
+    }
+
+    #[test]
+    fn cbc_first_block_depends_on_iv() {
+        let expanded_keys = expand(&[1, 2, 3, 4]);
+        let mut with_iv = vec![0u8; 8];
+        let mut with_zero_iv = vec![0u8; 8];
+
+        encrypt_cbc(&mut with_iv, &expanded_keys, CBC_IV).expect("CBC encrypt should succeed");
+        encrypt_cbc(&mut with_zero_iv, &expanded_keys, [0; 8]).expect("CBC encrypt should succeed");
+
+        assert_ne!(with_iv, with_zero_iv);
+
+        let mut ecb = vec![0u8; 8];
+        encrypt(&mut ecb, &expanded_keys).expect("encrypt should succeed");
+        assert_eq!(with_zero_iv, ecb, "a zero IV leaves the first block as ECB");
+    }
+
+    #[test]
+    fn cbc_rejects_non_multiple_of_8() {
+        let expanded_keys = expand(&[0; 4]);
+        assert!(matches!(
+            encrypt_cbc(&mut [0u8; 12], &expanded_keys, CBC_IV),
+            Err(XteaError::InvalidDataLength(12))
+        ));
+        assert!(matches!(
+            decrypt_cbc(&mut [0u8; 12], &expanded_keys, CBC_IV),
+            Err(XteaError::InvalidDataLength(12))
+        ));
+    }
+
     #[test]
     fn encrypt_rejects_non_multiple_of_8() {
         let key = [0; 4];
@@ -462,6 +678,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cached_schedule_encrypts_across_calls_like_one_pass() {
+        let key = [0x0123_4567, 0x89AB_CDEF, 0xFEDC_BA98, 0x7654_3210];
+        let schedule = expand(&key);
+        assert_eq!(schedule.len(), EXPANDED_KEY_LEN);
+        assert_eq!(schedule[0], key[0], "first round key is key[sum & 3] + 0");
+
+        let plaintext: Vec<u8> = (0..32).collect();
+        let mut one_pass = plaintext.clone();
+        encrypt(&mut one_pass, &schedule).expect("one-pass encrypt should succeed");
+
+        let mut per_block = plaintext.clone();
+        for block in per_block.chunks_mut(BLOCK_SIZE) {
+            encrypt(block, &schedule).expect("per-block encrypt should succeed");
+        }
+
+        assert_eq!(
+            per_block, one_pass,
+            "blocks are independent, so a cached schedule gives the same ciphertext"
+        );
+    }
+
     #[test]
     fn expand_const_fn() {
         // Asserts that `expand` can be evaluated at compile time.
@@ -0,0 +1,305 @@
+//! Async stream adapters that encrypt/decrypt XTEA transparently.
+//!
+//! [`XteaReader`] wraps an [`AsyncRead`] carrying ciphertext and yields
+//! plaintext; [`XteaWriter`] wraps an [`AsyncWrite`] and encrypts whatever
+//! is written through it. Both work on whole 8-byte blocks: bytes are held
+//! back until a block is complete, so the plaintext stream must be
+//! block-aligned (e.g. padded) by the time the stream ends.
+//!
+//! Enabled with the `tokio` feature.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{BLOCK_SIZE, ExpandedKey, XteaError, decrypt, encrypt};
+
+/// Bytes pulled from the inner reader per poll.
+const READ_CHUNK: usize = 1024;
+
+/// Encrypted bytes an [`XteaWriter`] buffers before pushing them to the
+/// inner writer.
+const WRITE_HIGH_WATER: usize = 8 * 1024;
+
+/// Rounds `len` down to a whole number of XTEA blocks.
+fn whole_blocks(len: usize) -> usize {
+    len - len % BLOCK_SIZE
+}
+
+fn invalid_length(len: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        XteaError::InvalidDataLength(len),
+    )
+}
+
+/// Decrypting wrapper around an [`AsyncRead`] of XTEA ciphertext.
+///
+/// Returns [`io::ErrorKind::InvalidData`] if the inner stream ends in the
+/// middle of a block.
+pub struct XteaReader<R> {
+    inner: R,
+    expanded: ExpandedKey,
+    /// `[..plain_end]` is decrypted, `[plain_end..]` is an incomplete block.
+    buffer: Vec<u8>,
+    plain_start: usize,
+    plain_end: usize,
+}
+
+impl<R> XteaReader<R> {
+    pub fn new(inner: R, expanded: ExpandedKey) -> Self {
+        XteaReader {
+            inner,
+            expanded,
+            buffer: Vec::with_capacity(READ_CHUNK),
+            plain_start: 0,
+            plain_end: 0,
+        }
+    }
+
+    /// Unwraps the adapter, discarding any buffered bytes.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for XteaReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            // Hand out already decrypted bytes first.
+            if this.plain_start < this.plain_end {
+                let available = &this.buffer[this.plain_start..this.plain_end];
+                let n = available.len().min(buf.remaining());
+                buf.put_slice(&available[..n]);
+                this.plain_start += n;
+
+                if this.plain_start == this.plain_end {
+                    this.buffer.drain(..this.plain_end);
+                    this.plain_start = 0;
+                    this.plain_end = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            // Pull more ciphertext from the inner reader.
+            let mut chunk = [0u8; READ_CHUNK];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf))?;
+
+            let filled = chunk_buf.filled();
+            if filled.is_empty() {
+                let leftover = this.buffer.len() - this.plain_end;
+                if leftover > 0 {
+                    return Poll::Ready(Err(invalid_length(leftover)));
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            this.buffer.extend_from_slice(filled);
+
+            // Decrypt every block that is now complete.
+            let whole = whole_blocks(this.buffer.len() - this.plain_end);
+            let blocks = &mut this.buffer[this.plain_end..this.plain_end + whole];
+            decrypt(blocks, &this.expanded).expect("slice is block-aligned");
+            this.plain_end += whole;
+        }
+    }
+}
+
+/// Encrypting wrapper around an [`AsyncWrite`].
+///
+/// Plaintext is buffered until a full block is available. Flushing pushes
+/// every complete block to the inner writer; shutting down fails with
+/// [`io::ErrorKind::InvalidData`] if an incomplete block is left over.
+pub struct XteaWriter<W> {
+    inner: W,
+    expanded: ExpandedKey,
+    /// Plaintext not yet forming a whole block.
+    pending: Vec<u8>,
+    /// Ciphertext waiting to be written, `[written..]` still unsent.
+    encrypted: Vec<u8>,
+    written: usize,
+}
+
+impl<W> XteaWriter<W> {
+    pub fn new(inner: W, expanded: ExpandedKey) -> Self {
+        XteaWriter {
+            inner,
+            expanded,
+            pending: Vec::with_capacity(BLOCK_SIZE),
+            encrypted: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Unwraps the adapter, discarding any buffered bytes.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: AsyncWrite + Unpin> XteaWriter<W> {
+    /// Writes all buffered ciphertext to the inner writer.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.encrypted.len() {
+            let n =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.encrypted[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+
+        self.encrypted.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for XteaWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.encrypted.len() >= WRITE_HIGH_WATER {
+            ready!(this.poll_drain(cx))?;
+        }
+
+        this.pending.extend_from_slice(buf);
+
+        let whole = whole_blocks(this.pending.len());
+        if whole > 0 {
+            let start = this.encrypted.len();
+            this.encrypted.extend_from_slice(&this.pending[..whole]);
+            encrypt(&mut this.encrypted[start..], &this.expanded).expect("slice is block-aligned");
+            this.pending.drain(..whole);
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.pending.is_empty() {
+            return Poll::Ready(Err(invalid_length(this.pending.len())));
+        }
+
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expand;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const KEY: ExpandedKey = expand(&[0x0123_4567, 0x89AB_CDEF, 0xFEDC_BA98, 0x7654_3210]);
+
+    #[tokio::test]
+    async fn writer_to_reader_roundtrip() {
+        let (client, server) = tokio::io::duplex(64);
+        let plaintext: Vec<u8> = (0..=255u8).cycle().take(8 * 300).collect();
+
+        let expected = plaintext.clone();
+        let writer = tokio::spawn(async move {
+            let mut writer = XteaWriter::new(client, KEY);
+            // Uneven chunk sizes so blocks straddle write calls.
+            for chunk in plaintext.chunks(13) {
+                writer
+                    .write_all(chunk)
+                    .await
+                    .expect("writing through the adapter should succeed");
+            }
+            writer
+                .shutdown()
+                .await
+                .expect("block-aligned stream should shut down cleanly");
+        });
+
+        let mut reader = XteaReader::new(server, KEY);
+        let mut decrypted = Vec::new();
+        reader
+            .read_to_end(&mut decrypted)
+            .await
+            .expect("reading through the adapter should succeed");
+        writer.await.expect("writer task should not panic");
+
+        assert_eq!(decrypted, expected);
+    }
+
+    #[tokio::test]
+    async fn writer_output_matches_block_encrypt() {
+        let (client, mut server) = tokio::io::duplex(64);
+        let mut writer = XteaWriter::new(client, KEY);
+        writer
+            .write_all(b"ABCDEFGH12345678")
+            .await
+            .expect("write should succeed");
+        writer.flush().await.expect("flush should succeed");
+
+        let mut expected = *b"ABCDEFGH12345678";
+        encrypt(&mut expected, &KEY).expect("16 bytes are block-aligned");
+
+        let mut ciphertext = [0u8; 16];
+        server
+            .read_exact(&mut ciphertext)
+            .await
+            .expect("ciphertext should arrive after flush");
+        assert_eq!(ciphertext, expected);
+    }
+
+    #[tokio::test]
+    async fn reader_rejects_truncated_block() {
+        let mut ciphertext = *b"ABCDEFGH";
+        encrypt(&mut ciphertext, &KEY).expect("8 bytes are block-aligned");
+
+        let mut stream = ciphertext.to_vec();
+        stream.extend_from_slice(&[1, 2, 3]);
+
+        let mut reader = XteaReader::new(stream.as_slice(), KEY);
+        let mut decrypted = Vec::new();
+        let err = reader
+            .read_to_end(&mut decrypted)
+            .await
+            .expect_err("a trailing partial block should fail");
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(decrypted, b"ABCDEFGH");
+    }
+
+    #[tokio::test]
+    async fn writer_shutdown_rejects_unaligned_plaintext() {
+        let mut writer = XteaWriter::new(Vec::new(), KEY);
+        writer
+            .write_all(b"short")
+            .await
+            .expect("buffering a partial block should succeed");
+
+        let err = writer
+            .shutdown()
+            .await
+            .expect_err("shutdown with a partial block should fail");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
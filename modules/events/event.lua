@@ -40,26 +40,31 @@ end
 ---Creates an event instance and dispatches to all registered handlers.
 ---@param ... any
 ---@return boolean success # true if all handlers returned true
+---@return integer failures # number of handlers that raised an error
 function M:trigger(...)
 	if not self.handlers or #self.handlers == 0 then
-		return true
+		return true, 0
 	end
 	return self:dispatch(self(...))
 end
 
 ---Dispatches handlers sorted by priority.  Skips those whose filter
----returns false.
+---returns false.  A handler that raises is logged and counted, and the
+---remaining handlers still run.
 ---@param eventInstance Event
 ---@return boolean success
+---@return integer failures
 function M:dispatch(eventInstance)
 	table.sort(self.handlers, function(a, b)
 		return a.priority > b.priority
 	end)
 
+	local failures = 0
 	for _, entry in ipairs(self.handlers) do
 		if not entry.filter or entry.filter(eventInstance) then
 			local ok, error = pcall(entry.handler, eventInstance)
 			if not ok then
+				failures = failures + 1
 				local source = "?"
 
 				if debug then
@@ -74,7 +79,7 @@ function M:dispatch(eventInstance)
 		end
 	end
 
-	return true
+	return true, failures
 end
 
 ---Create a new event subclass.
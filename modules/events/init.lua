@@ -6,6 +6,8 @@ require("events.network.connection")
 require("events.network.cancellable_connection")
 require("events.network.connection_begin")
 require("events.network.connection_end")
+require("events.network.connection_ready")
+require("events.network.session_resumed")
 require("events.network.raw_packet")
 require("events.network.packet")
 require("events.network.player_packet")
@@ -1,6 +1,7 @@
 ---Fired when a TCP connection is closed.
 ---@class ConnectionEndEvent : ConnectionEvent
 ---@field _connection Connection
+---@field reason string
 local M = ConnectionEvent:define()
 
 ---@class ConnectionEndEvent : ConnectionEvent
@@ -8,13 +9,20 @@ ConnectionEndEvent = M
 
 local MT = getmetatable(M)
 ---@return ConnectionEndEvent
-MT.__call = function(self, id)
+MT.__call = function(self, id, reason)
 	return setmetatable({
 		args = {
 			id,
+			reason,
 		},
 		_connection = Connection(id),
+		reason = reason,
 	}, self)
 end
 
+---@return string reason # "closed", "timeout", "io_error", "protocol_error", "decryption_error", "shutdown" or "session_lost"
+function M:getReason()
+	return self.reason
+end
+
 return M
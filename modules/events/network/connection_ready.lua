@@ -0,0 +1,10 @@
+---Fired once an accepted TCP connection is fully set up and can
+---send and receive packets.
+---@class ConnectionReadyEvent : ConnectionEvent
+---@field _connection Connection
+local M = ConnectionEvent:define()
+
+---@class ConnectionReadyEvent : ConnectionEvent
+ConnectionReadyEvent = M
+
+return M
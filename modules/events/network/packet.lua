@@ -9,6 +9,7 @@ PacketEvent = M
 
 ---@class PacketHandlerEntry
 ---@field port integer?
+---@field kind string?
 ---@field handler fun(connection: Connection, msg: IncomingMessage)
 ---@field priority integer
 
@@ -38,6 +39,26 @@ function M:onPort(port, opcode, handler, priority)
 	dirty = true
 end
 
+---Register a handler for a specific opcode sent by one kind of client
+---("status", "login" or "game"), on any port.
+---@param kind string
+---@param opcode integer
+---@param handler fun(connection: Connection, msg: IncomingMessage)
+---@param priority? integer
+function M:onKind(kind, opcode, handler, priority)
+	if not opcode_handlers[opcode] then
+		opcode_handlers[opcode] = {}
+	end
+
+	table.insert(opcode_handlers[opcode], {
+		kind = kind,
+		handler = handler,
+		priority = priority or EventPriority.NORMAL,
+	})
+
+	dirty = true
+end
+
 ---Register a handler for a specific opcode on any port.
 ---@param opcode integer
 ---@param handler fun(connection: Connection, msg: IncomingMessage)
@@ -59,6 +80,7 @@ end
 ---@param opcode integer
 ---@param connection Connection
 ---@param msg IncomingMessage
+---@return integer failures # number of handlers that raised an error
 function M:dispatch(opcode, connection, msg)
 	if dirty then
 		for _, handlers in pairs(opcode_handlers) do
@@ -71,31 +93,37 @@ function M:dispatch(opcode, connection, msg)
 
 	local list = opcode_handlers[opcode]
 	if not list then
-		return
+		return 0
 	end
 
+	local failures = 0
 	for _, entry in ipairs(list) do
-		if not entry.port or entry.port == connection:getPort() then
+		local port_matches = not entry.port or entry.port == connection:getPort()
+		local kind_matches = not entry.kind or entry.kind == connection:getClientKind()
+		if port_matches and kind_matches then
 			local ok, error = pcall(entry.handler, connection, msg)
 			if not ok then
+				failures = failures + 1
 				print(string.format("[PacketEvent] Handler error for opcode 0x%04X: %s", opcode, tostring(error)))
 			end
 		end
 	end
+
+	return failures
 end
 
 ---Receive raw decrypted data from a connection, parse the opcode,
 ---and dispatch to the registered handler(s).
 ---@param connection Connection
 ---@param raw string
+---@return integer failures # number of handlers that raised an error
 function M:trigger(connection, raw)
-	if #raw < 1 then
-		return
+	local opcode, msg = IncomingMessage.decodeOpcode(raw)
+	if not opcode then
+		return 0
 	end
 
-	local msg = IncomingMessage(raw)
-	local opcode = msg:getU8()
-	self:dispatch(opcode, connection, msg)
+	return self:dispatch(opcode, connection, msg)
 end
 
 return M
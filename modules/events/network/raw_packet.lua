@@ -2,6 +2,8 @@
 ---@class RawPacketEvent : ConnectionEvent
 ---@field _connection Connection
 ---@field data string
+---@field kind string
+---@field checksum integer?
 local M = ConnectionEvent:define()
 
 ---@class RawPacketEvent : ConnectionEvent
@@ -9,20 +11,47 @@ RawPacketEvent = M
 
 local MT = getmetatable(M)
 ---@return RawPacketEvent
-MT.__call = function(self, id, data)
+MT.__call = function(self, id, data, kind, checksum)
 	return setmetatable({
 		args = {
 			id,
 			data,
+			kind,
+			checksum,
 		},
 		_connection = Connection(id),
 		data = data,
+		kind = kind,
+		checksum = checksum,
 	}, self)
 end
 
+---Dispatches like any event, but raises once all handlers ran if any of
+---them failed, so the server can count packets game code could not
+---handle against the port's decode failure policy.
+---@param ... any
+---@return boolean success
+function M:trigger(...)
+	local success, failures = Event.trigger(self, ...)
+	if failures > 0 then
+		error(string.format("%d RawPacketEvent handler(s) failed", failures), 0)
+	end
+	return success
+end
+
 ---@return string data # raw bytes from the client
 function M:getData()
 	return self.data
 end
 
+---@return string kind # "status", "login", "game" or "unknown", resolved from the first packet
+function M:getClientKind()
+	return self.kind
+end
+
+---@return integer? checksum # adler32 checksum the packet arrived with and matched, nil when it carried none
+function M:getChecksum()
+	return self.checksum
+end
+
 return M
@@ -0,0 +1,29 @@
+---Fired when a reconnecting client resumes its previous session with a
+---resumption token, skipping the handshake and login.
+---@class SessionResumedEvent : ConnectionEvent
+---@field _connection Connection
+---@field previousId integer
+local M = ConnectionEvent:define()
+
+---@class SessionResumedEvent : ConnectionEvent
+SessionResumedEvent = M
+
+local MT = getmetatable(M)
+---@return SessionResumedEvent
+MT.__call = function(self, id, previousId)
+	return setmetatable({
+		args = {
+			id,
+			previousId,
+		},
+		_connection = Connection(id),
+		previousId = previousId,
+	}, self)
+end
+
+---@return integer previousId # Identifier of the connection the token was issued on
+function M:getPreviousId()
+	return self.previousId
+end
+
+return M
@@ -11,8 +11,14 @@ local storage = setmetatable({}, { __mode = "v" })
 ---@field _characterName string?
 ---@field _serverName string?
 ---@field _handshakeSent boolean?
+---@field _clientKind string?
 ---@field send fun(self: Connection, data: string)
+---@field sendAndClose fun(self: Connection, data: string)
 ---@field sendRaw fun(self: Connection, data: string)
+---@field setProtocolVersion fun(self: Connection, version: integer)
+---@field completeHandshake fun(self: Connection)
+---@field markAuthenticated fun(self: Connection)
+---@field issueResumptionToken fun(self: Connection): string
 ---@field close fun(self: Connection)
 local M = {}
 M.__index = M
@@ -43,6 +49,7 @@ setmetatable(M, {
 			_characterName = nil,
 			_serverName = nil,
 			_handshakeSent = nil,
+			_clientKind = nil,
 		}, M)
 
 		storage[id] = self
@@ -90,12 +97,26 @@ function M:getServerName()
 	return self._serverName
 end
 
+---@return string? kind # "status", "login", "game" or "unknown" once the first packet arrived
+function M:getClientKind()
+	return self._clientKind
+end
+
+---Sets the client kind resolved from the first packet.
+---@param kind string
+function M:setClientKind(kind)
+	self._clientKind = kind
+end
+
 ---Marks the connection as authenticated after a successful login.
 ---@param accountId integer
 ---@param sessionKey string
 ---@param characterName string?
 function M:authenticate(accountId, sessionKey, characterName)
 	self._authenticated = true
+	if self.markAuthenticated then
+		self:markAuthenticated()
+	end
 	self._accountId = accountId
 	self._sessionKey = sessionKey
 	self._characterName = characterName
@@ -16,7 +16,7 @@ IncomingMessage = M
 ---@overload fun(data: string): IncomingMessage
 local callable = setmetatable({}, {
 	__index = M,
-	__call = function(data)
+	__call = function(_, data)
 		return setmetatable({
 			_buffer = data or "",
 			_position = 1,
@@ -25,6 +25,36 @@ local callable = setmetatable({}, {
 	end,
 })
 
+---Points the message at `data` and rewinds it, so one message object can
+---decode packet after packet of a frequent type without allocating a new
+---table each time.
+---@param data string
+---@return IncomingMessage self
+function M:reset(data)
+	self._buffer = data or ""
+	self._position = 1
+	self._length = #self._buffer
+	return self
+end
+
+---Reads the leading opcode of a raw packet, so a dispatcher can route it
+---before knowing how the rest of the packet decodes.
+---@param data string
+---@return integer? opcode nil if `data` is empty
+---@return IncomingMessage? msg positioned just past the opcode
+function M.decodeOpcode(data)
+	if not data or #data < 1 then
+		return nil
+	end
+
+	local msg = setmetatable({
+		_buffer = data,
+		_position = 2,
+		_length = #data,
+	}, M)
+	return string.byte(data, 1), msg
+end
+
 ---Unsigned 8-bit integer.
 ---@return integer
 function M:getU8()
@@ -117,6 +147,42 @@ function M:getString()
 	return value
 end
 
+---Pascal-style string for client-chosen names and identifiers. Returns
+---nil and a reason, without advancing, if the string is longer than `max`
+---bytes ("too_long"), runs past the buffer ("truncated"), or contains NUL
+---or another control character ("invalid_string").
+---@param max integer
+---@return string?
+---@return string? reason
+function M:getStringSanitized(max)
+	if self._position + 1 > self._length then
+		return nil, "truncated"
+	end
+
+	local length = string.unpack("<I2", self._buffer, self._position)
+	if length > max then
+		return nil, "too_long"
+	end
+
+	local start = self._position + 2
+	if length == 0 then
+		self._position = start
+		return ""
+	end
+
+	if start + length - 1 > self._length then
+		return nil, "truncated"
+	end
+
+	local value = self._buffer:sub(start, start + length - 1)
+	if value:find("[%c]") then
+		return nil, "invalid_string"
+	end
+
+	self._position = start + length
+	return value
+end
+
 ---Little-endian 32-bit float.
 ---@return number
 function M:getFloat()
@@ -141,6 +207,20 @@ function M:getDouble()
 	return value
 end
 
+---IPv4 address in dotted form, read from its four octets in address
+---order, so 7F 00 00 01 is "127.0.0.1".
+---@return string
+function M:getIpv4()
+	local a, b, c, d = self:getU8(), self:getU8(), self:getU8(), self:getU8()
+	return string.format("%d.%d.%d.%d", a, b, c, d)
+end
+
+---Port, as a little-endian unsigned 16-bit integer.
+---@return integer
+function M:getPort()
+	return self:getU16()
+end
+
 ---Total buffer length in bytes.
 ---@return integer
 function M:getLength()
@@ -196,6 +276,51 @@ function M:readBytes(count)
 	return value
 end
 
+---Reads exactly `count` raw bytes, such as a 16-byte session token.
+---Unlike `readBytes`, a short read returns nil and does not advance.
+---@param count integer
+---@return string?
+function M:getArray(count)
+	if count < 0 or self._position + count - 1 > self._length then
+		return nil
+	end
+
+	local value = self._buffer:sub(self._position, self._position + count - 1)
+	self._position = self._position + count
+	return value
+end
+
+---Optional value behind a presence byte: 0x00 means absent and returns
+---nil, anything else is followed by the value, decoded by `read`.
+---@generic T
+---@param read fun(msg: IncomingMessage): T
+---@return T?
+function M:getOptional(read)
+	if self:getU8() == 0 then
+		return nil
+	end
+
+	return read(self)
+end
+
+---List behind a U16 item count, each item decoded by `read`. Stops early
+---if the buffer runs out, so a bogus count can't conjure up items.
+---@generic T
+---@param read fun(msg: IncomingMessage): T
+---@return T[]
+function M:getList(read)
+	local count = self:getU16()
+	local items = {}
+	for index = 1, count do
+		if self._position > self._length then
+			break
+		end
+
+		items[index] = read(self)
+	end
+	return items
+end
+
 ---Unsigned 8-bit integer without advancing.
 ---@return integer
 function M:peekU8()
@@ -29,7 +29,11 @@ RawPacketEvent:on(function(event)
 		return
 	end
 
-	PacketEvent:trigger(connection, event:getData())
+	connection:setClientKind(event:getClientKind())
+	local failures = PacketEvent:trigger(connection, event:getData())
+	if failures > 0 then
+		error(string.format("%d packet handler(s) failed", failures), 0)
+	end
 end)
 
 ---Feed raw HTTP requests into the method+path dispatcher.
@@ -83,13 +83,20 @@ function M:addBoolean(value)
 	self:addU8(value and 1 or 0)
 end
 
----Pascal-style string (U16 length prefix).
+---Pascal-style string (U16 length prefix). Nothing is written if the
+---string is too long for its prefix.
 ---@param value string
+---@return boolean true if the string was written
 function M:addString(value)
 	value = value or ""
+	if #value > 0xFFFF then
+		return false
+	end
+
 	self:addU16(#value)
 	table.insert(self._buffer, value)
 	self._length = self._length + #value
+	return true
 end
 
 ---Raw bytes appended directly.
@@ -101,6 +108,53 @@ function M:addRaw(data)
 	end
 end
 
+---Fixed-size raw bytes, such as a 16-byte session token. Nothing is
+---written unless `data` is exactly `count` bytes long.
+---@param data string
+---@param count integer
+---@return boolean true if the bytes were written
+function M:addArray(data, count)
+	if #data ~= count then
+		return false
+	end
+
+	self:addRaw(data)
+	return true
+end
+
+---Optional value behind a presence byte: 0x00 for nil, otherwise 0x01
+---followed by the value, encoded by `write`.
+---@generic T
+---@param value T?
+---@param write fun(msg: OutgoingMessage, value: T)
+function M:addOptional(value, write)
+	if value == nil then
+		self:addU8(0)
+		return
+	end
+
+	self:addU8(1)
+	write(self, value)
+end
+
+---List behind a U16 item count, each item encoded by `write`. Nothing is
+---written if there are more items than the count can hold.
+---@generic T
+---@param items T[]
+---@param write fun(msg: OutgoingMessage, item: T)
+---@return boolean true if the list was written
+function M:addList(items, write)
+	if #items > 0xFFFF then
+		return false
+	end
+
+	self:addU16(#items)
+	for _, item in ipairs(items) do
+		write(self, item)
+	end
+	return true
+end
+
 ---Little-endian 32-bit float.
 ---@param value number
 function M:addFloat(value)
@@ -115,6 +169,53 @@ function M:addDouble(value)
 	self._length = self._length + 8
 end
 
+---IPv4 address in dotted form, written as its four octets in address
+---order, so "127.0.0.1" is 7F 00 00 01. Nothing is written unless the
+---address is four dot-separated octets.
+---@param address string
+---@return boolean true if the address was written
+function M:addIpv4(address)
+	local octets = { string.match(address or "", "^(%d+)%.(%d+)%.(%d+)%.(%d+)$") }
+	if #octets ~= 4 then
+		return false
+	end
+
+	for index, octet in ipairs(octets) do
+		octets[index] = tonumber(octet)
+		if octets[index] > 255 then
+			return false
+		end
+	end
+
+	for _, octet in ipairs(octets) do
+		self:addU8(octet)
+	end
+	return true
+end
+
+---Port, as a little-endian unsigned 16-bit integer.
+---@param port integer
+function M:addPort(port)
+	self:addU16(port)
+end
+
+---Overwrites already-written bytes starting at `position` (1-based),
+---for fields such as a length that are only known once the bytes after
+---them have been added. Leaves the buffer untouched if `data` would
+---extend past its end.
+---@param position integer
+---@param data string
+---@return boolean true if the bytes were patched
+function M:patch(position, data)
+	if position < 1 or position + #data - 1 > self._length then
+		return false
+	end
+
+	local buffer = table.concat(self._buffer)
+	self._buffer = { buffer:sub(1, position - 1), data, buffer:sub(position + #data) }
+	return true
+end
+
 ---Current byte length of the buffer.
 ---@return integer
 function M:getLength()
@@ -1,10 +1,11 @@
 use suon_app::App;
 use suon_lua::LuaPlugin;
-use suon_network::NetworkPlugin;
+use suon_network::{NetworkDiagnosticsPlugin, NetworkPlugin};
 
 fn main() {
     App::new()
         .add_plugin(LuaPlugin)
-        .add_plugin(NetworkPlugin)
+        .add_plugin(NetworkPlugin::default())
+        .add_plugin(NetworkDiagnosticsPlugin::default())
         .run();
 }